@@ -0,0 +1,103 @@
+use bollard::{container::Config, ClientVersion};
+
+use super::errors::*;
+
+// Docker 1.12: healthchecks, labeled networks and volumes, everything `up` can't do without
+pub const MIN_VERSION: ClientVersion = ClientVersion {
+    major_version: 1,
+    minor_version: 24,
+};
+
+// What needs a newer API than MIN_VERSION, from the changelog of the docker API
+pub struct Requirement {
+    pub feature: &'static str,
+    pub version: ClientVersion,
+}
+
+pub const PLATFORM: Requirement = Requirement {
+    feature: "platform",
+    version: ClientVersion {
+        major_version: 1,
+        minor_version: 32,
+    },
+};
+
+pub const INIT: Requirement = Requirement {
+    feature: "init",
+    version: ClientVersion {
+        major_version: 1,
+        minor_version: 25,
+    },
+};
+
+pub const GPUS: Requirement = Requirement {
+    feature: "hostRequirements.gpu",
+    version: ClientVersion {
+        major_version: 1,
+        minor_version: 40,
+    },
+};
+
+// "1.41" as reported by the daemon
+pub fn parse(version: &str) -> Option<ClientVersion> {
+    let (major, minor) = version.trim().split_once('.')?;
+
+    Some(ClientVersion {
+        major_version: major.parse().ok()?,
+        minor_version: minor.parse().ok()?,
+    })
+}
+
+// The version to talk to the daemon: ours, or its own when it is older
+pub fn negotiate(server: &ClientVersion, client: &ClientVersion) -> Result<ClientVersion, Error> {
+    if *server < MIN_VERSION {
+        return Err(Error::UnsupportedApi(format!(
+            "the daemon API is {}, this tool needs {} or newer",
+            server, MIN_VERSION
+        )));
+    }
+
+    if server < client {
+        Ok(*server)
+    } else {
+        Ok(*client)
+    }
+}
+
+pub fn require(version: &ClientVersion, requirement: &Requirement) -> Result<(), Error> {
+    if *version < requirement.version {
+        return Err(Error::UnsupportedApi(format!(
+            "the daemon API is {}, this tool needs {} or newer for {}",
+            version, requirement.version, requirement.feature
+        )));
+    }
+
+    Ok(())
+}
+
+// Checks the options of the container are known to the daemon, instead of it failing or
+// ignoring them on create
+pub fn check_container_config(
+    version: &ClientVersion,
+    config: &Config<String>,
+) -> Result<(), Error> {
+    let host_config = match config.host_config.as_ref() {
+        Some(host_config) => host_config,
+        None => return Ok(()),
+    };
+
+    if host_config.init.is_some() {
+        require(version, &INIT)?;
+    }
+
+    if host_config
+        .device_requests
+        .as_ref()
+        .map(|requests| !requests.is_empty())
+        .unwrap_or(false)
+    {
+        require(version, &GPUS)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use bollard::container::Config;
+use bollard::service::{DeviceRequest, HostConfig};
+use bollard::ClientVersion;
+
+use super::api_version::*;
+
+fn version(minor_version: usize) -> ClientVersion {
+    ClientVersion {
+        major_version: 1,
+        minor_version,
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse("1.41"), Some(version(41)));
+    assert_eq!(parse(" 1.24\n"), Some(version(24)));
+    assert_eq!(parse("1"), None);
+    assert_eq!(parse("v1.x"), None);
+}
+
+#[test]
+fn test_negotiate() {
+    assert_eq!(negotiate(&version(39), &version(40)).unwrap(), version(39));
+    assert_eq!(negotiate(&version(43), &version(40)).unwrap(), version(40));
+
+    let err = negotiate(&version(22), &version(40))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("1.22"));
+    assert!(err.contains("1.24 or newer"));
+}
+
+#[test]
+fn test_require() {
+    assert!(require(&version(40), &GPUS).is_ok());
+
+    let err = require(&version(39), &GPUS).unwrap_err().to_string();
+    assert!(err.contains(
+        "the daemon API is 1.39, this tool needs 1.40 or newer for hostRequirements.gpu"
+    ));
+}
+
+#[test]
+fn test_check_container_config() {
+    let config = Config {
+        host_config: Some(HostConfig {
+            init: Some(true),
+            device_requests: Some(vec![DeviceRequest::default()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert!(check_container_config(&version(40), &config).is_ok());
+    assert!(check_container_config(&version(39), &config).is_err());
+    assert!(check_container_config(&version(24), &Config::default()).is_ok());
+}
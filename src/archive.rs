@@ -0,0 +1,323 @@
+use futures::{channel::mpsc, executor, SinkExt, StreamExt};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use super::errors::*;
+
+// Reads the chunks of an async stream from a blocking task, so an archive is unpacked while it
+// downloads instead of being buffered whole
+pub struct ChunkReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChunkReader {
+    pub fn new(receiver: mpsc::Receiver<Vec<u8>>) -> Self {
+        ChunkReader {
+            receiver,
+            chunk: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match executor::block_on(self.receiver.next()) {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+// Sends what a blocking task writes to an async stream, in chunks, so an archive is uploaded
+// while it is built instead of being buffered whole
+pub struct ChunkWriter {
+    sender: mpsc::Sender<Vec<u8>>,
+    chunk: Vec<u8>,
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl ChunkWriter {
+    pub fn new(sender: mpsc::Sender<Vec<u8>>) -> Self {
+        ChunkWriter {
+            sender,
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+        executor::block_on(self.sender.send(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "The upload stopped"))
+    }
+}
+
+// One line of a .dockerignore or .gitignore
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    // with a slash, matched from the root, otherwise against the name at any depth
+    anchored: bool,
+}
+
+// Patterns of the .dockerignore and .gitignore at the root of a folder, the last matching one
+// wins. Ignoring a folder ignores all it holds
+#[derive(Default)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    pub fn load(root: &Path) -> Self {
+        let mut rules = IgnoreRules::default();
+        for file in [".gitignore", ".dockerignore"].iter() {
+            if let Ok(contents) = fs::read_to_string(root.join(file)) {
+                rules.add(&contents);
+            }
+        }
+
+        rules
+    }
+
+    pub fn add(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let dir_only = line.ends_with('/');
+            let line = line.trim_end_matches('/');
+            let anchored = line.contains('/');
+            let glob = line.trim_start_matches('/').to_string();
+            if glob.is_empty() {
+                continue;
+            }
+
+            self.patterns.push(IgnorePattern {
+                glob,
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+    }
+
+    // `path` is relative to the root, with `/` separators
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        let mut ignored = false;
+        for pattern in self.patterns.iter() {
+            if pattern.dir_only && !is_dir {
+                continue;
+            }
+            let text = if pattern.anchored { path } else { name };
+            if glob_match(pattern.glob.as_bytes(), text.as_bytes()) {
+                ignored = !pattern.negated;
+            }
+        }
+
+        ignored
+    }
+}
+
+// `*` and `?` stop at a `/`, `**` does not
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = pattern[2..].strip_prefix(b"/").unwrap_or(&pattern[2..]);
+            (0..=text.len()).any(|i| {
+                (i == 0 || text[i - 1] == b'/' || rest.is_empty()) && glob_match(rest, &text[i..])
+            })
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            let end = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=end).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some(b'?') => !text.is_empty() && text[0] != b'/' && glob_match(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+// Archives the content of `root` into `writer`, without what its ignore files list. Symlinks
+// are kept as such. The writer is dropped at the end, closing a ChunkWriter stream
+pub fn pack_workspace<W: Write>(writer: W, root: &Path) -> Result<(), Error> {
+    let rules = IgnoreRules::load(root);
+
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    pack_dir(&mut builder, root, Path::new(""), &rules)?;
+
+    let mut writer = builder.into_inner().map_err(other)?;
+    writer.flush().map_err(other)
+}
+
+fn pack_dir<W: Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    relative: &Path,
+    rules: &IgnoreRules,
+) -> Result<(), Error> {
+    let mut entries = fs::read_dir(root.join(relative))
+        .map_err(other)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(other)?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = relative.join(entry.file_name());
+        let is_dir = entry.file_type().map_err(other)?.is_dir();
+        let name = path.to_string_lossy().replace('\\', "/");
+        if rules.is_ignored(&name, is_dir) {
+            continue;
+        }
+
+        builder
+            .append_path_with_name(entry.path(), &path)
+            .map_err(other)?;
+        if is_dir {
+            pack_dir(builder, root, &path, rules)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn other(err: impl std::fmt::Display) -> Error {
+    Error::Other(err.to_string())
+}
+
+// The path of an archive entry without its first component, None when it would leave the
+// destination
+fn strip_first(path: &Path) -> Option<PathBuf> {
+    let mut components = path.components().skip_while(|c| *c == Component::CurDir);
+    components.next()?;
+
+    let mut stripped = PathBuf::new();
+    for component in components {
+        match component {
+            Component::Normal(part) => stripped.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    Some(stripped)
+}
+
+// Unpacks an archive whose entries are all in one folder, the one `docker cp` gives for a
+// directory, into `dst`. Entries escaping `dst`, by their path, a link or a symlink already
+// unpacked, are refused
+pub fn unpack_stripped<R: Read>(reader: R, dst: &Path) -> Result<(), Error> {
+    let canon_dst = dst.canonicalize().map_err(other)?;
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(other)? {
+        let mut entry = entry.map_err(other)?;
+        let entry_path = entry.path().map_err(other)?.into_owned();
+
+        let path = strip_first(&entry_path).ok_or_else(|| {
+            Error::Other(format!(
+                "Refusing to unpack {} outside of the workspace",
+                entry_path.display()
+            ))
+        })?;
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        let target = dst.join(&path);
+
+        // the parent may be a symlink unpacked before
+        let parent = target.parent().unwrap_or(dst);
+        fs::create_dir_all(parent).map_err(other)?;
+        if !parent
+            .canonicalize()
+            .map_err(other)?
+            .starts_with(&canon_dst)
+        {
+            return Err(Error::Other(format!(
+                "Refusing to unpack {} outside of the workspace",
+                entry_path.display()
+            )));
+        }
+
+        // never write through a symlink left at the target
+        if let Ok(meta) = fs::symlink_metadata(&target) {
+            if meta.file_type().is_symlink() {
+                fs::remove_file(&target).map_err(other)?;
+            }
+        }
+
+        if entry.header().entry_type().is_hard_link() {
+            let link = entry
+                .link_name()
+                .map_err(other)?
+                .and_then(|link| strip_first(&link))
+                .filter(|link| !link.as_os_str().is_empty())
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "Refusing to link {} outside of the workspace",
+                        entry_path.display()
+                    ))
+                })?;
+            let source = dst.join(link);
+            if !source
+                .canonicalize()
+                .map_err(other)?
+                .starts_with(&canon_dst)
+            {
+                return Err(Error::Other(format!(
+                    "Refusing to link {} outside of the workspace",
+                    entry_path.display()
+                )));
+            }
+            if target.exists() {
+                fs::remove_file(&target).map_err(other)?;
+            }
+            fs::hard_link(source, &target).map_err(other)?;
+            continue;
+        }
+
+        entry.unpack(&target).map_err(other)?;
+    }
+
+    Ok(())
+}
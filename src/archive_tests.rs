@@ -0,0 +1,170 @@
+use futures::{SinkExt, StreamExt};
+use std::path::PathBuf;
+
+use super::archive::*;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("devcontainers-rs-archive-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// An entry with the name as is, the tar builder refusing `..`
+fn append(
+    builder: &mut tar::Builder<Vec<u8>>,
+    name: &str,
+    kind: tar::EntryType,
+    link: Option<&str>,
+    data: &[u8],
+) {
+    let mut header = tar::Header::new_old();
+    header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+    header.set_entry_type(kind);
+    if let Some(link) = link {
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+    }
+    header.set_mode(0o644);
+    header.set_size(data.len() as u64);
+    header.set_cksum();
+    builder.append(&header, data).unwrap();
+}
+
+fn archive(entries: &[(&str, tar::EntryType, Option<&str>, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(vec![]);
+    for (name, kind, link, data) in entries {
+        append(&mut builder, name, *kind, *link, data);
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_unpack_stripped() {
+    let dst = temp_dir("unpack");
+
+    let data = archive(&[
+        ("workspace/", tar::EntryType::Directory, None, b""),
+        ("workspace/a.txt", tar::EntryType::Regular, None, b"a"),
+        ("workspace/dir/b.txt", tar::EntryType::Regular, None, b"b"),
+        (
+            "workspace/c.txt",
+            tar::EntryType::Link,
+            Some("workspace/a.txt"),
+            b"",
+        ),
+    ]);
+    unpack_stripped(data.as_slice(), &dst).unwrap();
+
+    assert_eq!(std::fs::read(dst.join("a.txt")).unwrap(), b"a");
+    assert_eq!(std::fs::read(dst.join("dir/b.txt")).unwrap(), b"b");
+    assert_eq!(std::fs::read(dst.join("c.txt")).unwrap(), b"a");
+}
+
+#[test]
+fn test_unpack_stripped_outside() {
+    let root = temp_dir("outside");
+    let dst = root.join("workspace");
+    std::fs::create_dir_all(&dst).unwrap();
+    let outside = root.join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+
+    let data = archive(&[("workspace/../evil", tar::EntryType::Regular, None, b"x")]);
+    assert!(unpack_stripped(data.as_slice(), &dst).is_err());
+    assert!(!root.join("evil").exists());
+
+    let link = outside.display().to_string();
+    let data = archive(&[
+        (
+            "workspace/link",
+            tar::EntryType::Symlink,
+            Some(link.as_str()),
+            b"",
+        ),
+        ("workspace/link/evil", tar::EntryType::Regular, None, b"x"),
+    ]);
+    assert!(unpack_stripped(data.as_slice(), &dst).is_err());
+    assert!(!outside.join("evil").exists());
+
+    let data = archive(&[(
+        "workspace/hard",
+        tar::EntryType::Link,
+        Some("/etc/hostname"),
+        b"",
+    )]);
+    assert!(unpack_stripped(data.as_slice(), &dst).is_err());
+    assert!(!dst.join("hard").exists());
+}
+
+#[tokio::test]
+async fn test_chunk_reader() {
+    let dst = temp_dir("chunks");
+    let data = archive(&[("workspace/a.txt", tar::EntryType::Regular, None, b"chunked")]);
+
+    let (mut sender, receiver) = futures::channel::mpsc::channel(1);
+    let unpack = {
+        let dst = dst.clone();
+        tokio::task::spawn_blocking(move || unpack_stripped(ChunkReader::new(receiver), &dst))
+    };
+    // the reader is dropped at the end of the archive, before its padding
+    for chunk in data.chunks(100) {
+        if sender.send(chunk.to_vec()).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    unpack.await.unwrap().unwrap();
+    assert_eq!(std::fs::read(dst.join("a.txt")).unwrap(), b"chunked");
+}
+
+#[test]
+fn test_ignore_rules() {
+    let mut rules = IgnoreRules::default();
+    rules.add("# comment\n*.log\n/build\ntarget/\ndocs/**/*.md\n!keep.log\n");
+
+    assert!(rules.is_ignored("app.log", false));
+    assert!(rules.is_ignored("src/app.log", false));
+    assert!(!rules.is_ignored("keep.log", false));
+    assert!(rules.is_ignored("build", true));
+    assert!(!rules.is_ignored("src/build", true));
+    assert!(rules.is_ignored("target", true));
+    assert!(rules.is_ignored("src/target", true));
+    assert!(!rules.is_ignored("target", false));
+    assert!(rules.is_ignored("docs/a.md", false));
+    assert!(rules.is_ignored("docs/guide/a.md", false));
+    assert!(!rules.is_ignored("docs/a.txt", false));
+    assert!(!rules.is_ignored("src/main.rs", false));
+}
+
+#[tokio::test]
+async fn test_pack_workspace() {
+    let src = temp_dir("pack");
+    std::fs::create_dir_all(src.join("src")).unwrap();
+    std::fs::create_dir_all(src.join("target/debug")).unwrap();
+    std::fs::write(src.join("src/main.rs"), b"main").unwrap();
+    std::fs::write(src.join("target/debug/app"), b"app").unwrap();
+    std::fs::write(src.join("debug.log"), b"log").unwrap();
+    std::fs::write(src.join(".gitignore"), b"target/\n").unwrap();
+    std::fs::write(src.join(".dockerignore"), b"*.log\n").unwrap();
+
+    let (sender, receiver) = futures::channel::mpsc::channel(1);
+    let pack = {
+        let src = src.clone();
+        tokio::task::spawn_blocking(move || pack_workspace(ChunkWriter::new(sender), &src))
+    };
+    let data: Vec<u8> = receiver.concat().await;
+    pack.await.unwrap().unwrap();
+
+    let mut archive = tar::Archive::new(data.as_slice());
+    let mut names: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+        .collect();
+    names.sort();
+
+    assert_eq!(
+        names,
+        vec![".dockerignore", ".gitignore", "src", "src/main.rs"]
+    );
+}
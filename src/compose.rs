@@ -0,0 +1,1943 @@
+use bollard::errors::Error as DockerError;
+use bollard::{
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, NetworkingConfig,
+        RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
+    },
+    image::{BuildImageOptions, RemoveImageOptions},
+    network::{
+        ConnectNetworkOptions, CreateNetworkOptions, InspectNetworkOptions, ListNetworksOptions,
+    },
+    service::{
+        ContainerSummaryInner, EndpointSettings, HealthConfig, HostConfig, Mount, MountTypeEnum,
+        PortBinding,
+    },
+    volume::{CreateVolumeOptions, ListVolumesOptions},
+    Docker,
+};
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use futures::{future, StreamExt};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use super::dockerfile;
+use super::errors::*;
+use super::health::Condition;
+use super::host_requirements::parse_size;
+use super::run_args;
+use super::secrets::parse_dotenv;
+
+// The labels docker-compose puts on what it creates, so both find the same containers
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const SERVICE_LABEL: &str = "com.docker.compose.service";
+pub const ONEOFF_LABEL: &str = "com.docker.compose.oneoff";
+pub const CONTAINER_NUMBER_LABEL: &str = "com.docker.compose.container-number";
+pub const NETWORK_LABEL: &str = "com.docker.compose.network";
+pub const VOLUME_LABEL: &str = "com.docker.compose.volume";
+pub const WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+pub const CONFIG_FILES_LABEL: &str = "com.docker.compose.project.config_files";
+
+// The hash of a service config as the native engine computes it. docker-compose hashes its own
+// model into `com.docker.compose.config-hash`, the two never match
+pub const NATIVE_CONFIG_HASH_LABEL: &str = "devcontainer_compose_config_hash";
+
+// Lists of the later files replacing the earlier ones instead of being appended to them
+const REPLACED_LISTS: &[&str] = &["command", "entrypoint", "test"];
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ComposeFile {
+    #[serde(default)]
+    pub services: BTreeMap<String, ComposeService>,
+    #[serde(default)]
+    pub networks: BTreeMap<String, Option<Resource>>,
+    #[serde(default)]
+    pub volumes: BTreeMap<String, Option<Resource>>,
+}
+
+// An entry of the top level `networks` or `volumes`
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Resource {
+    #[serde(default)]
+    pub external: bool,
+    pub name: Option<String>,
+    pub driver: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ComposeService {
+    pub image: Option<String>,
+    pub build: Option<BuildSpec>,
+    pub command: Option<StringOrList>,
+    pub entrypoint: Option<StringOrList>,
+    pub environment: Option<MapOrList>,
+    pub env_file: Option<StringOrList>,
+    #[serde(default)]
+    pub ports: Vec<PortSpec>,
+    #[serde(default)]
+    pub volumes: Vec<VolumeSpec>,
+    pub depends_on: Option<DependsOn>,
+    pub working_dir: Option<String>,
+    pub user: Option<String>,
+    pub hostname: Option<String>,
+    pub labels: Option<MapOrList>,
+    pub privileged: Option<bool>,
+    #[serde(default)]
+    pub cap_add: Vec<String>,
+    #[serde(default)]
+    pub security_opt: Vec<String>,
+    pub init: Option<bool>,
+    #[serde(default)]
+    pub extra_hosts: Vec<String>,
+    pub restart: Option<String>,
+    pub network_mode: Option<String>,
+    pub networks: Option<ServiceNetworks>,
+    pub shm_size: Option<Value>,
+    pub tty: Option<bool>,
+    pub stdin_open: Option<bool>,
+    pub healthcheck: Option<Healthcheck>,
+    pub container_name: Option<String>,
+    pub platform: Option<String>,
+    // only started when one of them is active, or when asked for by name
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum StringOrList {
+    String(String),
+    List(Vec<String>),
+}
+
+impl StringOrList {
+    // A string is split like a shell would
+    pub fn to_args(&self) -> Result<Vec<String>, Error> {
+        match self {
+            StringOrList::String(s) => split_command(s),
+            StringOrList::List(list) => Ok(list.clone()),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            StringOrList::String(s) => vec![s.clone()],
+            StringOrList::List(list) => list.clone(),
+        }
+    }
+}
+
+// `environment` and `labels`: "KEY: value" or "KEY=value" entries. A key without a value is None
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum MapOrList {
+    Map(BTreeMap<String, Option<Value>>),
+    List(Vec<String>),
+}
+
+impl MapOrList {
+    pub fn to_map(&self) -> BTreeMap<String, Option<String>> {
+        match self {
+            MapOrList::Map(map) => map
+                .iter()
+                .map(|(key, value)| (key.clone(), value.as_ref().and_then(scalar_string)))
+                .collect(),
+            MapOrList::List(list) => list
+                .iter()
+                .map(|entry| match entry.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (entry.clone(), None),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BuildSpec {
+    Context(String),
+    Full {
+        context: Option<String>,
+        dockerfile: Option<String>,
+        args: Option<MapOrList>,
+        target: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PortSpec {
+    Long {
+        target: u16,
+        published: Option<Value>,
+        host_ip: Option<String>,
+        protocol: Option<String>,
+    },
+    // "8080:80", "127.0.0.1:8080:80/udp", or a number
+    Short(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortMapping {
+    pub host_ip: Option<String>,
+    pub host_port: Option<String>,
+    pub container_port: u16,
+    pub protocol: String,
+}
+
+impl PortSpec {
+    pub fn mapping(&self) -> Result<PortMapping, Error> {
+        match self {
+            PortSpec::Long {
+                target,
+                published,
+                host_ip,
+                protocol,
+            } => Ok(PortMapping {
+                host_ip: host_ip.clone(),
+                host_port: published.as_ref().and_then(scalar_string),
+                container_port: *target,
+                protocol: protocol.clone().unwrap_or_else(|| "tcp".to_string()),
+            }),
+            PortSpec::Short(value) => {
+                let spec = scalar_string(value).unwrap_or_default();
+                parse_port(&spec)
+            }
+        }
+    }
+}
+
+fn parse_port(spec: &str) -> Result<PortMapping, Error> {
+    let invalid = |reason: &str| Error::InvalidConfig(format!("port '{}': {}", spec, reason));
+
+    let (ports, protocol) = match spec.rsplit_once('/') {
+        Some((ports, protocol)) => (ports, protocol.to_string()),
+        None => (spec, "tcp".to_string()),
+    };
+
+    // an IPv6 host address is in brackets
+    let (host_ip, rest) = match ports.strip_prefix('[') {
+        Some(rest) => {
+            let (ip, rest) = rest
+                .split_once("]:")
+                .ok_or_else(|| invalid("unterminated host address"))?;
+            (Some(ip.to_string()), rest)
+        }
+        None => (None, ports),
+    };
+
+    let parts: Vec<&str> = rest.split(':').collect();
+    let (host_ip, host_port, container_port) = match (host_ip, parts.as_slice()) {
+        (None, [container]) => (None, None, *container),
+        (None, [host, container]) => (None, Some(*host), *container),
+        (None, [ip, host, container]) => (Some(ip.to_string()), Some(*host), *container),
+        (Some(ip), [host, container]) => (Some(ip), Some(*host), *container),
+        _ => return Err(invalid("unexpected format")),
+    };
+
+    if container_port.contains('-') || host_port.map(|p| p.contains('-')).unwrap_or(false) {
+        return Err(invalid("port ranges are not supported"));
+    }
+
+    Ok(PortMapping {
+        host_ip,
+        host_port: host_port.filter(|p| !p.is_empty()).map(|p| p.to_string()),
+        container_port: container_port
+            .parse()
+            .map_err(|_| invalid("invalid container port"))?,
+        protocol,
+    })
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum VolumeSpec {
+    Long {
+        #[serde(rename = "type")]
+        typ: String,
+        source: Option<String>,
+        target: String,
+        #[serde(default)]
+        read_only: bool,
+    },
+    // "./src:/src:ro", "data:/var/lib/data", or only the target of an anonymous volume
+    Short(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeMount {
+    pub typ: MountTypeEnum,
+    pub source: Option<String>,
+    pub target: String,
+    pub read_only: bool,
+}
+
+impl VolumeSpec {
+    pub fn mount(&self) -> Result<VolumeMount, Error> {
+        match self {
+            VolumeSpec::Long {
+                typ,
+                source,
+                target,
+                read_only,
+            } => {
+                let typ = match typ.as_str() {
+                    "bind" => MountTypeEnum::BIND,
+                    "volume" => MountTypeEnum::VOLUME,
+                    "tmpfs" => MountTypeEnum::TMPFS,
+                    other => {
+                        return Err(Error::InvalidConfig(format!(
+                            "volume type '{}' is not supported",
+                            other
+                        )))
+                    }
+                };
+                Ok(VolumeMount {
+                    typ,
+                    source: source.clone(),
+                    target: target.clone(),
+                    read_only: *read_only,
+                })
+            }
+            VolumeSpec::Short(spec) => {
+                let parts: Vec<&str> = spec.split(':').collect();
+                let (source, target, mode) = match parts.as_slice() {
+                    [target] => (None, *target, ""),
+                    [source, target] => (Some(*source), *target, ""),
+                    [source, target, mode] => (Some(*source), *target, *mode),
+                    _ => {
+                        return Err(Error::InvalidConfig(format!(
+                            "volume '{}': unexpected format",
+                            spec
+                        )))
+                    }
+                };
+                let typ = match source {
+                    Some(source) if is_path(source) => MountTypeEnum::BIND,
+                    _ => MountTypeEnum::VOLUME,
+                };
+
+                Ok(VolumeMount {
+                    typ,
+                    source: source.map(|s| s.to_string()),
+                    target: target.to_string(),
+                    read_only: mode.split(',').any(|m| m == "ro"),
+                })
+            }
+        }
+    }
+}
+
+// The short syntax of a bind or volume mount: "source:target[:ro]"
+pub fn short_volume(mount: &Mount) -> Option<String> {
+    match mount.typ {
+        Some(MountTypeEnum::BIND) | Some(MountTypeEnum::VOLUME) | None => {}
+        _ => return None,
+    }
+
+    let mut volume = format!("{}:{}", mount.source.as_ref()?, mount.target.as_ref()?);
+    if mount.read_only == Some(true) {
+        volume.push_str(":ro");
+    }
+
+    Some(volume)
+}
+
+fn is_path(source: &str) -> bool {
+    source.starts_with('.') || source.starts_with('/') || source.starts_with('~')
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum DependsOn {
+    List(Vec<String>),
+    Map(BTreeMap<String, DependsOnCondition>),
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DependsOnCondition {
+    pub condition: Option<String>,
+}
+
+impl DependsOn {
+    pub fn services(&self) -> Vec<String> {
+        match self {
+            DependsOn::List(list) => list.clone(),
+            DependsOn::Map(map) => map.keys().cloned().collect(),
+        }
+    }
+
+    // The services with what they must reach before the dependent one can be used. The short
+    // syntax only asks for them to be started
+    pub fn conditions(&self) -> Vec<(String, Condition)> {
+        match self {
+            DependsOn::List(list) => list
+                .iter()
+                .map(|service| (service.clone(), Condition::Started))
+                .collect(),
+            DependsOn::Map(map) => map
+                .iter()
+                .map(|(service, dependency)| {
+                    let condition = match dependency.condition.as_deref() {
+                        Some("service_healthy") => Condition::Healthy,
+                        Some("service_completed_successfully") => Condition::CompletedSuccessfully,
+                        _ => Condition::Started,
+                    };
+                    (service.clone(), condition)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ServiceNetworks {
+    List(Vec<String>),
+    Map(BTreeMap<String, Option<ServiceNetwork>>),
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct ServiceNetwork {
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl ServiceNetworks {
+    // the networks of the service, with their extra aliases
+    pub fn entries(&self) -> Vec<(String, Vec<String>)> {
+        match self {
+            ServiceNetworks::List(list) => list.iter().map(|n| (n.clone(), vec![])).collect(),
+            ServiceNetworks::Map(map) => map
+                .iter()
+                .map(|(name, network)| {
+                    let aliases = network.as_ref().map(|n| n.aliases.clone());
+                    (name.clone(), aliases.unwrap_or_default())
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Healthcheck {
+    pub test: Option<StringOrList>,
+    pub interval: Option<String>,
+    pub timeout: Option<String>,
+    pub retries: Option<i64>,
+    pub start_period: Option<String>,
+    #[serde(default)]
+    pub disable: bool,
+}
+
+impl Healthcheck {
+    pub fn to_config(&self) -> Result<HealthConfig, Error> {
+        if self.disable {
+            return Ok(HealthConfig {
+                test: Some(vec!["NONE".to_string()]),
+                ..Default::default()
+            });
+        }
+
+        let duration = |value: &Option<String>| value.as_deref().map(parse_duration).transpose();
+
+        Ok(HealthConfig {
+            test: self.test.as_ref().map(|test| match test {
+                StringOrList::String(s) => vec!["CMD-SHELL".to_string(), s.clone()],
+                StringOrList::List(list) => list.clone(),
+            }),
+            interval: duration(&self.interval)?,
+            timeout: duration(&self.timeout)?,
+            retries: self.retries,
+            start_period: duration(&self.start_period)?,
+        })
+    }
+}
+
+// "1m30s", "10s" or "500ms" -> nanoseconds
+pub fn parse_duration(duration: &str) -> Result<i64, Error> {
+    let invalid = || Error::InvalidConfig(format!("Invalid duration: '{}'", duration));
+
+    let mut total = 0.0;
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let multiplier = match unit {
+            "h" => 3_600_000_000_000.0,
+            "m" => 60_000_000_000.0,
+            "s" => 1_000_000_000.0,
+            "ms" => 1_000_000.0,
+            "us" => 1_000.0,
+            "ns" => 1.0,
+            _ => return Err(invalid()),
+        };
+
+        total += number * multiplier;
+        rest = tail;
+    }
+
+    Ok(total as i64)
+}
+
+// Splits a command like a shell would: words, quotes and backslashes
+pub fn split_command(command: &str) -> Result<Vec<String>, Error> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(unterminated(command)),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if c == '"' || c == '\\' || c == '$' => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(unterminated(command)),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(unterminated(command)),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    current.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(arg) = current {
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+fn unterminated(command: &str) -> Error {
+    Error::InvalidConfig(format!("unterminated quote in '{}'", command))
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        other => serde_yaml::to_string(other).ok(),
+    }
+}
+
+// `${VAR}`, `${VAR:-default}`, `${VAR-default}`, `${VAR:?error}`, `${VAR:+other}` and `$VAR`
+// replaced by their value, `$$` by `$`. Unset variables without a default are empty
+pub fn interpolate(text: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+
+        if let Some(tail) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('{') {
+            let end = tail.find('}').ok_or_else(|| {
+                Error::InvalidConfig(format!("unterminated variable in '{}'", text))
+            })?;
+            result.push_str(&expand(&tail[..end], lookup)?);
+            rest = &tail[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            if end == 0 {
+                result.push('$');
+            } else {
+                result.push_str(&expand(&rest[..end], lookup)?);
+            }
+            rest = &rest[end..];
+        }
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+fn expand(expression: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let end = expression
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(expression.len());
+    let (name, modifier) = expression.split_at(end);
+
+    let value = lookup(name);
+    let set = value.is_some();
+    let non_empty = value.as_deref().map(|v| !v.is_empty()).unwrap_or(false);
+    let value = value.unwrap_or_default();
+
+    match modifier {
+        "" => {
+            if !set {
+                warn!("The {} variable is not set, using an empty string", name);
+            }
+            Ok(value)
+        }
+        m if m.starts_with(":-") => Ok(if non_empty { value } else { m[2..].to_string() }),
+        m if m.starts_with('-') => Ok(if set { value } else { m[1..].to_string() }),
+        m if m.starts_with(":+") => Ok(if non_empty {
+            m[2..].to_string()
+        } else {
+            String::new()
+        }),
+        m if m.starts_with('+') => Ok(if set {
+            m[1..].to_string()
+        } else {
+            String::new()
+        }),
+        m if m.starts_with(":?") && !non_empty => Err(missing_variable(name, &m[2..])),
+        m if m.starts_with('?') && !set => Err(missing_variable(name, &m[1..])),
+        m if m.starts_with(":?") || m.starts_with('?') => Ok(value),
+        _ => Err(Error::InvalidConfig(format!(
+            "invalid variable: '${{{}}}'",
+            expression
+        ))),
+    }
+}
+
+fn missing_variable(name: &str, message: &str) -> Error {
+    Error::InvalidConfig(format!(
+        "required variable {} is missing a value: {}",
+        name, message
+    ))
+}
+
+pub fn interpolate_value(
+    value: &mut Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), Error> {
+    match value {
+        Value::String(s) => *s = interpolate(s, lookup)?,
+        Value::Sequence(items) => {
+            for item in items {
+                interpolate_value(item, lookup)?;
+            }
+        }
+        Value::Mapping(map) => {
+            for (_, item) in map.iter_mut() {
+                interpolate_value(item, lookup)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// Puts `other` on top of `base`, like docker-compose does with several -f files: mappings are
+// merged, lists appended, other values replaced
+pub fn merge(base: &mut Value, other: Value, key: &str) {
+    // `build: .` is short for `build: { context: . }`, the override may only add args to it
+    if let (Value::String(context), Value::Mapping(_), "build") = (&*base, &other, key) {
+        let mut full = serde_yaml::Mapping::new();
+        full.insert(Value::from("context"), Value::from(context.as_str()));
+        *base = Value::Mapping(full);
+    }
+
+    match (base, other) {
+        (Value::Mapping(base), Value::Mapping(other)) => {
+            for (k, v) in other {
+                let name = k.as_str().unwrap_or_default().to_string();
+                match base.get_mut(&k) {
+                    Some(existing) => merge(existing, v, &name),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(other)) if !REPLACED_LISTS.contains(&key) => {
+            for v in other {
+                if !base.contains(&v) {
+                    base.push(v);
+                }
+            }
+        }
+        (base, other) => *base = other,
+    }
+}
+
+struct ComposeSource {
+    name: String,
+    content: String,
+    value: Value,
+}
+
+// The compose files merged as plain YAML, what any compose engine reads, without the model of the
+// native one
+pub struct ComposeFiles {
+    pub files: Vec<PathBuf>,
+    pub merged: Value,
+    sources: Vec<ComposeSource>,
+}
+
+impl ComposeFiles {
+    pub fn load(files: &[PathBuf], lookup: &dyn Fn(&str) -> Option<String>) -> Result<Self, Error> {
+        let contents = files
+            .iter()
+            .map(|file| {
+                std::fs::read_to_string(file).map_err(|err| match err.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        Error::InvalidConfig(format!("Compose file not found: {}", file.display()))
+                    }
+                    _ => Error::InvalidConfig(format!("{}: {}", file.display(), err)),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::parse(files.to_vec(), &contents, lookup)
+    }
+
+    pub fn parse(
+        files: Vec<PathBuf>,
+        contents: &[String],
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, Error> {
+        let mut merged = Value::Null;
+        let mut sources = vec![];
+
+        for (idx, content) in contents.iter().enumerate() {
+            let name = files
+                .get(idx)
+                .map(|f| f.display().to_string())
+                .unwrap_or_else(|| format!("compose file {}", idx + 1));
+
+            let mut value: Value = serde_yaml::from_str(content)
+                .map_err(|err| Error::InvalidConfig(format!("{}: {}", name, err)))?;
+            interpolate_value(&mut value, lookup)
+                .map_err(|err| Error::InvalidConfig(format!("{}: {}", name, err)))?;
+
+            match &value {
+                Value::Null => continue,
+                Value::Mapping(mapping) => {
+                    let services = mapping.get(&Value::from("services"));
+                    if !matches!(services, None | Some(Value::Mapping(_)) | Some(Value::Null)) {
+                        return Err(Error::InvalidConfig(format!(
+                            "{}: services must be a mapping",
+                            name
+                        )));
+                    }
+                }
+                _ => {
+                    return Err(Error::InvalidConfig(format!(
+                        "{}: the top level must be a mapping",
+                        name
+                    )))
+                }
+            }
+
+            merge(&mut merged, value.clone(), "");
+            sources.push(ComposeSource {
+                name,
+                content: content.clone(),
+                value,
+            });
+        }
+
+        Ok(ComposeFiles {
+            files,
+            merged,
+            sources,
+        })
+    }
+
+    // `version` of the last file setting it, "3.8" or sometimes a number
+    pub fn version(&self) -> Option<String> {
+        match self.merged.get("version")? {
+            Value::String(version) => Some(version.clone()),
+            Value::Number(version) => Some(version.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn services(&self) -> Vec<String> {
+        match self.merged.get("services") {
+            Some(Value::Mapping(services)) => services
+                .iter()
+                .filter_map(|(name, _)| name.as_str().map(|name| name.to_string()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    // `image` of the services that are not built
+    pub fn images(&self) -> Vec<String> {
+        let mut images: Vec<String> = vec![];
+        if let Some(Value::Mapping(services)) = self.merged.get("services") {
+            for (_, service) in services.iter() {
+                if service.get("build").is_some() {
+                    continue;
+                }
+                if let Some(image) = service.get("image").and_then(|i| i.as_str()) {
+                    if !images.iter().any(|i| i == image) {
+                        images.push(image.to_string());
+                    }
+                }
+            }
+        }
+
+        images
+    }
+
+    // Checks the devcontainer service and `runServices` are in the merged files, before anything
+    // is started
+    pub fn validate(&self, service: &str, run_services: &[String]) -> Result<(), Error> {
+        let files: Vec<String> = self.files.iter().map(|f| f.display().to_string()).collect();
+        let available = self.services();
+
+        let missing: Vec<&str> = std::iter::once(service)
+            .chain(run_services.iter().map(|s| s.as_str()))
+            .filter(|name| !available.iter().any(|s| s == name))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::InvalidConfig(format!(
+            "No such service in {}: {}. Services: {}",
+            files.join(", "),
+            missing.join(", "),
+            match available.is_empty() {
+                true => "none".to_string(),
+                false => available.join(", "),
+            }
+        )))
+    }
+}
+
+// The compose files of a project, merged in order
+pub struct ComposeProject {
+    pub name: String,
+    // relative paths of the files are from here: the folder of the first one
+    pub dir: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub model: ComposeFile,
+}
+
+impl ComposeProject {
+    // Reads the files, their variables from `lookup`
+    pub fn load(
+        name: &str,
+        files: &[PathBuf],
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, Error> {
+        let dir = files
+            .first()
+            .and_then(|file| file.parent())
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default();
+
+        Self::from_files(name, dir, ComposeFiles::load(files, lookup)?)
+    }
+
+    // The typed model of the native engine, stricter than compose itself
+    pub fn from_files(name: &str, dir: PathBuf, files: ComposeFiles) -> Result<Self, Error> {
+        let model: ComposeFile = match files.merged.clone() {
+            Value::Null => ComposeFile::default(),
+            merged => serde_yaml::from_value(merged).map_err(|err| {
+                // the merged values have no lines, the error is told from the file holding it
+                let located = files.sources.iter().find_map(|source| {
+                    serde_yaml::from_value::<ComposeFile>(source.value.clone())
+                        .err()
+                        .map(|err| {
+                            // the same error read from the text has its line, unless the
+                            // variables changed it
+                            let message = err.to_string();
+                            let message = serde_yaml::from_str::<ComposeFile>(&source.content)
+                                .err()
+                                .filter(|err| err.location().is_some())
+                                .map(|err| err.to_string())
+                                .filter(|located| located.contains(&message))
+                                .unwrap_or(message);
+                            format!("{}: {}", source.name, message)
+                        })
+                });
+                Error::InvalidConfig(located.unwrap_or_else(|| err.to_string()))
+            })?,
+        };
+
+        Ok(ComposeProject {
+            name: name.to_string(),
+            dir,
+            files: files.files,
+            model,
+        })
+    }
+
+    pub fn service(&self, name: &str) -> Result<&ComposeService, Error> {
+        self.model.services.get(name).ok_or_else(|| {
+            Error::InvalidConfig(format!("No such service in the compose files: {}", name))
+        })
+    }
+
+    // The `depends_on` of the service
+    pub fn dependencies(&self, name: &str) -> Result<Vec<(String, Condition)>, Error> {
+        Ok(self
+            .service(name)?
+            .depends_on
+            .as_ref()
+            .map(|depends_on| depends_on.conditions())
+            .unwrap_or_default())
+    }
+
+    // The services to start for `requested`, their dependencies first
+    pub fn service_order(&self, requested: &[String]) -> Result<Vec<String>, Error> {
+        fn visit(
+            project: &ComposeProject,
+            name: &str,
+            visiting: &mut Vec<String>,
+            order: &mut Vec<String>,
+        ) -> Result<(), Error> {
+            if order.iter().any(|s| s == name) {
+                return Ok(());
+            }
+            if visiting.iter().any(|s| s == name) {
+                visiting.push(name.to_string());
+                return Err(Error::InvalidConfig(format!(
+                    "circular dependency between services: {}",
+                    visiting.join(" -> ")
+                )));
+            }
+
+            let service = project.service(name)?;
+            visiting.push(name.to_string());
+            for dependency in service.depends_on.iter().flat_map(|d| d.services()) {
+                visit(project, &dependency, visiting, order)?;
+            }
+            visiting.pop();
+            order.push(name.to_string());
+
+            Ok(())
+        }
+
+        let mut order = vec![];
+        for name in requested {
+            visit(self, name, &mut vec![], &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    pub fn image_name(&self, service_name: &str) -> Result<String, Error> {
+        let service = self.service(service_name)?;
+
+        Ok(service
+            .image
+            .clone()
+            .unwrap_or_else(|| format!("{}_{}", self.name, service_name)))
+    }
+
+    pub fn container_name(&self, service_name: &str) -> Result<String, Error> {
+        let service = self.service(service_name)?;
+
+        Ok(service
+            .container_name
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}-1", self.name, service_name)))
+    }
+
+    // Name on the daemon of a network of the file
+    pub fn network_name(&self, key: &str) -> String {
+        resource_name(&self.name, key, self.model.networks.get(key))
+    }
+
+    pub fn volume_name(&self, key: &str) -> String {
+        resource_name(&self.name, key, self.model.volumes.get(key))
+    }
+
+    // The networks the service joins, with its aliases on each. None with `network_mode`
+    pub fn service_networks(
+        &self,
+        service_name: &str,
+    ) -> Result<Vec<(String, Vec<String>)>, Error> {
+        let service = self.service(service_name)?;
+        if service.network_mode.is_some() {
+            return Ok(vec![]);
+        }
+
+        let entries = match service.networks.as_ref() {
+            Some(networks) => networks.entries(),
+            None => vec![("default".to_string(), vec![])],
+        };
+
+        entries
+            .into_iter()
+            .map(|(key, mut aliases)| {
+                if key != "default" && !self.model.networks.contains_key(&key) {
+                    return Err(Error::InvalidConfig(format!(
+                        "service {} refers to undefined network {}",
+                        service_name, key
+                    )));
+                }
+                aliases.insert(0, service_name.to_string());
+                Ok((key, aliases))
+            })
+            .collect()
+    }
+
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        resolve_path(&self.dir, path)
+    }
+
+    fn labels(&self, service_name: &str) -> HashMap<String, String> {
+        let mut labels = HashMap::new();
+        labels.insert(PROJECT_LABEL.to_string(), self.name.clone());
+        labels.insert(SERVICE_LABEL.to_string(), service_name.to_string());
+        labels.insert(ONEOFF_LABEL.to_string(), "False".to_string());
+        labels.insert(CONTAINER_NUMBER_LABEL.to_string(), "1".to_string());
+        labels.insert(
+            WORKING_DIR_LABEL.to_string(),
+            self.dir.display().to_string(),
+        );
+        labels.insert(
+            CONFIG_FILES_LABEL.to_string(),
+            self.files
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+        labels
+    }
+
+    fn environment(&self, service: &ComposeService) -> Result<BTreeMap<String, String>, Error> {
+        let mut env = BTreeMap::new();
+
+        for file in service.env_file.iter().flat_map(|f| f.to_vec()) {
+            let path = self.resolve_path(&file);
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|err| Error::InvalidConfig(format!("{}: {}", path.display(), err)))?;
+            env.extend(
+                parse_dotenv(&contents)
+                    .map_err(|err| Error::InvalidConfig(format!("{}: {}", path.display(), err)))?,
+            );
+        }
+
+        if let Some(environment) = service.environment.as_ref() {
+            for (key, value) in environment.to_map() {
+                // a key alone takes the value of the host, and is left out when unset there
+                match value.or_else(|| std::env::var(&key).ok()) {
+                    Some(value) => {
+                        env.insert(key, value);
+                    }
+                    None => {
+                        env.remove(&key);
+                    }
+                }
+            }
+        }
+
+        Ok(env)
+    }
+
+    // What the daemon gets to create the container of the service. The config hash label tells
+    // whether an existing one was created from the same config
+    pub fn container_config(&self, service_name: &str) -> Result<Config<String>, Error> {
+        let service = self.service(service_name)?;
+        let context = |err: Error| match err {
+            Error::InvalidConfig(err) => {
+                Error::InvalidConfig(format!("service {}: {}", service_name, err))
+            }
+            err => err,
+        };
+
+        let mut labels = self.labels(service_name);
+        if let Some(service_labels) = service.labels.as_ref() {
+            labels.extend(
+                service_labels
+                    .to_map()
+                    .into_iter()
+                    .map(|(k, v)| (k, v.unwrap_or_default())),
+            );
+        }
+
+        let env = self
+            .environment(service)
+            .map_err(context)?
+            .into_iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+
+        let mut exposed_ports = HashMap::new();
+        let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+        for port in service.ports.iter() {
+            let mapping = port.mapping().map_err(context)?;
+            let container_port = format!("{}/{}", mapping.container_port, mapping.protocol);
+
+            exposed_ports.insert(container_port.clone(), HashMap::new());
+            port_bindings
+                .entry(container_port)
+                .or_default()
+                .get_or_insert_with(Vec::new)
+                .push(PortBinding {
+                    host_ip: mapping.host_ip,
+                    host_port: mapping.host_port,
+                });
+        }
+
+        let mut mounts = vec![];
+        for volume in service.volumes.iter() {
+            let mount = volume.mount().map_err(context)?;
+            let source = match (mount.typ, mount.source.as_ref()) {
+                (MountTypeEnum::BIND, Some(source)) => {
+                    Some(self.resolve_path(source).display().to_string())
+                }
+                (MountTypeEnum::VOLUME, Some(source)) => {
+                    if !self.model.volumes.contains_key(source) {
+                        return Err(Error::InvalidConfig(format!(
+                            "service {} refers to undefined volume {}",
+                            service_name, source
+                        )));
+                    }
+                    Some(self.volume_name(source))
+                }
+                (_, source) => source.cloned(),
+            };
+
+            mounts.push(Mount {
+                typ: Some(mount.typ),
+                source,
+                target: Some(mount.target),
+                read_only: Some(mount.read_only),
+                ..Default::default()
+            });
+        }
+
+        let networks = self.service_networks(service_name)?;
+        let network_mode = service.network_mode.clone().or_else(|| {
+            networks
+                .first()
+                .map(|(key, _)| self.network_name(key.as_str()))
+        });
+        let networking_config = networks.first().map(|(key, aliases)| {
+            let mut endpoints_config = HashMap::new();
+            endpoints_config.insert(
+                self.network_name(key),
+                EndpointSettings {
+                    aliases: Some(aliases.clone()),
+                    ..Default::default()
+                },
+            );
+            NetworkingConfig { endpoints_config }
+        });
+
+        let shm_size = match service.shm_size.as_ref() {
+            Some(Value::Number(n)) => n.as_u64().map(|n| n as usize),
+            Some(value) => match scalar_string(value) {
+                Some(size) => Some(parse_size(&size).map_err(context)? as usize),
+                None => None,
+            },
+            None => None,
+        };
+
+        let host_config = HostConfig {
+            mounts: Some(mounts).filter(|m| !m.is_empty()),
+            port_bindings: Some(port_bindings).filter(|p| !p.is_empty()),
+            privileged: service.privileged,
+            cap_add: Some(service.cap_add.clone()).filter(|c| !c.is_empty()),
+            security_opt: Some(service.security_opt.clone()).filter(|s| !s.is_empty()),
+            init: service.init,
+            extra_hosts: Some(service.extra_hosts.clone()).filter(|h| !h.is_empty()),
+            restart_policy: service
+                .restart
+                .as_deref()
+                .map(run_args::parse_restart_policy)
+                .transpose()
+                .map_err(context)?,
+            network_mode,
+            shm_size,
+            ..Default::default()
+        };
+
+        let mut config = Config {
+            image: Some(self.image_name(service_name)?),
+            cmd: service
+                .command
+                .as_ref()
+                .map(|c| c.to_args())
+                .transpose()
+                .map_err(context)?,
+            entrypoint: service
+                .entrypoint
+                .as_ref()
+                .map(|e| e.to_args())
+                .transpose()
+                .map_err(context)?,
+            env: Some(env),
+            working_dir: service.working_dir.clone(),
+            user: service.user.clone(),
+            hostname: service.hostname.clone(),
+            tty: service.tty,
+            open_stdin: service.stdin_open,
+            exposed_ports: Some(exposed_ports).filter(|p| !p.is_empty()),
+            healthcheck: service
+                .healthcheck
+                .as_ref()
+                .map(|h| h.to_config())
+                .transpose()
+                .map_err(context)?,
+            labels: Some(labels),
+            host_config: Some(host_config),
+            networking_config,
+            ..Default::default()
+        };
+
+        let hash = config_hash(&config);
+        config
+            .labels
+            .get_or_insert_with(HashMap::new)
+            .insert(NATIVE_CONFIG_HASH_LABEL.to_string(), hash);
+
+        Ok(config)
+    }
+
+    // Services enabled by `profiles`, the ones without profiles are always enabled
+    pub fn active_services(&self, profiles: &[String]) -> Vec<String> {
+        self.model
+            .services
+            .iter()
+            .filter(|(_, service)| {
+                service.profiles.is_empty() || service.profiles.iter().any(|p| profiles.contains(p))
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    // Services that only `profiles` enable
+    pub fn profile_services(&self, profiles: &[String]) -> Vec<String> {
+        self.model
+            .services
+            .iter()
+            .filter(|(_, service)| service.profiles.iter().any(|p| profiles.contains(p)))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    // Images of `services` to pull: the ones not built
+    pub fn images_to_pull(&self, services: &[String]) -> Result<Vec<String>, Error> {
+        let mut images = vec![];
+        for name in services {
+            let service = self.service(name)?;
+            if let (Some(image), None) = (service.image.as_ref(), service.build.as_ref()) {
+                if !images.contains(image) {
+                    images.push(image.clone());
+                }
+            }
+        }
+
+        Ok(images)
+    }
+
+    pub fn services_to_build(&self, services: &[String]) -> Result<Vec<String>, Error> {
+        let mut built = vec![];
+        for name in services {
+            if self.service(name)?.build.is_some() {
+                built.push(name.clone());
+            }
+        }
+
+        Ok(built)
+    }
+
+    // The host ports the service publishes, with their protocol. The ones left to the daemon to
+    // pick, and those compose reads differently (ranges), are not known before it starts
+    pub fn published_ports(&self, service_name: &str) -> Result<Vec<(u16, String)>, Error> {
+        let mut ports = vec![];
+        for spec in self.service(service_name)?.ports.iter() {
+            let mapping = match spec.mapping() {
+                Ok(mapping) => mapping,
+                Err(_) => continue,
+            };
+            if let Some(port) = mapping.host_port.and_then(|port| port.parse().ok()) {
+                ports.push((port, mapping.protocol));
+            }
+        }
+
+        Ok(ports)
+    }
+
+    // The Dockerfile the service builds from, None for the services using an image
+    pub fn dockerfile_path(&self, service_name: &str) -> Result<Option<PathBuf>, Error> {
+        let (context, dockerfile) = match self.service(service_name)?.build.as_ref() {
+            Some(BuildSpec::Context(context)) => (context.clone(), None),
+            Some(BuildSpec::Full {
+                context,
+                dockerfile,
+                ..
+            }) => (
+                context.clone().unwrap_or_else(|| ".".to_string()),
+                dockerfile.clone(),
+            ),
+            None => return Ok(None),
+        };
+
+        Ok(Some(self.resolve_path(&context).join(
+            dockerfile.unwrap_or_else(|| "Dockerfile".to_string()),
+        )))
+    }
+
+    // Builds the image of the service through the API, from its context sent as a tarball.
+    // `pull` gets newer versions of the base images
+    pub async fn build(
+        &self,
+        docker: &Docker,
+        service_name: &str,
+        no_cache: bool,
+        pull: bool,
+    ) -> Result<String, Error> {
+        let service = self.service(service_name)?;
+        let image = self.image_name(service_name)?;
+        let (context, dockerfile, args, target) = match service.build.as_ref() {
+            Some(BuildSpec::Context(context)) => (context.clone(), None, None, None),
+            Some(BuildSpec::Full {
+                context,
+                dockerfile,
+                args,
+                target,
+            }) => (
+                context.clone().unwrap_or_else(|| ".".to_string()),
+                dockerfile.clone(),
+                args.clone(),
+                target.clone(),
+            ),
+            None => return Ok(image),
+        };
+        let compose_error = |err: String| {
+            Error::UpError(UpError::ComposeError(format!(
+                "service {}: {}",
+                service_name, err
+            )))
+        };
+
+        info!("Building image of {}: {}", service_name, image);
+        let context = self.resolve_path(&context);
+        let dockerfile = dockerfile.unwrap_or_else(|| "Dockerfile".to_string());
+
+        let mut tar = tar::Builder::new(Vec::new());
+        tar.append_dir_all("", &context)
+            .map_err(|err| compose_error(err.to_string()))?;
+
+        // like the devcontainer builds, the Dockerfile is cut after the target stage
+        let dockerfile = match target.as_ref() {
+            Some(target) => {
+                let contents = std::fs::read_to_string(context.join(&dockerfile))
+                    .map_err(|err| compose_error(err.to_string()))?;
+                let contents = dockerfile::truncate_to_target(&contents, target)
+                    .ok_or_else(|| compose_error(format!("build target '{}' not found", target)))?;
+
+                let generated = ".compose.Dockerfile".to_string();
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar.append_data(&mut header, &generated, contents.as_bytes())
+                    .map_err(|err| compose_error(err.to_string()))?;
+                generated
+            }
+            None => dockerfile,
+        };
+        let context = tar
+            .into_inner()
+            .map_err(|err| compose_error(err.to_string()))?;
+
+        let build_args: HashMap<String, String> = args
+            .map(|args| args.to_map())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(k, v)| v.or_else(|| std::env::var(&k).ok()).map(|v| (k, v)))
+            .collect();
+        let platform = service.platform.clone().unwrap_or_default();
+
+        let options = BuildImageOptions {
+            dockerfile: dockerfile.as_str(),
+            t: image.as_str(),
+            rm: true,
+            nocache: no_cache,
+            pull,
+            buildargs: build_args
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            platform: platform.as_str(),
+            ..Default::default()
+        };
+
+        let mut stream = docker.build_image(options, None, Some(context.into()));
+        while let Some(result) = stream.next().await {
+            let output = result.map_err(|err| compose_error(err.to_string()))?;
+            debug!("Build output: {:?}", output);
+            if let Some(error) = output.error {
+                return Err(compose_error(error));
+            }
+        }
+        drop(stream);
+
+        info!("Building image of {}: done", service_name);
+        Ok(image)
+    }
+
+    // Creates what `services` need and starts them, dependencies first. Containers are only
+    // created again when their config changed, or with `recreate`
+    pub async fn up(
+        &self,
+        docker: &Docker,
+        services: &[String],
+        recreate: bool,
+    ) -> Result<(), Error> {
+        let order = self.service_order(services)?;
+
+        let mut configs = HashMap::new();
+        for name in order.iter() {
+            for (key, _) in self.service_networks(name)? {
+                self.ensure_network(docker, &key).await?;
+            }
+            let config = self.container_config(name)?;
+            for mount in config
+                .host_config
+                .iter()
+                .flat_map(|h| h.mounts.iter().flatten())
+            {
+                if mount.typ == Some(MountTypeEnum::VOLUME) {
+                    if let Some(key) = self.volume_key(mount.source.as_deref()) {
+                        self.ensure_volume(docker, &key).await?;
+                    }
+                }
+            }
+
+            configs.insert(name.clone(), config);
+        }
+
+        for level in self.start_levels(&order)? {
+            let services: Vec<(String, Config<String>)> = level
+                .into_iter()
+                .map(|name| {
+                    let config = configs.remove(&name).unwrap_or_default();
+                    (name, config)
+                })
+                .collect();
+
+            future::try_join_all(services.into_iter().map(|(name, config)| async move {
+                self.up_service(docker, &name, config, recreate).await
+            }))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // `order` in groups started together: the services of a group only depend on the ones of the
+    // groups before it
+    pub fn start_levels(&self, order: &[String]) -> Result<Vec<Vec<String>>, Error> {
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        let mut levels: Vec<Vec<String>> = vec![];
+
+        for name in order {
+            let depth = self
+                .service(name)?
+                .depends_on
+                .iter()
+                .flat_map(|d| d.services())
+                .map(|dependency| depths.get(dependency.as_str()).map(|d| d + 1).unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+            depths.insert(name.as_str(), depth);
+
+            if levels.len() <= depth {
+                levels.resize(depth + 1, vec![]);
+            }
+            levels[depth].push(name.clone());
+        }
+
+        Ok(levels)
+    }
+
+    async fn up_service(
+        &self,
+        docker: &Docker,
+        name: &str,
+        config: Config<String>,
+        recreate: bool,
+    ) -> Result<(), Error> {
+        let hash = config
+            .labels
+            .as_ref()
+            .and_then(|l| l.get(NATIVE_CONFIG_HASH_LABEL))
+            .cloned();
+        let container_name = self.container_name(name)?;
+
+        if let Some(existing) = self
+            .containers(docker, Some(name))
+            .await?
+            .into_iter()
+            .next()
+        {
+            let id = existing.id.clone().unwrap_or_default();
+            let existing_hash = existing
+                .labels
+                .as_ref()
+                .and_then(|l| l.get(NATIVE_CONFIG_HASH_LABEL));
+
+            // created by the compose cli, whose changes we can't tell
+            if existing_hash.is_none() && !recreate {
+                warn!(
+                    "The container of service {} was created by the compose cli, keeping it. `up --recreate` creates it again",
+                    name
+                );
+            }
+
+            if (existing_hash.is_none() || existing_hash == hash.as_ref()) && !recreate {
+                if existing.state.as_deref() != Some("running") {
+                    info!("Starting {}", container_name);
+                    docker
+                        .start_container(&id, None::<StartContainerOptions<String>>)
+                        .await?;
+                }
+                return Ok(());
+            }
+
+            info!("Recreating {}", container_name);
+            self.remove_container(docker, &id).await?;
+        }
+
+        info!("Creating {}", container_name);
+        let networks = self.service_networks(name)?;
+        let created = docker
+            .create_container(
+                Some(CreateContainerOptions {
+                    name: container_name.clone(),
+                }),
+                config,
+            )
+            .await
+            .map_err(|err| {
+                Error::UpError(UpError::ComposeError(format!("service {}: {}", name, err)))
+            })?;
+
+        // only one network can be given on create
+        for (key, aliases) in networks.into_iter().skip(1) {
+            docker
+                .connect_network(
+                    self.network_name(&key).as_str(),
+                    ConnectNetworkOptions {
+                        container: created.id.clone(),
+                        endpoint_config: EndpointSettings {
+                            aliases: Some(aliases),
+                            ..Default::default()
+                        },
+                    },
+                )
+                .await?;
+        }
+
+        info!("Starting {}", container_name);
+        docker
+            .start_container(&created.id, None::<StartContainerOptions<String>>)
+            .await
+            .map_err(|err| {
+                Error::UpError(UpError::ComposeError(format!("service {}: {}", name, err)))
+            })?;
+
+        Ok(())
+    }
+
+    fn volume_key(&self, name: Option<&str>) -> Option<String> {
+        let name = name?;
+        self.model
+            .volumes
+            .keys()
+            .find(|key| self.volume_name(key) == name)
+            .cloned()
+    }
+
+    async fn ensure_network(&self, docker: &Docker, key: &str) -> Result<(), Error> {
+        let name = self.network_name(key);
+        let resource = self.model.networks.get(key).cloned().flatten();
+
+        match docker
+            .inspect_network(name.as_str(), None::<InspectNetworkOptions<String>>)
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(DockerError::DockerResponseNotFoundError { .. }) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        if resource.as_ref().map(|r| r.external).unwrap_or(false) {
+            return Err(Error::UpError(UpError::ComposeError(format!(
+                "external network {} not found",
+                name
+            ))));
+        }
+
+        let mut labels = HashMap::new();
+        labels.insert(PROJECT_LABEL.to_string(), self.name.clone());
+        labels.insert(NETWORK_LABEL.to_string(), key.to_string());
+
+        info!("Creating network: {}", name);
+        docker
+            .create_network(CreateNetworkOptions {
+                name: name.clone(),
+                check_duplicate: true,
+                driver: resource
+                    .and_then(|r| r.driver)
+                    .unwrap_or_else(|| "bridge".to_string()),
+                labels,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn ensure_volume(&self, docker: &Docker, key: &str) -> Result<(), Error> {
+        let name = self.volume_name(key);
+        let resource = self.model.volumes.get(key).cloned().flatten();
+
+        match docker.inspect_volume(name.as_str()).await {
+            Ok(_) => return Ok(()),
+            Err(DockerError::DockerResponseNotFoundError { .. }) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        if resource.as_ref().map(|r| r.external).unwrap_or(false) {
+            return Err(Error::UpError(UpError::ComposeError(format!(
+                "external volume {} not found",
+                name
+            ))));
+        }
+
+        let mut labels = HashMap::new();
+        labels.insert(PROJECT_LABEL.to_string(), self.name.clone());
+        labels.insert(VOLUME_LABEL.to_string(), key.to_string());
+
+        info!("Creating volume: {}", name);
+        docker
+            .create_volume(CreateVolumeOptions {
+                name,
+                driver: resource
+                    .and_then(|r| r.driver)
+                    .unwrap_or_else(|| "local".to_string()),
+                labels,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    // The containers of the project, or of one of its services, stopped ones too
+    pub async fn containers(
+        &self,
+        docker: &Docker,
+        service: Option<&str>,
+    ) -> Result<Vec<ContainerSummaryInner>, Error> {
+        let project_label = format!("{}={}", PROJECT_LABEL, self.name);
+        let service_label = service.map(|s| format!("{}={}", SERVICE_LABEL, s));
+
+        let mut labels = vec![project_label.as_str()];
+        if let Some(service_label) = service_label.as_ref() {
+            labels.push(service_label.as_str());
+        }
+        let mut filters = HashMap::new();
+        filters.insert("label", labels);
+
+        Ok(docker
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters,
+                ..Default::default()
+            }))
+            .await?)
+    }
+
+    pub async fn stop(&self, docker: &Docker) -> Result<(), Error> {
+        for container in self.containers(docker, None).await? {
+            if container.state.as_deref() != Some("running") {
+                continue;
+            }
+            let id = container.id.unwrap_or_default();
+            info!("Stopping {}", container_display_name(&container.names, &id));
+            docker
+                .stop_container(&id, None::<StopContainerOptions>)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Stops and removes the containers, keeping the networks and volumes
+    pub async fn remove_containers(&self, docker: &Docker) -> Result<(), Error> {
+        for container in self.containers(docker, None).await? {
+            let id = container.id.unwrap_or_default();
+            info!("Removing {}", container_display_name(&container.names, &id));
+            self.remove_container(docker, &id).await?;
+        }
+
+        Ok(())
+    }
+
+    // A container of the project whose service is not in the files
+    pub fn is_orphan(&self, container: &ContainerSummaryInner) -> bool {
+        container
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(SERVICE_LABEL))
+            .map(|service| !self.model.services.contains_key(service))
+            .unwrap_or(false)
+    }
+
+    async fn remove_container(&self, docker: &Docker, id: &str) -> Result<(), Error> {
+        docker
+            .remove_container(
+                id,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // Removes the containers and networks, the volumes of the file and the built images when
+    // asked to. External ones are never removed. The containers of services no longer in the
+    // files (orphans) are left alone unless `remove_orphans`
+    pub async fn down(
+        &self,
+        docker: &Docker,
+        volumes: bool,
+        rmi: bool,
+        remove_orphans: bool,
+    ) -> Result<(), Error> {
+        let containers = self.containers(docker, None).await?;
+        let images: HashSet<String> = containers.iter().filter_map(|c| c.image.clone()).collect();
+
+        for container in containers {
+            let id = container.id.clone().unwrap_or_default();
+            let name = container_display_name(&container.names, &id);
+
+            if self.is_orphan(&container) && !remove_orphans {
+                warn!(
+                    "Found orphan container {}, remove it with --remove-orphans",
+                    name
+                );
+                continue;
+            }
+
+            info!("Removing {}", name);
+            self.remove_container(docker, &id).await?;
+        }
+
+        let project_label = format!("{}={}", PROJECT_LABEL, self.name);
+        let mut filters = HashMap::new();
+        filters.insert("label", vec![project_label.as_str()]);
+
+        for network in docker
+            .list_networks(Some(ListNetworksOptions {
+                filters: filters.clone(),
+            }))
+            .await?
+        {
+            if let Some(name) = network.name {
+                info!("Removing network: {}", name);
+                // the orphans left may still use it
+                if let Err(err) = docker.remove_network(&name).await {
+                    warn!("Could not remove network {}: {}", name, err);
+                }
+            }
+        }
+
+        if volumes {
+            let volumes = docker
+                .list_volumes(Some(ListVolumesOptions { filters }))
+                .await?;
+            for volume in volumes.volumes {
+                info!("Removing volume: {}", volume.name);
+                docker.remove_volume(volume.name.as_str(), None).await?;
+            }
+        }
+
+        if rmi {
+            // only the images built for the project, like --rmi local
+            for (name, service) in self.model.services.iter() {
+                if service.image.is_some() || service.build.is_none() {
+                    continue;
+                }
+                let image = self.image_name(name)?;
+                if !images.contains(&image) {
+                    continue;
+                }
+                info!("Removing image: {}", image);
+                match docker
+                    .remove_image(image.as_str(), None::<RemoveImageOptions>, None)
+                    .await
+                {
+                    Ok(_) | Err(DockerError::DockerResponseNotFoundError { .. }) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn resource_name(project: &str, key: &str, resource: Option<&Option<Resource>>) -> String {
+    match resource.cloned().flatten() {
+        Some(Resource {
+            name: Some(name), ..
+        }) => name,
+        Some(Resource { external: true, .. }) => key.to_string(),
+        _ => format!("{}_{}", project, key),
+    }
+}
+
+fn container_display_name(names: &Option<Vec<String>>, id: &str) -> String {
+    names
+        .as_ref()
+        .and_then(|names| names.first())
+        .map(|name| name.trim_start_matches('/').to_string())
+        .unwrap_or_else(|| id.to_string())
+}
+
+// sha1 of the config. Through a json value, so the maps are in the same order every time
+pub fn config_hash(config: &Config<String>) -> String {
+    let json = serde_json::to_value(config)
+        .map(|value| value.to_string())
+        .unwrap_or_default();
+
+    let mut hasher = Sha1::new();
+    hasher.input_str(&json);
+    hasher.result_str()
+}
+
+// The top level `name` of the files, the last one setting it wins
+pub fn file_project_name(
+    contents: &[String],
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Option<String>, Error> {
+    let mut name = None;
+
+    for content in contents {
+        let value: Value =
+            serde_yaml::from_str(content).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+        if let Some(file_name) = value.get("name").and_then(|n| n.as_str()) {
+            name = Some(interpolate(file_name, lookup)?);
+        }
+    }
+
+    Ok(name.filter(|name| !name.is_empty()))
+}
+
+// Whether an image `created` (RFC 3339, as the daemon reports it) before the Dockerfile was
+// last `modified`. Unknown dates never make it outdated
+pub fn image_is_outdated(created: &str, modified: std::time::SystemTime) -> bool {
+    match chrono::DateTime::parse_from_rfc3339(created) {
+        Ok(created) => chrono::DateTime::<chrono::Utc>::from(modified) > created,
+        Err(_) => false,
+    }
+}
+
+// Lowercase letters, digits, dashes and underscores, starting with a letter or digit, like
+// compose wants it
+pub fn normalize_project_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>()
+        .trim_start_matches(['-', '_'])
+        .to_string()
+}
+
+// COMPOSE_PROFILES: "debug,tools"
+pub fn parse_profiles(profiles: &str) -> Vec<String> {
+    profiles
+        .split(',')
+        .map(|profile| profile.trim())
+        .filter(|profile| !profile.is_empty())
+        .map(|profile| profile.to_string())
+        .collect()
+}
+
+// A path of the compose files: `~` is the home folder, relative ones are from `dir`
+pub fn resolve_path(dir: &Path, path: &str) -> PathBuf {
+    let path = match path.strip_prefix('~') {
+        Some(rest) => dirs::home_dir()
+            .unwrap_or_default()
+            .join(rest.trim_start_matches('/')),
+        None => dir.join(path),
+    };
+
+    normalize_path(&path)
+}
+
+// Without the `.` and `..` of the path, the daemon wants the plain one. Only the `..` going
+// above a relative path are kept
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,713 @@
+use bollard::service::{ContainerSummaryInner, MountTypeEnum};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::compose::*;
+use super::health::Condition;
+
+fn lookup(var: &str) -> Option<String> {
+    match var {
+        "TAG" => Some("22.04".to_string()),
+        "EMPTY" => Some("".to_string()),
+        _ => None,
+    }
+}
+
+fn project(files: &[&str]) -> ComposeProject {
+    let contents: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+
+    ComposeProject::from_files(
+        "demo",
+        PathBuf::from("/src/demo"),
+        ComposeFiles::parse(vec![], &contents, &lookup).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_interpolate() {
+    assert_eq!(
+        interpolate("ubuntu:${TAG}", &lookup).unwrap(),
+        "ubuntu:22.04"
+    );
+    assert_eq!(
+        interpolate("ubuntu:$TAG-slim", &lookup).unwrap(),
+        "ubuntu:22.04-slim"
+    );
+    assert_eq!(interpolate("${MISSING:-a-b}", &lookup).unwrap(), "a-b");
+    assert_eq!(
+        interpolate("${EMPTY:-default}", &lookup).unwrap(),
+        "default"
+    );
+    assert_eq!(interpolate("${EMPTY-default}", &lookup).unwrap(), "");
+    assert_eq!(
+        interpolate("${TAG:+set}${MISSING+set}", &lookup).unwrap(),
+        "set"
+    );
+    assert_eq!(interpolate("$$HOME $", &lookup).unwrap(), "$HOME $");
+    assert_eq!(interpolate("${MISSING}", &lookup).unwrap(), "");
+
+    assert!(interpolate("${MISSING:?needed}", &lookup).is_err());
+    assert!(interpolate("${EMPTY:?needed}", &lookup).is_err());
+    assert!(interpolate("${EMPTY?needed}", &lookup).is_ok());
+    assert!(interpolate("${TAG", &lookup).is_err());
+}
+
+#[test]
+fn test_split_command() {
+    assert_eq!(
+        split_command(r#"sh -c 'echo "$HOME"' "a b" c\ d"#).unwrap(),
+        vec!["sh", "-c", r#"echo "$HOME""#, "a b", "c d"]
+    );
+    assert_eq!(split_command("  ").unwrap(), Vec::<String>::new());
+    assert!(split_command("echo 'oops").is_err());
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("10s").unwrap(), 10_000_000_000);
+    assert_eq!(parse_duration("1m30s").unwrap(), 90_000_000_000);
+    assert_eq!(parse_duration("500ms").unwrap(), 500_000_000);
+    assert!(parse_duration("10").is_err());
+    assert!(parse_duration("").is_err());
+}
+
+#[test]
+fn test_ports() {
+    let mapping = |spec: &str| {
+        PortSpec::Short(serde_yaml::Value::String(spec.to_string()))
+            .mapping()
+            .unwrap()
+    };
+
+    assert_eq!(
+        mapping("127.0.0.1:8080:80/udp"),
+        PortMapping {
+            host_ip: Some("127.0.0.1".to_string()),
+            host_port: Some("8080".to_string()),
+            container_port: 80,
+            protocol: "udp".to_string(),
+        }
+    );
+    assert_eq!(mapping("[::1]:8080:80").host_ip, Some("::1".to_string()));
+    assert_eq!(mapping("127.0.0.1::80").host_port, None);
+    assert_eq!(mapping("3000").host_port, None);
+
+    assert!(
+        PortSpec::Short(serde_yaml::Value::String("3000-3005:3000-3005".to_string()))
+            .mapping()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_published_ports() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+    ports:
+      - "8080:80"
+      - "127.0.0.1:5353:53/udp"
+      - "3000"
+      - "9000-9001:9000-9001"
+      - target: 443
+        published: 8443
+"#]);
+
+    assert_eq!(
+        project.published_ports("app").unwrap(),
+        vec![
+            (8080, "tcp".to_string()),
+            (5353, "udp".to_string()),
+            (8443, "tcp".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_volumes() {
+    let mount = |spec: &str| VolumeSpec::Short(spec.to_string()).mount().unwrap();
+
+    assert_eq!(
+        mount("./src:/src:ro"),
+        VolumeMount {
+            typ: MountTypeEnum::BIND,
+            source: Some("./src".to_string()),
+            target: "/src".to_string(),
+            read_only: true,
+        }
+    );
+    assert_eq!(mount("data:/var/lib/data").typ, MountTypeEnum::VOLUME);
+    assert_eq!(mount("/cache").source, None);
+}
+
+#[test]
+fn test_merge() {
+    let project = project(&[
+        r#"
+version: "3"
+services:
+  app:
+    image: "ubuntu:${TAG}"
+    command: sleep infinity
+    ports: ["8080:80"]
+    environment:
+      A: "1"
+"#,
+        r#"
+version: "3"
+services:
+  app:
+    command: ["sleep", "1d"]
+    ports: ["8080:80", "9090:90"]
+    environment:
+      B: "2"
+"#,
+    ]);
+
+    let app = project.service("app").unwrap();
+    assert_eq!(app.image, Some("ubuntu:22.04".to_string()));
+    assert_eq!(
+        app.command.as_ref().unwrap().to_args().unwrap(),
+        vec!["sleep", "1d"]
+    );
+    assert_eq!(app.ports.len(), 2);
+    assert_eq!(app.environment.as_ref().unwrap().to_map().len(), 2);
+}
+
+#[test]
+fn test_merge_build() {
+    let project = project(&[
+        r#"
+services:
+  app:
+    build: ./app
+"#,
+        r#"
+services:
+  app:
+    build:
+      args:
+        VARIANT: bullseye
+"#,
+    ]);
+
+    let app = project.service("app").unwrap();
+    match app.build.as_ref().unwrap() {
+        BuildSpec::Full { context, args, .. } => {
+            assert_eq!(context.as_deref(), Some("./app"));
+            assert_eq!(
+                args.as_ref().unwrap().to_map().get("VARIANT"),
+                Some(&Some("bullseye".to_string()))
+            );
+        }
+        build => panic!("unexpected build: {:?}", build),
+    }
+    assert_eq!(
+        project.dockerfile_path("app").unwrap(),
+        Some(PathBuf::from("/src/demo/app/Dockerfile"))
+    );
+}
+
+#[test]
+fn test_image_is_outdated() {
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+
+    // 2020-09-13T12:26:40Z
+    assert!(image_is_outdated(
+        "2020-09-13T12:00:00.123456789Z",
+        modified
+    ));
+    assert!(!image_is_outdated("2020-09-13T13:00:00Z", modified));
+    assert!(!image_is_outdated("", modified));
+}
+
+#[test]
+fn test_service_order() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+    depends_on: [db, cache]
+  db:
+    image: db
+    depends_on:
+      cache:
+        condition: service_started
+  cache:
+    image: cache
+  loop_a:
+    image: a
+    depends_on: [loop_b]
+  loop_b:
+    image: b
+    depends_on: [loop_a]
+"#]);
+
+    assert_eq!(
+        project.service_order(&["app".to_string()]).unwrap(),
+        vec!["cache", "db", "app"]
+    );
+    assert!(project.service_order(&["loop_a".to_string()]).is_err());
+    assert!(project.service_order(&["missing".to_string()]).is_err());
+}
+
+#[test]
+fn test_validate() {
+    let merged = ComposeFiles::parse(
+        vec![
+            PathBuf::from("/src/demo/docker-compose.yml"),
+            PathBuf::from("/src/demo/docker-compose.dev.yml"),
+        ],
+        &[
+            "version: '3.8'\nservices:\n  app:\n    image: app\n".to_string(),
+            "version: 3.9\nservices:\n  db:\n    image: db\n".to_string(),
+        ],
+        &lookup,
+    )
+    .unwrap();
+
+    assert_eq!(merged.version(), Some("3.9".to_string()));
+    assert!(merged.validate("app", &["db".to_string()]).is_ok());
+
+    let err = merged
+        .validate("dev", &["db".to_string(), "cache".to_string()])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("/src/demo/docker-compose.yml, /src/demo/docker-compose.dev.yml"));
+    assert!(err.contains("dev, cache"));
+    assert!(err.contains("Services: app, db"));
+
+    let files = |content: &str| ComposeFiles::parse(vec![], &[content.to_string()], &lookup);
+    assert_eq!(files("services: {}").unwrap().version(), None);
+
+    // valid compose the native engine doesn't model
+    let merged = files(
+        r#"
+services:
+  app:
+    image: app
+    extra_hosts:
+      host.docker.internal: host-gateway
+    env_file:
+      - path: ./local.env
+        required: false
+    networks: [back]
+networks:
+  back:
+    external:
+      name: shared
+"#,
+    )
+    .unwrap();
+    assert!(merged.validate("app", &[]).is_ok());
+
+    assert!(files("services: [app]").is_err());
+    assert!(files("- app").is_err());
+}
+
+#[test]
+fn test_parse_errors() {
+    let parse = |second: &str| {
+        ComposeFiles::parse(
+            vec![
+                PathBuf::from("/src/demo/docker-compose.yml"),
+                PathBuf::from("/src/demo/docker-compose.dev.yml"),
+            ],
+            &[
+                "services:\n  app:\n    image: app\n".to_string(),
+                second.to_string(),
+            ],
+            &lookup,
+        )
+        .and_then(|files| ComposeProject::from_files("demo", PathBuf::from("/src/demo"), files))
+        .err()
+        .unwrap()
+        .to_string()
+    };
+
+    let err = parse("services:\n  app:\n    image: [app\n");
+    assert!(err.contains("/src/demo/docker-compose.dev.yml"), "{}", err);
+    assert!(err.contains("line"), "{}", err);
+
+    let err = parse("services:\n  app:\n    depends_on: 3\n");
+    assert!(err.contains("/src/demo/docker-compose.dev.yml"), "{}", err);
+    assert!(err.contains("line 3"), "{}", err);
+
+    let err = ComposeFiles::load(&[PathBuf::from("/does/not/exist.yml")], &lookup)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("Compose file not found: /does/not/exist.yml"));
+}
+
+#[test]
+fn test_dependencies() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+    depends_on:
+      db:
+        condition: service_healthy
+      migrate:
+        condition: service_completed_successfully
+      cache:
+        condition: service_started
+  worker:
+    image: worker
+    depends_on: [db]
+  db:
+    image: db
+  migrate:
+    image: migrate
+  cache:
+    image: cache
+"#]);
+
+    assert_eq!(
+        project.dependencies("app").unwrap(),
+        vec![
+            ("cache".to_string(), Condition::Started),
+            ("db".to_string(), Condition::Healthy),
+            ("migrate".to_string(), Condition::CompletedSuccessfully),
+        ]
+    );
+    assert_eq!(
+        project.dependencies("worker").unwrap(),
+        vec![("db".to_string(), Condition::Started)]
+    );
+    assert!(project.dependencies("db").unwrap().is_empty());
+    assert!(project.dependencies("missing").is_err());
+}
+
+#[test]
+fn test_container_config() {
+    let project = project(&[r#"
+services:
+  app:
+    build: .
+    command: sleep infinity
+    environment:
+      - LANG=C.UTF-8
+    labels:
+      team: dev
+    ports:
+      - "127.0.0.1:8080:80"
+    volumes:
+      - ../src:/workspace
+      - data:/data
+    networks:
+      back:
+        aliases: [api]
+      default:
+    healthcheck:
+      test: curl -f localhost
+      interval: 5s
+    restart: unless-stopped
+    shm_size: 1g
+volumes:
+  data:
+  shared:
+    external: true
+networks:
+  back:
+    name: backend
+"#]);
+
+    let config = project.container_config("app").unwrap();
+    assert_eq!(config.image, Some("demo_app".to_string()));
+    assert_eq!(
+        config.cmd,
+        Some(vec!["sleep".to_string(), "infinity".to_string()])
+    );
+    assert_eq!(config.env, Some(vec!["LANG=C.UTF-8".to_string()]));
+
+    let labels = config.labels.as_ref().unwrap();
+    assert_eq!(labels.get(PROJECT_LABEL), Some(&"demo".to_string()));
+    assert_eq!(labels.get(SERVICE_LABEL), Some(&"app".to_string()));
+    assert_eq!(labels.get("team"), Some(&"dev".to_string()));
+    assert!(labels.contains_key(NATIVE_CONFIG_HASH_LABEL));
+
+    let host_config = config.host_config.as_ref().unwrap();
+    let mounts = host_config.mounts.as_ref().unwrap();
+    assert_eq!(mounts[0].source, Some("/src/src".to_string()));
+    assert_eq!(mounts[1].source, Some("demo_data".to_string()));
+    assert_eq!(host_config.network_mode, Some("backend".to_string()));
+    assert_eq!(host_config.shm_size, Some(1 << 30));
+    assert_eq!(
+        host_config.port_bindings.as_ref().unwrap()["80/tcp"]
+            .as_ref()
+            .unwrap()[0]
+            .host_port,
+        Some("8080".to_string())
+    );
+
+    let healthcheck = config.healthcheck.as_ref().unwrap();
+    assert_eq!(
+        healthcheck.test,
+        Some(vec![
+            "CMD-SHELL".to_string(),
+            "curl -f localhost".to_string()
+        ])
+    );
+    assert_eq!(healthcheck.interval, Some(5_000_000_000));
+
+    assert_eq!(
+        project.service_networks("app").unwrap(),
+        vec![
+            (
+                "back".to_string(),
+                vec!["app".to_string(), "api".to_string()]
+            ),
+            ("default".to_string(), vec!["app".to_string()]),
+        ]
+    );
+    assert_eq!(project.network_name("default"), "demo_default");
+    assert_eq!(project.container_name("app").unwrap(), "demo-app-1");
+    assert_eq!(project.volume_name("shared"), "shared");
+}
+
+#[test]
+fn test_config_hash() {
+    let project = project(&[r#"
+services:
+  app:
+    image: ubuntu
+    environment:
+      A: "1"
+      B: "2"
+"#]);
+    let changed = self::project(&[r#"
+services:
+  app:
+    image: ubuntu
+    environment:
+      A: "1"
+      B: "3"
+"#]);
+
+    let hash = |project: &ComposeProject| {
+        project
+            .container_config("app")
+            .unwrap()
+            .labels
+            .unwrap_or_default()
+            .get(NATIVE_CONFIG_HASH_LABEL)
+            .cloned()
+    };
+    assert_eq!(hash(&project), hash(&project));
+    assert_ne!(hash(&project), hash(&changed));
+}
+
+#[test]
+fn test_undefined_resources() {
+    let project = project(&[r#"
+services:
+  app:
+    image: ubuntu
+    volumes: ["missing:/data"]
+  other:
+    image: ubuntu
+    networks: [missing]
+"#]);
+
+    assert!(project.container_config("app").is_err());
+    assert!(project.container_config("other").is_err());
+}
+
+#[test]
+fn test_profiles() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+  debug:
+    image: debug
+    profiles: [debug]
+  tools:
+    image: tools
+    profiles: [tools, debug]
+"#]);
+
+    assert_eq!(project.active_services(&[]), vec!["app"]);
+    assert_eq!(
+        project.active_services(&["tools".to_string()]),
+        vec!["app", "tools"]
+    );
+    assert_eq!(
+        project.profile_services(&["debug".to_string()]),
+        vec!["debug", "tools"]
+    );
+    assert_eq!(parse_profiles(" debug, ,tools"), vec!["debug", "tools"]);
+}
+
+#[test]
+fn test_start_levels() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+    depends_on: [db, cache]
+  db:
+    image: db
+  cache:
+    image: cache
+  worker:
+    image: worker
+    depends_on: [app]
+"#]);
+
+    let order = project
+        .service_order(&["worker".to_string(), "db".to_string()])
+        .unwrap();
+    assert_eq!(
+        project.start_levels(&order).unwrap(),
+        vec![
+            vec!["db".to_string(), "cache".to_string()],
+            vec!["app".to_string()],
+            vec!["worker".to_string()],
+        ]
+    );
+}
+
+#[test]
+fn test_is_orphan() {
+    let project = project(&[r#"
+services:
+  app:
+    image: app
+"#]);
+
+    let container = |service: Option<&str>| {
+        let mut labels = HashMap::new();
+        labels.insert(PROJECT_LABEL.to_string(), "demo".to_string());
+        if let Some(service) = service {
+            labels.insert(SERVICE_LABEL.to_string(), service.to_string());
+        }
+        ContainerSummaryInner {
+            labels: Some(labels),
+            ..Default::default()
+        }
+    };
+
+    assert!(!project.is_orphan(&container(Some("app"))));
+    assert!(project.is_orphan(&container(Some("old"))));
+    assert!(!project.is_orphan(&container(None)));
+}
+
+#[test]
+fn test_project_name() {
+    assert_eq!(normalize_project_name("My Project.v2"), "myprojectv2");
+    assert_eq!(normalize_project_name("_dev-env_1"), "dev-env_1");
+
+    let contents = vec![
+        "name: base\nservices: {}\n".to_string(),
+        "name: app-${TAG}\n".to_string(),
+        "services: {}\n".to_string(),
+    ];
+    assert_eq!(
+        file_project_name(&contents, &lookup).unwrap(),
+        Some("app-22.04".to_string())
+    );
+    assert_eq!(
+        file_project_name(&["services: {}\n".to_string()], &lookup).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_interpolate_value() {
+    let mut value: serde_yaml::Value =
+        serde_yaml::from_str("services:\n  app:\n    image: \"ubuntu:${TAG}\"\n    init: true\n")
+            .unwrap();
+    interpolate_value(&mut value, &lookup).unwrap();
+
+    assert_eq!(
+        value["services"]["app"]["image"].as_str(),
+        Some("ubuntu:22.04")
+    );
+    assert_eq!(value["services"]["app"]["init"].as_bool(), Some(true));
+}
+
+#[test]
+fn test_short_volume() {
+    use bollard::service::Mount;
+
+    let mount = |typ, read_only| Mount {
+        typ: Some(typ),
+        source: Some("/home/me/project".to_string()),
+        target: Some("/workspace".to_string()),
+        read_only: Some(read_only),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        short_volume(&mount(MountTypeEnum::BIND, false)),
+        Some("/home/me/project:/workspace".to_string())
+    );
+    assert_eq!(
+        short_volume(&mount(MountTypeEnum::VOLUME, true)),
+        Some("/home/me/project:/workspace:ro".to_string())
+    );
+    assert_eq!(short_volume(&mount(MountTypeEnum::TMPFS, false)), None);
+}
+
+#[test]
+fn test_resolve_path() {
+    let dir = PathBuf::from("/src/demo/.devcontainer");
+
+    assert_eq!(
+        resolve_path(&dir, "docker-compose.yml"),
+        PathBuf::from("/src/demo/.devcontainer/docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "./compose/../docker-compose.yml"),
+        PathBuf::from("/src/demo/.devcontainer/docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "../docker-compose.yml"),
+        PathBuf::from("/src/demo/docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "../../../../docker-compose.yml"),
+        PathBuf::from("/docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "/etc/compose/docker-compose.yml"),
+        PathBuf::from("/etc/compose/docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "~/compose.yml"),
+        dirs::home_dir().unwrap().join("compose.yml")
+    );
+
+    // from a relative project path
+    let dir = PathBuf::from("./.devcontainer");
+    assert_eq!(
+        resolve_path(&dir, "../docker-compose.yml"),
+        PathBuf::from("docker-compose.yml")
+    );
+    assert_eq!(
+        resolve_path(&dir, "../../docker-compose.yml"),
+        PathBuf::from("../docker-compose.yml")
+    );
+}
+
+#[test]
+fn test_compose_files_images() {
+    let files = ComposeFiles::parse(
+        vec![],
+        &[
+            "services:\n  app:\n    build: .\n    image: app\n  db:\n    image: postgres:${TAG}\n"
+                .to_string(),
+            "services:\n  cache:\n    image: postgres:22.04\n  web:\n    image: nginx\n"
+                .to_string(),
+        ],
+        &lookup,
+    )
+    .unwrap();
+
+    assert_eq!(files.images(), vec!["postgres:22.04", "nginx"]);
+}
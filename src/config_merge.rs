@@ -0,0 +1,177 @@
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+use super::errors::*;
+
+// Where an `extends` entry points to
+#[derive(Debug, PartialEq)]
+pub enum BaseConfig {
+    // a devcontainer.json on disk, relative to the config that extends it
+    File(PathBuf),
+    // a published template, e.g. ghcr.io/devcontainers/templates/rust:latest
+    Template(String),
+}
+
+impl BaseConfig {
+    pub fn parse(reference: &str, dir: Option<&Path>) -> Result<Self, Error> {
+        let is_file = reference.starts_with('.')
+            || reference.starts_with('/')
+            || reference.ends_with(".json")
+            || reference.ends_with(".jsonc");
+
+        if !is_file {
+            return Ok(BaseConfig::Template(reference.to_string()));
+        }
+
+        match dir {
+            Some(dir) => Ok(BaseConfig::File(dir.join(reference))),
+            None => Err(Error::InvalidConfig(format!(
+                "extends: '{}' can't be resolved from a published template",
+                reference
+            ))),
+        }
+    }
+}
+
+// The `extends` entries of a config, a string or an array of strings. Later entries override
+// earlier ones
+pub fn get_extends(config: &Value, dir: Option<&Path>) -> Result<Vec<BaseConfig>, Error> {
+    let invalid = || Error::InvalidConfig("extends must be a string or an array of strings".into());
+
+    match config.get("extends") {
+        None | Some(Value::Null) => Ok(vec![]),
+        Some(Value::String(reference)) => Ok(vec![BaseConfig::parse(reference, dir)?]),
+        Some(Value::Array(references)) => references
+            .iter()
+            .map(|r| r.as_str().ok_or_else(invalid))
+            .map(|r| r.and_then(|r| BaseConfig::parse(r, dir)))
+            .collect(),
+        Some(_) => Err(invalid()),
+    }
+}
+
+// Layers `overlay` on top of `base`:
+// - objects are merged key by key, recursively
+// - arrays, strings, numbers and booleans in the overlay replace the base value
+// - a null in the overlay removes the key
+pub fn merge(base: Value, overlay: Value) -> Value {
+    match (base, overlay) {
+        (Value::Object(mut base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+
+                let merged = match base.remove(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => strip_nulls(value),
+                };
+                base.insert(key, merged);
+            }
+
+            Value::Object(base)
+        }
+        (_, overlay) => strip_nulls(overlay),
+    }
+}
+
+// Top level arrays the local override adds to instead of replacing
+const LOCAL_APPENDED_KEYS: &[&str] = &[
+    "mounts",
+    "forwardPorts",
+    "appPort",
+    "runArgs",
+    "capAdd",
+    "securityOpt",
+];
+
+// devcontainer.json -> devcontainer.local.json
+pub fn local_filename(filename: &str) -> String {
+    match filename.strip_suffix(".json") {
+        Some(stem) => format!("{}.local.json", stem),
+        None => format!("{}.local", filename),
+    }
+}
+
+// Layers a personal devcontainer.local.json on top of the shared config. Same as `merge`, except
+// that the entries of `mounts`, `forwardPorts`, `appPort`, `runArgs`, `capAdd` and `securityOpt`
+// are added to the shared ones (skipping duplicates), so a personal mount or port doesn't drop
+// the shared ones
+pub fn merge_local(config: Value, local: Value) -> Result<Value, Error> {
+    let mut local = match local {
+        Value::Object(local) => local,
+        _ => {
+            return Err(Error::InvalidConfig(
+                "devcontainer.local.json must be an object".to_string(),
+            ))
+        }
+    };
+
+    if local.contains_key("extends") {
+        return Err(Error::InvalidConfig(
+            "extends is not supported in devcontainer.local.json".to_string(),
+        ));
+    }
+
+    for key in LOCAL_APPENDED_KEYS.iter() {
+        let (shared, personal) = match (config.get(*key), local.get(*key)) {
+            (Some(Value::Array(shared)), Some(Value::Array(personal))) => (shared, personal),
+            _ => continue,
+        };
+
+        let mut combined = shared.clone();
+        for value in personal {
+            if !combined.contains(value) {
+                combined.push(value.clone());
+            }
+        }
+        local.insert(key.to_string(), Value::Array(combined));
+    }
+
+    Ok(merge(config, Value::Object(local)))
+}
+
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect::<Map<String, Value>>(),
+        ),
+        other => other,
+    }
+}
+
+// Paths in a base config are relative to its own folder. They are made absolute so they still
+// point to the same files once merged into a config in another folder
+pub fn rebase_paths(config: &mut Value, dir: &Path) {
+    let rebase = |value: &mut Value| {
+        if let Some(path) = value.as_str() {
+            *value = Value::String(dir.join(path).to_string_lossy().to_string());
+        }
+    };
+
+    if let Some(build) = config.get_mut("build") {
+        for key in ["dockerfile", "context"].iter() {
+            if let Some(value) = build.get_mut(*key) {
+                rebase(value);
+            }
+        }
+    }
+
+    match config.get_mut("dockerComposeFile") {
+        Some(Value::Array(files)) => files.iter_mut().for_each(rebase),
+        Some(value) => rebase(value),
+        None => {}
+    }
+}
+
+// Identifies a config file in the `extends` chain, whatever relative path led to it
+pub fn canonical_path(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,152 @@
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use super::config_merge::*;
+
+#[test]
+fn test_merge() {
+    let base = json!({
+        "image": "rust:latest",
+        "forwardPorts": [3000, 8080],
+        "remoteEnv": { "EDITOR": "vim", "PAGER": "less" },
+        "customizations": { "vscode": { "extensions": ["a"] } },
+        "postCreateCommand": "cargo build",
+    });
+    let overlay = json!({
+        "forwardPorts": [5432],
+        "remoteEnv": { "EDITOR": "nvim", "LANG": "C.UTF-8" },
+        "customizations": { "neovim": { "plugins": ["b"] } },
+        "postCreateCommand": null,
+        "name": "project",
+    });
+
+    assert_eq!(
+        merge(base, overlay),
+        json!({
+            "image": "rust:latest",
+            "forwardPorts": [5432],
+            "remoteEnv": { "EDITOR": "nvim", "PAGER": "less", "LANG": "C.UTF-8" },
+            "customizations": {
+                "vscode": { "extensions": ["a"] },
+                "neovim": { "plugins": ["b"] },
+            },
+            "name": "project",
+        })
+    );
+
+    // an object replaces a scalar and loses its nulls
+    assert_eq!(
+        merge(
+            json!({ "build": "x" }),
+            json!({ "build": { "dockerfile": "D", "target": null } })
+        ),
+        json!({ "build": { "dockerfile": "D" } })
+    );
+}
+
+#[test]
+fn test_get_extends() {
+    let dir = Path::new("/repo/.devcontainer");
+
+    assert_eq!(get_extends(&json!({}), Some(dir)).unwrap(), vec![]);
+
+    assert_eq!(
+        get_extends(
+            &json!({ "extends": "../base/devcontainer.json" }),
+            Some(dir)
+        )
+        .unwrap(),
+        vec![BaseConfig::File(PathBuf::from(
+            "/repo/.devcontainer/../base/devcontainer.json"
+        ))]
+    );
+
+    assert_eq!(
+        get_extends(
+            &json!({ "extends": ["./base.jsonc", "ghcr.io/devcontainers/templates/rust:latest"] }),
+            Some(dir)
+        )
+        .unwrap(),
+        vec![
+            BaseConfig::File(PathBuf::from("/repo/.devcontainer/./base.jsonc")),
+            BaseConfig::Template("ghcr.io/devcontainers/templates/rust:latest".to_string()),
+        ]
+    );
+
+    assert!(get_extends(&json!({ "extends": 1 }), Some(dir)).is_err());
+    assert!(get_extends(&json!({ "extends": [true] }), Some(dir)).is_err());
+    // a template can't point to files
+    assert!(get_extends(&json!({ "extends": "./base.json" }), None).is_err());
+}
+
+#[test]
+fn test_rebase_paths() {
+    let mut config = json!({
+        "build": { "dockerfile": "Dockerfile", "context": ".." },
+        "dockerComposeFile": ["compose.yml", "compose.dev.yml"],
+    });
+    rebase_paths(&mut config, Path::new("/shared"));
+
+    assert_eq!(
+        config,
+        json!({
+            "build": { "dockerfile": "/shared/Dockerfile", "context": "/shared/.." },
+            "dockerComposeFile": ["/shared/compose.yml", "/shared/compose.dev.yml"],
+        })
+    );
+
+    let mut config = json!({ "dockerComposeFile": "compose.yml" });
+    rebase_paths(&mut config, Path::new("/shared"));
+    assert_eq!(
+        config,
+        json!({ "dockerComposeFile": "/shared/compose.yml" })
+    );
+}
+
+#[test]
+fn test_local_filename() {
+    assert_eq!(
+        local_filename("devcontainer.json"),
+        "devcontainer.local.json"
+    );
+    assert_eq!(local_filename("custom"), "custom.local");
+}
+
+#[test]
+fn test_merge_local() {
+    let config = json!({
+        "image": "rust:latest",
+        "mounts": ["source=cache,target=/cache,type=volume"],
+        "forwardPorts": [8080],
+        "remoteEnv": { "EDITOR": "vim" },
+        "postCreateCommand": "cargo build",
+    });
+    let local = json!({
+        "mounts": ["source=/home/me/.ssh,target=/home/vscode/.ssh,type=bind"],
+        "forwardPorts": [8080, 9229],
+        "remoteEnv": { "EDITOR": "nvim" },
+        "postCreateCommand": null,
+    });
+
+    assert_eq!(
+        merge_local(config.clone(), local).unwrap(),
+        json!({
+            "image": "rust:latest",
+            "mounts": [
+                "source=cache,target=/cache,type=volume",
+                "source=/home/me/.ssh,target=/home/vscode/.ssh,type=bind",
+            ],
+            "forwardPorts": [8080, 9229],
+            "remoteEnv": { "EDITOR": "nvim" },
+        })
+    );
+
+    // a personal list replaces a missing shared one
+    assert_eq!(
+        merge_local(json!({}), json!({ "runArgs": ["--init"] })).unwrap(),
+        json!({ "runArgs": ["--init"] })
+    );
+
+    assert!(merge_local(config.clone(), json!([])).is_err());
+    assert!(merge_local(config, json!({ "extends": "../base.json" })).is_err());
+}
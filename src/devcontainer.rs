@@ -1,14 +1,20 @@
-use serde::{de, Deserialize, Deserializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 
 use crate::errors::*;
+use crate::features::{self, ImageMetadata};
+use crate::host_requirements;
+use crate::resources::ResourceLimits;
+use crate::run_args;
+use crate::substitution;
 
 fn default_true() -> bool {
     true
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, Clone)]
 pub struct DevContainer {
     pub name: Option<String>,
 
@@ -39,15 +45,33 @@ pub struct DevContainer {
     #[serde(rename = "workspaceMount")]
     pub workspace_mount: Option<String>,
 
+    #[serde(rename = "workspaceFolder")]
+    pub workspace_folder: Option<String>,
+
+    // Docker network the container joins, created when missing. One per project by default
+    pub network: Option<String>,
+
+    // only --memory, --cpus, --shm-size, --ulimit, --init, --add-host, --restart and --hostname
+    // are used
     #[serde(rename = "runArgs")]
     pub run_args: Option<Vec<String>>,
 
+    // Run an init process (tini) as PID 1, forwarding signals and reaping zombies
+    pub init: Option<bool>,
+
     #[serde(rename = "overrideCommand", default = "default_true")]
     pub override_command: bool,
 
+    // os/arch of the images, not part of the spec. The user settings and --platform override it
+    pub platform: Option<String>,
+
     #[serde(rename = "shutdownAction")]
     pub shutdown_action: Option<ShutdownAction>,
 
+    // Whether removing the container/compose project also removes its volumes
+    #[serde(rename = "shutdownRemoveVolumes")]
+    pub shutdown_remove_volumes: Option<bool>,
+
     // Docker compose stuff
     #[serde(rename = "dockerComposeFile")]
     pub docker_compose_file: Option<DockerComposeFile>,
@@ -57,38 +81,105 @@ pub struct DevContainer {
     #[serde(rename = "runServices")]
     pub run_services: Option<Vec<String>>,
 
+    // the compose project, instead of the one named after the devcontainer
+    #[serde(rename = "composeProjectName")]
+    pub compose_project_name: Option<String>,
+
     #[serde(rename = "forwardPorts")]
-    pub forward_ports: Option<Vec<i32>>,
+    pub forward_ports: Option<Vec<ForwardPort>>,
+
+    // port ("3000") or port range ("40000-55000") -> attributes
+    #[serde(rename = "portsAttributes")]
+    pub ports_attributes: Option<BTreeMap<String, PortAttributes>>,
+
+    #[serde(rename = "otherPortsAttributes")]
+    pub other_ports_attributes: Option<PortAttributes>,
 
     #[serde(rename = "postCreateCommand")]
-    pub post_create_command: Option<CommandLineVec>,
+    pub post_create_command: Option<LifecycleCommand>,
 
     #[serde(rename = "postStartCommand")]
-    pub post_start_command: Option<CommandLineVec>,
+    pub post_start_command: Option<LifecycleCommand>,
 
     #[serde(rename = "postAttachCommand")]
-    pub post_attach_command: Option<CommandLineVec>,
+    pub post_attach_command: Option<LifecycleCommand>,
 
     #[serde(rename = "initializeCommand")]
-    pub initialize_command: Option<CommandLineVec>,
+    pub initialize_command: Option<LifecycleCommand>,
+
+    #[serde(rename = "commandShell")]
+    pub command_shell: Option<CommandShell>,
+
+    #[serde(rename = "hostRequirements")]
+    pub host_requirements: Option<HostRequirements>,
+
+    // Last lifecycle command `up` blocks on, the ones after it run in the background
+    #[serde(rename = "waitFor")]
+    pub wait_for: Option<WaitFor>,
 
-    #[serde(rename = "devPort", default)]
-    pub dev_port: i32,
+    // Dev Container Features: reference -> options (or a version string)
+    pub features: Option<BTreeMap<String, serde_json::Value>>,
+
+    #[serde(rename = "overrideFeatureInstallOrder")]
+    pub override_feature_install_order: Option<Vec<String>>,
+
+    // Tool specific settings ("vscode", "neovim", ...), kept as is for the tools to read
+    pub customizations: Option<BTreeMap<String, serde_json::Value>>,
+
+    // Deprecated, the port of the VS Code server. Only read to warn about it: forwardPorts and
+    // appPort publish ports
+    #[serde(rename = "devPort")]
+    pub dev_port: Option<serde_json::Value>,
+
+    // devcontainer.metadata entries of the image, merged in by merge_image_metadata
+    #[serde(skip)]
+    pub image_metadata: Vec<ImageMetadata>,
+
+    // files merged into this config (`extends`, devcontainer.local.json), set by the project
+    // when loading
+    #[serde(skip)]
+    pub merged_files: Vec<PathBuf>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct BuildOpts {
-    #[serde(alias = "dockerFile")]
+    // empty next to dockerComposeFile, where only the args are used
+    #[serde(alias = "dockerFile", default)]
     pub dockerfile: String,
 
+    // relative to the devcontainer.json folder, like the dockerfile
     pub context: Option<String>,
 
     pub args: Option<BTreeMap<String, String>>,
 
     pub target: Option<String>,
+
+    #[serde(rename = "cacheFrom")]
+    pub cache_from: Option<CacheFrom>,
+
+    // Forward the ssh agent to `RUN --mount=type=ssh`, like `docker build --ssh default`
+    #[serde(rename = "sshForward", default)]
+    pub ssh_forward: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum CacheFrom {
+    Image(String),
+    Images(Vec<String>),
+}
+
+impl BuildOpts {
+    pub fn get_cache_from(&self) -> Vec<String> {
+        match self.cache_from.as_ref() {
+            Some(CacheFrom::Image(image)) => vec![image.clone()],
+            Some(CacheFrom::Images(images)) => images.clone(),
+            None => vec![],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
 #[serde(untagged)]
 pub enum AppPort {
     Port(u32),
@@ -96,25 +187,218 @@ pub enum AppPort {
     PortStr(String),
 }
 
-#[derive(Deserialize)]
+// forwardPorts entry: 8080 (same port on both sides), "8000:8080" (host:container) or
+// "db:5432" (port of another compose service)
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForwardPort {
+    pub service: Option<String>,
+    pub host_port: u16,
+    pub container_port: u16,
+}
+
+impl ForwardPort {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidConfig(format!("Invalid forward port: '{}'", spec));
+        let parse_port = |p: &str| p.trim().parse::<u16>().map_err(|_| invalid());
+
+        match spec.split_once(':') {
+            None => {
+                let port = parse_port(spec)?;
+                Ok(ForwardPort {
+                    service: None,
+                    host_port: port,
+                    container_port: port,
+                })
+            }
+            Some((left, right)) => {
+                let container_port = parse_port(right)?;
+                match left.trim().parse::<u16>() {
+                    Ok(host_port) => Ok(ForwardPort {
+                        service: None,
+                        host_port,
+                        container_port,
+                    }),
+                    Err(_) if !left.trim().is_empty() => Ok(ForwardPort {
+                        service: Some(left.trim().to_string()),
+                        host_port: container_port,
+                        container_port,
+                    }),
+                    Err(_) => Err(invalid()),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ForwardPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.service.as_ref() {
+            Some(service) => write!(f, "{}:{}", service, self.container_port),
+            None if self.host_port == self.container_port => write!(f, "{}", self.host_port),
+            None => write!(f, "{}:{}", self.host_port, self.container_port),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ForwardPort {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Port(u16),
+            Spec(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Port(port) => Ok(ForwardPort {
+                service: None,
+                host_port: port,
+                container_port: port,
+            }),
+            Raw::Spec(spec) => ForwardPort::parse(&spec).map_err(de::Error::custom),
+        }
+    }
+}
+
+// Plain ports are written back as numbers, like they are usually given
+impl Serialize for ForwardPort {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.service.is_none() && self.host_port == self.container_port {
+            serializer.serialize_u16(self.host_port)
+        } else {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    // http/https (tcp), or udp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+
+    // "ignore" keeps the port from being published
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_auto_forward: Option<String>,
+
+    // `up` fails when the host port is taken, otherwise the port is published on a free one
+    #[serde(default)]
+    pub require_local_port: bool,
+
+    // host address the port is published on, overrides bindAddress of the user settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<IpAddr>,
+}
+
+impl PortAttributes {
+    // Protocol of the docker port binding
+    pub fn docker_protocol(&self) -> &'static str {
+        match self.protocol.as_deref() {
+            Some(p) if p.eq_ignore_ascii_case("udp") => "udp",
+            _ => "tcp",
+        }
+    }
+
+    pub fn is_ignored(&self) -> bool {
+        self.on_auto_forward.as_deref() == Some("ignore")
+    }
+}
+
+#[derive(Deserialize, Clone)]
 #[serde(untagged)]
 pub enum DockerComposeFile {
     File(String),
     Files(Vec<String>),
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum CommandLineVec {
     Line(String),
     Args(Vec<String>),
 }
 
-#[derive(Debug, PartialEq)]
+// A lifecycle command, or an object of named commands that run in parallel
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum LifecycleCommand {
+    Single(CommandLineVec),
+    Parallel(BTreeMap<String, CommandLineVec>),
+}
+
+// Shell used to run the string form of the lifecycle commands.
+// Either one shell for all hooks or a map of hook name (e.g. "postCreateCommand") to shell
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum CommandShell {
+    Shell(CommandLineVec),
+    PerHook(BTreeMap<String, CommandLineVec>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ShutdownAction {
     None,
     StopContainer,
     StopCompose,
+    RemoveContainer,
+    DownCompose,
+}
+
+// Minimum resources the docker host must provide. Sizes are strings like "4gb" or "512mb"
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct HostRequirements {
+    pub cpus: Option<u64>,
+    pub memory: Option<String>,
+    pub storage: Option<String>,
+    pub gpu: Option<GpuRequirement>,
+}
+
+// gpu: true/false, "optional" (used when available) or { cores, memory }
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum GpuRequirement {
+    Required(bool),
+    Optional(String),
+    Detailed {
+        cores: Option<u64>,
+        memory: Option<String>,
+    },
+}
+
+impl GpuRequirement {
+    pub fn is_required(&self) -> bool {
+        match self {
+            GpuRequirement::Required(required) => *required,
+            GpuRequirement::Optional(_) => false,
+            GpuRequirement::Detailed { .. } => true,
+        }
+    }
+
+    pub fn is_wanted(&self) -> bool {
+        !matches!(self, GpuRequirement::Required(false))
+    }
+}
+
+// Lifecycle commands in the order they run
+#[derive(Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::enum_variant_names)]
+pub enum WaitFor {
+    InitializeCommand,
+    OnCreateCommand,
+    UpdateContentCommand,
+    PostCreateCommand,
+    PostStartCommand,
 }
 
 // Specify which mode should this devcontainer operate on
@@ -134,6 +418,8 @@ impl<'de> Deserialize<'de> for ShutdownAction {
             "none" => ShutdownAction::None,
             "stopcontainer" => ShutdownAction::StopContainer,
             "stopcompose" => ShutdownAction::StopCompose,
+            "removecontainer" => ShutdownAction::RemoveContainer,
+            "downcompose" => ShutdownAction::DownCompose,
             other => {
                 return Err(de::Error::custom(format!(
                     "Invalid shutdown action '{}'",
@@ -145,23 +431,53 @@ impl<'de> Deserialize<'de> for ShutdownAction {
     }
 }
 
+impl Serialize for ShutdownAction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(match self {
+            ShutdownAction::None => "none",
+            ShutdownAction::StopContainer => "stopContainer",
+            ShutdownAction::StopCompose => "stopCompose",
+            ShutdownAction::RemoveContainer => "removeContainer",
+            ShutdownAction::DownCompose => "downCompose",
+        })
+    }
+}
+
 impl DevContainer {
     pub fn get_mode(&self) -> Mode {
         if self.image.is_some() {
             Mode::Image
-        } else if self.build.is_some() {
+        } else if self.docker_compose_file.is_none() && self.build.is_some() {
             Mode::Build
         } else {
             Mode::Compose
         }
     }
 
+    // Fields that are accepted but do nothing
+    pub fn deprecation_warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+
+        if let Some(port) = self.dev_port.as_ref() {
+            warnings.push(format!(
+                "'devPort' is deprecated and ignored, use 'forwardPorts': [{}] to publish the port. `upgrade-config` removes it",
+                port
+            ));
+        }
+
+        warnings
+    }
+
     pub fn validate(&self) -> Result<(), Error> {
-        // image conflicts with docker_compose_file
-        let sources = vec![
+        // image conflicts with docker_compose_file. The build args of a compose project go to
+        // its service
+        let sources = [
             self.image.is_some(),
             self.docker_compose_file.is_some(),
-            self.build.is_some(),
+            self.build.is_some() && self.docker_compose_file.is_none(),
         ];
         if sources.iter().filter(|v| **v).count() > 1 {
             return Err(Error::InvalidConfig(
@@ -174,6 +490,61 @@ impl DevContainer {
             ));
         }
 
+        if let Some(folder) = self.workspace_folder.as_ref() {
+            if !folder.starts_with('/') {
+                return Err(Error::InvalidConfig(format!(
+                    "workspaceFolder must be an absolute path: '{}'",
+                    folder
+                )));
+            }
+        }
+
+        if let Some(args) = self.run_args.as_ref() {
+            ResourceLimits::from_run_args(args)?.apply(&mut Default::default())?;
+            if let Some(policy) = run_args::flag_value(args, &["--restart"]) {
+                run_args::parse_restart_policy(&policy)?;
+            }
+        }
+
+        // os[/arch[/variant]]
+        if let Some(platform) = self.platform.as_ref() {
+            let parts: Vec<&str> = platform.split('/').collect();
+            if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+                return Err(Error::InvalidConfig(format!(
+                    "Invalid platform: '{}'",
+                    platform
+                )));
+            }
+        }
+
+        if let Some(reqs) = self.host_requirements.as_ref() {
+            let gpu_memory = match reqs.gpu.as_ref() {
+                Some(GpuRequirement::Optional(value)) if value != "optional" => {
+                    return Err(Error::InvalidConfig(format!(
+                        "Invalid hostRequirements gpu: '{}'",
+                        value
+                    )));
+                }
+                Some(GpuRequirement::Detailed { memory, .. }) => memory.as_ref(),
+                _ => None,
+            };
+
+            for size in [reqs.memory.as_ref(), reqs.storage.as_ref(), gpu_memory]
+                .iter()
+                .flatten()
+            {
+                host_requirements::parse_size(size)?;
+            }
+        }
+
+        // references between entries must not loop
+        for envs in [self.container_env.as_ref(), self.remote_env.as_ref()]
+            .iter()
+            .flatten()
+        {
+            substitution::resolve_env_references(envs, None)?;
+        }
+
         if let Some(img) = self.image.as_ref() {
             if img.trim().is_empty() {
                 return Err(Error::InvalidConfig(format!("Invalid image: '{}'", img)));
@@ -181,7 +552,13 @@ impl DevContainer {
         }
 
         if let Some(opts) = self.build.as_ref() {
-            if opts.dockerfile.trim().is_empty() {
+            if self.docker_compose_file.is_some() {
+                if !opts.dockerfile.is_empty() {
+                    return Err(Error::InvalidConfig(
+                        "Only build.args can be used with dockerComposeFile, the service builds from its own dockerfile".to_string(),
+                    ));
+                }
+            } else if opts.dockerfile.trim().is_empty() {
                 return Err(Error::InvalidConfig(format!(
                     "Invalid docker file: '{}'",
                     opts.dockerfile
@@ -211,12 +588,103 @@ impl DevContainer {
         Ok(())
     }
 
-    pub fn get_name(&self, path: &PathBuf) -> String {
+    // portsAttributes entry matching the port, otherPortsAttributes otherwise
+    pub fn get_port_attributes(&self, port: u16) -> PortAttributes {
+        let matches = |key: &str| match key.split_once('-') {
+            Some((from, to)) => match (from.trim().parse::<u16>(), to.trim().parse::<u16>()) {
+                (Ok(from), Ok(to)) => from <= port && port <= to,
+                _ => false,
+            },
+            None => key.trim().parse::<u16>() == Ok(port),
+        };
+
+        self.ports_attributes
+            .iter()
+            .flatten()
+            .find(|(key, _)| matches(key))
+            .map(|(_, attributes)| attributes)
+            .or(self.other_ports_attributes.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn get_name(&self, path: &Path) -> String {
         self.name
             .as_ref()
             .map(|s| s.to_string())
             .unwrap_or(path.file_name().unwrap().to_string_lossy().to_string())
     }
+
+    // Puts the devcontainer.metadata entries of the image under this config. Users are only taken
+    // from the image when not set here, envs, mounts and ports are combined (this config wins)
+    // and the image lifecycle commands run before the ones of this config
+    pub fn merge_image_metadata(&mut self, entries: Vec<ImageMetadata>) {
+        let mut container_user = None;
+        let mut remote_user = None;
+        let mut container_env = BTreeMap::new();
+        let mut remote_env = BTreeMap::new();
+        let mut mounts = vec![];
+        let mut forward_ports: Vec<ForwardPort> = vec![];
+
+        for entry in entries.iter() {
+            container_user = entry.container_user.clone().or(container_user);
+            remote_user = entry.remote_user.clone().or(remote_user);
+            container_env.extend(entry.container.container_env.clone().unwrap_or_default());
+            remote_env.extend(entry.remote_env.clone().unwrap_or_default());
+            mounts.extend(
+                entry
+                    .container
+                    .mounts
+                    .iter()
+                    .flatten()
+                    .filter_map(features::mount_to_str),
+            );
+            for port in entry.forward_ports.iter().flatten() {
+                if !forward_ports.contains(port) {
+                    forward_ports.push(port.clone());
+                }
+            }
+        }
+
+        self.container_user = self.container_user.take().or(container_user);
+        self.remote_user = self.remote_user.take().or(remote_user);
+
+        if let Some(env) = self.container_env.take() {
+            container_env.extend(env);
+        }
+        if !container_env.is_empty() {
+            self.container_env = Some(container_env);
+        }
+
+        if let Some(env) = self.remote_env.take() {
+            remote_env.extend(env);
+        }
+        if !remote_env.is_empty() {
+            self.remote_env = Some(remote_env);
+        }
+
+        mounts.extend(self.mounts.take().unwrap_or_default());
+        if !mounts.is_empty() {
+            self.mounts = Some(mounts);
+        }
+
+        for port in self.forward_ports.take().unwrap_or_default() {
+            if !forward_ports.contains(&port) {
+                forward_ports.push(port);
+            }
+        }
+        if !forward_ports.is_empty() {
+            self.forward_ports = Some(forward_ports);
+        }
+
+        self.image_metadata = entries;
+    }
+
+    pub fn get_customizations(&self, tool: &str) -> Option<&serde_json::Value> {
+        self.customizations
+            .as_ref()
+            .and_then(|customizations| customizations.get(tool))
+    }
 }
 
 impl CommandLineVec {
@@ -226,4 +694,58 @@ impl CommandLineVec {
             CommandLineVec::Args(args) => args.clone(),
         }
     }
+
+    // String commands are handed to the shell as a single argument, arrays are executed as is
+    pub fn to_exec_args(&self, shell: &[String]) -> Vec<String> {
+        match self {
+            CommandLineVec::Line(line) => {
+                let mut args = shell.to_vec();
+                args.push(line.clone());
+                args
+            }
+            CommandLineVec::Args(args) => args.clone(),
+        }
+    }
+}
+
+impl LifecycleCommand {
+    // The commands of several origins, e.g. the project and the user settings, as one object.
+    // Named commands are prefixed with their origin: "user:format"
+    pub fn merge(commands: &[(&str, &LifecycleCommand)]) -> LifecycleCommand {
+        let mut merged = BTreeMap::new();
+        for (origin, command) in commands {
+            match command {
+                LifecycleCommand::Single(cmd) => {
+                    merged.insert(origin.to_string(), cmd.clone());
+                }
+                LifecycleCommand::Parallel(cmds) => {
+                    for (name, cmd) in cmds {
+                        merged.insert(format!("{}:{}", origin, name), cmd.clone());
+                    }
+                }
+            }
+        }
+
+        LifecycleCommand::Parallel(merged)
+    }
+
+    // The exec args of each command with its name. A single command is named `name`
+    pub fn to_named_exec_args(&self, name: &str, shell: &[String]) -> Vec<(String, Vec<String>)> {
+        match self {
+            LifecycleCommand::Single(cmd) => vec![(name.to_string(), cmd.to_exec_args(shell))],
+            LifecycleCommand::Parallel(cmds) => cmds
+                .iter()
+                .map(|(name, cmd)| (name.clone(), cmd.to_exec_args(shell)))
+                .collect(),
+        }
+    }
+}
+
+impl CommandShell {
+    pub fn for_hook(&self, hook_name: &str) -> Option<Vec<String>> {
+        match self {
+            CommandShell::Shell(shell) => Some(shell.to_args_vec()),
+            CommandShell::PerHook(shells) => shells.get(hook_name).map(|s| s.to_args_vec()),
+        }
+    }
 }
@@ -69,3 +69,368 @@ fn test_invalid_docker_compose_service_empty_string() {
     };
     dc.validate().unwrap()
 }
+
+#[test]
+fn test_docker_compose_build_args() {
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            dockerComposeFile: "docker-compose.yaml",
+            service: "app",
+            build: { args: { VARIANT: "bullseye" } },
+        }"#,
+    )
+    .unwrap();
+    dc.validate().unwrap();
+    assert!(matches!(dc.get_mode(), Mode::Compose));
+
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            dockerComposeFile: "docker-compose.yaml",
+            service: "app",
+            build: { dockerfile: "Dockerfile" },
+        }"#,
+    )
+    .unwrap();
+    assert!(dc.validate().is_err());
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ build: { args: { VARIANT: "bullseye" } } }"#).unwrap();
+    assert!(dc.validate().is_err());
+}
+
+#[test]
+fn test_command_line_exec_args() {
+    let shell = vec!["/bin/sh".to_string(), "-c".to_string()];
+
+    let line = CommandLineVec::Line("echo hello && echo world".to_string());
+    assert_eq!(
+        line.to_exec_args(&shell),
+        vec!["/bin/sh", "-c", "echo hello && echo world"]
+    );
+
+    let args = CommandLineVec::Args(vec!["echo".to_string(), "hello world".to_string()]);
+    assert_eq!(args.to_exec_args(&shell), vec!["echo", "hello world"]);
+}
+
+#[test]
+fn test_command_shell() {
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            image: "myimage",
+            commandShell: { postCreateCommand: "bash -lc" },
+        }"#,
+    )
+    .unwrap();
+    let shell = dc.command_shell.unwrap();
+
+    assert_eq!(
+        shell.for_hook("postCreateCommand"),
+        Some(vec!["bash".to_string(), "-lc".to_string()])
+    );
+    assert_eq!(shell.for_hook("postStartCommand"), None);
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", commandShell: ["zsh", "-c"] }"#).unwrap();
+    assert_eq!(
+        dc.command_shell.unwrap().for_hook("postStartCommand"),
+        Some(vec!["zsh".to_string(), "-c".to_string()])
+    );
+}
+
+#[test]
+fn test_shutdown_action() {
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", shutdownAction: "removeContainer" }"#).unwrap();
+    assert_eq!(dc.shutdown_action, Some(ShutdownAction::RemoveContainer));
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", shutdownAction: "downCompose" }"#).unwrap();
+    assert_eq!(dc.shutdown_action, Some(ShutdownAction::DownCompose));
+
+    let res: Result<DevContainer, _> =
+        json5::from_str(r#"{ image: "myimage", shutdownAction: "explode" }"#);
+    assert!(res.is_err());
+}
+
+#[test]
+#[should_panic]
+fn test_relative_workspace_folder() {
+    let dc = DevContainer {
+        image: Some("myimage".to_string()),
+        workspace_folder: Some("workspace".to_string()),
+        ..Default::default()
+    };
+    dc.validate().unwrap()
+}
+
+#[test]
+fn test_forward_ports() {
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "rust", forwardPorts: [3000, "8000:8080", "db:5432"] }"#)
+            .unwrap();
+
+    assert_eq!(
+        dc.forward_ports.unwrap(),
+        vec![
+            ForwardPort {
+                service: None,
+                host_port: 3000,
+                container_port: 3000,
+            },
+            ForwardPort {
+                service: None,
+                host_port: 8000,
+                container_port: 8080,
+            },
+            ForwardPort {
+                service: Some("db".to_string()),
+                host_port: 5432,
+                container_port: 5432,
+            },
+        ]
+    );
+
+    assert!(ForwardPort::parse("abc").is_err());
+    assert!(ForwardPort::parse(":8080").is_err());
+    assert!(ForwardPort::parse("db:abc").is_err());
+
+    assert_eq!(
+        serde_json::to_value(ForwardPort::parse("3000").unwrap()).unwrap(),
+        serde_json::json!(3000)
+    );
+    assert_eq!(
+        serde_json::to_value(ForwardPort::parse("8000:8080").unwrap()).unwrap(),
+        serde_json::json!("8000:8080")
+    );
+}
+
+#[test]
+fn test_port_attributes() {
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            image: "rust",
+            portsAttributes: {
+                "3000": { label: "Frontend", onAutoForward: "ignore" },
+                "5000-5010": { protocol: "udp", requireLocalPort: true, bindAddress: "0.0.0.0" },
+            },
+            otherPortsAttributes: { label: "Other" },
+        }"#,
+    )
+    .unwrap();
+
+    let frontend = dc.get_port_attributes(3000);
+    assert_eq!(frontend.label.as_deref(), Some("Frontend"));
+    assert!(frontend.is_ignored());
+    assert_eq!(frontend.docker_protocol(), "tcp");
+
+    let ranged = dc.get_port_attributes(5005);
+    assert_eq!(ranged.docker_protocol(), "udp");
+    assert!(ranged.require_local_port);
+    assert!(!frontend.require_local_port);
+    assert_eq!(ranged.bind_address, Some("0.0.0.0".parse().unwrap()));
+    assert_eq!(frontend.bind_address, None);
+
+    assert_eq!(dc.get_port_attributes(8080).label.as_deref(), Some("Other"));
+    assert_eq!(
+        DevContainer::default().get_port_attributes(8080),
+        PortAttributes::default()
+    );
+}
+
+#[test]
+fn test_wait_for() {
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", waitFor: "postCreateCommand" }"#).unwrap();
+    assert_eq!(dc.wait_for, Some(WaitFor::PostCreateCommand));
+    assert!(WaitFor::UpdateContentCommand < WaitFor::PostCreateCommand);
+
+    let res: Result<DevContainer, _> =
+        json5::from_str(r#"{ image: "myimage", waitFor: "postAttachCommand" }"#);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_host_requirements() {
+    let dc: DevContainer = json5::from_str(
+        r#"{ image: "myimage", hostRequirements: { cpus: 4, memory: "8gb", gpu: "optional" } }"#,
+    )
+    .unwrap();
+    dc.validate().unwrap();
+    let reqs = dc.host_requirements.unwrap();
+    assert_eq!(reqs.cpus, Some(4));
+    assert_eq!(
+        reqs.gpu,
+        Some(GpuRequirement::Optional("optional".to_string()))
+    );
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", hostRequirements: { gpu: { cores: 2 } } }"#)
+            .unwrap();
+    assert!(dc.host_requirements.unwrap().gpu.unwrap().is_required());
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", hostRequirements: { memory: "lots" } }"#).unwrap();
+    assert!(dc.validate().is_err());
+
+    let dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", hostRequirements: { gpu: "maybe" } }"#).unwrap();
+    assert!(dc.validate().is_err());
+}
+
+#[test]
+fn test_customizations() {
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            image: "myimage",
+            customizations: {
+                vscode: { extensions: ["rust-lang.rust-analyzer"] },
+                neovim: { plugins: ["nvim-lspconfig"] },
+            },
+        }"#,
+    )
+    .unwrap();
+    dc.validate().unwrap();
+
+    assert_eq!(
+        dc.get_customizations("neovim").unwrap()["plugins"][0],
+        "nvim-lspconfig"
+    );
+    assert!(dc.get_customizations("emacs").is_none());
+}
+
+#[test]
+fn test_build_cache_from() {
+    let dc: DevContainer = json5::from_str(
+        r#"{ build: { dockerfile: "Dockerfile", context: "..", cacheFrom: "ghcr.io/me/cache" } }"#,
+    )
+    .unwrap();
+    let build = dc.build.unwrap();
+    assert_eq!(build.context, Some("..".to_string()));
+    assert_eq!(build.get_cache_from(), vec!["ghcr.io/me/cache".to_string()]);
+
+    let dc: DevContainer = json5::from_str(
+        r#"{ build: { dockerfile: "Dockerfile", cacheFrom: ["a", "b"], args: { VARIANT: "1" } } }"#,
+    )
+    .unwrap();
+    let build = dc.build.unwrap();
+    assert_eq!(build.get_cache_from().len(), 2);
+    assert_eq!(build.args.unwrap()["VARIANT"], "1");
+}
+
+#[test]
+fn test_build_ssh_forward() {
+    let dc: DevContainer =
+        json5::from_str(r#"{ build: { dockerfile: "Dockerfile", sshForward: true } }"#).unwrap();
+    assert!(dc.build.unwrap().ssh_forward);
+
+    let dc: DevContainer = json5::from_str(r#"{ build: { dockerfile: "Dockerfile" } }"#).unwrap();
+    assert!(!dc.build.unwrap().ssh_forward);
+}
+
+#[test]
+fn test_merge_image_metadata() {
+    let mut dc: DevContainer = json5::from_str(
+        r#"{
+            image: "myimage",
+            remoteEnv: { EDITOR: "nvim" },
+            mounts: ["source=cache,target=/cache,type=volume"],
+            forwardPorts: [3000],
+            postCreateCommand: "make",
+        }"#,
+    )
+    .unwrap();
+
+    let entries = crate::features::parse_image_metadata_label(
+        r#"[
+            { "remoteUser": "vscode", "remoteEnv": { "EDITOR": "vi", "PAGER": "less" } },
+            { "mounts": [{ "source": "dind", "target": "/var/lib/docker", "type": "volume" }] },
+            { "forwardPorts": [3000, 8080], "postCreateCommand": "npm install", "remoteUser": "node" }
+        ]"#,
+    )
+    .unwrap();
+    dc.merge_image_metadata(entries);
+
+    assert_eq!(dc.remote_user, Some("node".to_string()));
+    let remote_env = dc.remote_env.as_ref().unwrap();
+    assert_eq!(remote_env["EDITOR"], "nvim");
+    assert_eq!(remote_env["PAGER"], "less");
+    assert_eq!(
+        dc.mounts.unwrap(),
+        vec![
+            "source=dind,target=/var/lib/docker,type=volume".to_string(),
+            "source=cache,target=/cache,type=volume".to_string(),
+        ]
+    );
+    assert_eq!(dc.forward_ports.unwrap().len(), 2);
+    assert_eq!(dc.image_metadata.len(), 3);
+
+    // set here wins over the image
+    let mut dc: DevContainer =
+        json5::from_str(r#"{ image: "myimage", remoteUser: "me" }"#).unwrap();
+    dc.merge_image_metadata(
+        crate::features::parse_image_metadata_label(r#"{ "remoteUser": "vscode" }"#).unwrap(),
+    );
+    assert_eq!(dc.remote_user, Some("me".to_string()));
+}
+
+#[test]
+fn test_lifecycle_command() {
+    let shell = vec!["/bin/sh".to_string(), "-c".to_string()];
+
+    let dc: DevContainer = json5::from_str(
+        r#"{
+            image: "myimage",
+            postCreateCommand: "make",
+            postStartCommand: { server: "npm start", db: ["pg_ctl", "start"] },
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        dc.post_create_command
+            .unwrap()
+            .to_named_exec_args("postCreateCommand", &shell),
+        vec![(
+            "postCreateCommand".to_string(),
+            vec!["/bin/sh".to_string(), "-c".to_string(), "make".to_string()]
+        )]
+    );
+
+    let named = dc
+        .post_start_command
+        .unwrap()
+        .to_named_exec_args("postStartCommand", &shell);
+    assert_eq!(named.len(), 2);
+    assert_eq!(named[0].0, "db");
+    assert_eq!(named[0].1, vec!["pg_ctl", "start"]);
+    assert_eq!(named[1].0, "server");
+    assert_eq!(named[1].1, vec!["/bin/sh", "-c", "npm start"]);
+}
+
+#[test]
+fn test_platform() {
+    let dc: DevContainer =
+        json5::from_str(r#"{ "image": "ubuntu", "platform": "linux/amd64" }"#).unwrap();
+    assert_eq!(dc.platform.as_deref(), Some("linux/amd64"));
+    dc.validate().unwrap();
+
+    for platform in ["linux/", "/amd64", "linux/arm64/v8/x"].iter() {
+        let dc = DevContainer {
+            image: Some("ubuntu".to_string()),
+            platform: Some(platform.to_string()),
+            ..Default::default()
+        };
+        assert!(dc.validate().is_err(), "{}", platform);
+    }
+}
+
+#[test]
+fn test_dev_port_deprecated() {
+    let dc: DevContainer = json5::from_str(r#"{ image: "ubuntu", devPort: 8000 }"#).unwrap();
+    let warnings = dc.deprecation_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("'forwardPorts': [8000]"));
+
+    let dc: DevContainer = json5::from_str(r#"{ image: "ubuntu" }"#).unwrap();
+    assert!(dc.deprecation_warnings().is_empty());
+}
@@ -0,0 +1,147 @@
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::docker_host::TlsCerts;
+use super::errors::*;
+
+// The context that means "no context": DOCKER_HOST or the local socket
+const DEFAULT_CONTEXT: &str = "default";
+
+#[derive(Deserialize, Default)]
+struct DockerConfig {
+    #[serde(rename = "currentContext")]
+    current_context: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ContextMeta {
+    #[serde(rename = "Endpoints", default)]
+    endpoints: HashMap<String, ContextEndpoint>,
+}
+
+#[derive(Deserialize)]
+struct ContextEndpoint {
+    #[serde(rename = "Host")]
+    host: Option<String>,
+
+    #[serde(rename = "SkipTLSVerify", default)]
+    skip_tls_verify: bool,
+}
+
+// Where the daemon is and how to talk to it
+#[derive(Debug, Default, PartialEq)]
+pub struct Endpoint {
+    pub host: Option<String>,
+    pub tls: Option<TlsCerts>,
+}
+
+// What the docker cli was given, besides the config folder
+#[derive(Default)]
+pub struct EndpointSources<'a> {
+    pub flag_host: Option<&'a str>,
+    pub flag_context: Option<&'a str>,
+    pub env_host: Option<&'a str>,
+    pub env_context: Option<&'a str>,
+}
+
+// DOCKER_CONFIG or ~/.docker
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("DOCKER_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".docker"))
+}
+
+// Contexts are stored in folders named after the sha256 of their name
+pub fn context_dir_name(name: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(name);
+
+    hasher.result_str()
+}
+
+fn current_context(config_dir: &Path) -> Option<String> {
+    let contents = std::fs::read_to_string(config_dir.join("config.json")).ok()?;
+    let config: DockerConfig = serde_json::from_str(&contents).unwrap_or_default();
+
+    config.current_context
+}
+
+// The docker endpoint of a context, None for the default one
+pub fn load_context(config_dir: &Path, name: &str) -> Result<Option<Endpoint>, Error> {
+    if name == DEFAULT_CONTEXT {
+        return Ok(None);
+    }
+
+    let dir_name = context_dir_name(name);
+    let meta_path = config_dir
+        .join("contexts")
+        .join("meta")
+        .join(&dir_name)
+        .join("meta.json");
+    let contents = std::fs::read_to_string(&meta_path)
+        .map_err(|_| Error::InvalidSettings(format!("Docker context '{}' not found", name)))?;
+    let meta: ContextMeta = serde_json::from_str(&contents).map_err(|err| {
+        Error::InvalidSettings(format!("Invalid docker context '{}': {}", name, err))
+    })?;
+
+    let endpoint = meta.endpoints.get("docker").ok_or_else(|| {
+        Error::InvalidSettings(format!("Docker context '{}' has no docker endpoint", name))
+    })?;
+
+    let tls_dir = config_dir
+        .join("contexts")
+        .join("tls")
+        .join(&dir_name)
+        .join("docker");
+    let tls = match tls_dir.exists() {
+        true => {
+            if endpoint.skip_tls_verify {
+                warn!(
+                    "Docker context '{}' skips TLS verification, it is verified anyway",
+                    name
+                );
+            }
+            Some(TlsCerts::new(Some(tls_dir)))
+        }
+        false => None,
+    };
+
+    Ok(Some(Endpoint {
+        host: endpoint.host.clone(),
+        tls,
+    }))
+}
+
+// Same precedence as the docker cli: --host, --context, DOCKER_HOST, DOCKER_CONTEXT, then the
+// currentContext of config.json
+pub fn resolve_endpoint(sources: &EndpointSources, config_dir: &Path) -> Result<Endpoint, Error> {
+    if let Some(host) = sources.flag_host {
+        return Ok(Endpoint {
+            host: Some(host.to_string()),
+            tls: None,
+        });
+    }
+
+    if let Some(context) = sources.flag_context {
+        return Ok(load_context(config_dir, context)?.unwrap_or_default());
+    }
+
+    if let Some(host) = sources.env_host.filter(|h| !h.is_empty()) {
+        return Ok(Endpoint {
+            host: Some(host.to_string()),
+            tls: None,
+        });
+    }
+
+    let context = sources
+        .env_context
+        .map(|c| c.to_string())
+        .or_else(|| current_context(config_dir));
+    match context {
+        Some(context) => Ok(load_context(config_dir, &context)?.unwrap_or_default()),
+        None => Ok(Endpoint::default()),
+    }
+}
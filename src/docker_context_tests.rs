@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use super::docker_context::*;
+use super::docker_host::TlsCerts;
+
+fn config_dir() -> PathBuf {
+    let mut dir = std::env::current_dir().unwrap();
+    dir.push("test_files");
+    dir.push("docker-config");
+
+    dir
+}
+
+fn remote_endpoint() -> Endpoint {
+    Endpoint {
+        host: Some("tcp://build-server:2376".to_string()),
+        tls: Some(TlsCerts::new(Some(
+            config_dir()
+                .join("contexts")
+                .join("tls")
+                .join(context_dir_name("remote"))
+                .join("docker"),
+        ))),
+    }
+}
+
+#[test]
+fn test_context_dir_name() {
+    assert_eq!(
+        context_dir_name("remote"),
+        "b71199ebd070b36beab7317920c2c2f1d777df8d05e5527d8458fda57cb17a7a"
+    );
+}
+
+#[test]
+fn test_load_context() {
+    assert_eq!(load_context(&config_dir(), "default").unwrap(), None);
+    assert_eq!(
+        load_context(&config_dir(), "remote").unwrap(),
+        Some(remote_endpoint())
+    );
+    assert!(load_context(&config_dir(), "missing").is_err());
+}
+
+#[test]
+fn test_resolve_endpoint() {
+    // the current context of config.json
+    assert_eq!(
+        resolve_endpoint(&EndpointSources::default(), &config_dir()).unwrap(),
+        remote_endpoint()
+    );
+
+    // DOCKER_HOST wins over the current context, DOCKER_CONTEXT doesn't matter then
+    let sources = EndpointSources {
+        env_host: Some("unix:///run/docker.sock"),
+        env_context: Some("remote"),
+        ..EndpointSources::default()
+    };
+    assert_eq!(
+        resolve_endpoint(&sources, &config_dir()).unwrap().host,
+        Some("unix:///run/docker.sock".to_string())
+    );
+
+    // --context wins over DOCKER_HOST
+    let sources = EndpointSources {
+        flag_context: Some("default"),
+        env_host: Some("unix:///run/docker.sock"),
+        ..EndpointSources::default()
+    };
+    assert_eq!(
+        resolve_endpoint(&sources, &config_dir()).unwrap(),
+        Endpoint::default()
+    );
+
+    // and --host wins over everything
+    let sources = EndpointSources {
+        flag_host: Some("tcp://other:2375"),
+        flag_context: Some("remote"),
+        ..EndpointSources::default()
+    };
+    assert_eq!(
+        resolve_endpoint(&sources, &config_dir()).unwrap().host,
+        Some("tcp://other:2375".to_string())
+    );
+
+    let sources = EndpointSources {
+        env_context: Some("missing"),
+        ..EndpointSources::default()
+    };
+    assert!(resolve_endpoint(&sources, &config_dir()).is_err());
+}
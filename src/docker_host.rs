@@ -0,0 +1,168 @@
+// Helpers to reason about the docker host given with --host
+
+use bollard::errors::Error as DockerError;
+use bollard::{ClientVersion, Docker};
+use std::path::PathBuf;
+
+use super::errors::*;
+
+// Where the daemon listens when there is no --host, DOCKER_HOST or context
+#[cfg(unix)]
+pub const LOCAL_SOCKET: &str = "unix:///var/run/docker.sock";
+#[cfg(windows)]
+pub const LOCAL_SOCKET: &str = "npipe:////./pipe/docker_engine";
+
+// The daemon went away, e.g. restarted by a Docker Desktop update, rather than refusing the
+// request
+pub fn is_disconnect(err: &DockerError) -> bool {
+    matches!(
+        err,
+        DockerError::IOError { .. }
+            | DockerError::HyperResponseError { .. }
+            | DockerError::RequestTimeoutError
+    )
+}
+
+// Local sockets and loopback addresses share the filesystem with us, anything else does not
+pub fn is_remote(host: &str) -> bool {
+    let (scheme, address) = match host.find("://") {
+        Some(idx) => (&host[..idx], &host[idx + 3..]),
+        None => ("tcp", host),
+    };
+
+    if scheme == "unix" || scheme == "npipe" {
+        return false;
+    }
+
+    // ssh://user@host:port
+    let address = address.rsplit('@').next().unwrap_or(address);
+    let hostname = if address.starts_with('[') {
+        address
+            .trim_start_matches('[')
+            .split(']')
+            .next()
+            .unwrap_or(address)
+    } else {
+        address.split(':').next().unwrap_or(address)
+    };
+    let hostname = hostname.split('/').next().unwrap_or(hostname);
+
+    !matches!(hostname, "localhost" | "127.0.0.1" | "::1" | "")
+}
+
+// Unix sockets, or the named pipes Docker Desktop listens on in Windows
+#[cfg(unix)]
+pub fn connect_local(
+    address: &str,
+    timeout: u64,
+    version: &ClientVersion,
+) -> Result<Docker, Error> {
+    if address.starts_with("npipe://") {
+        return Err(Error::InvalidSettings(format!(
+            "Named pipes are only available on Windows: {}",
+            address
+        )));
+    }
+
+    Ok(Docker::connect_with_unix(
+        address.trim_start_matches("unix://"),
+        timeout,
+        version,
+    )?)
+}
+
+#[cfg(windows)]
+pub fn connect_local(
+    address: &str,
+    timeout: u64,
+    version: &ClientVersion,
+) -> Result<Docker, Error> {
+    if !address.starts_with("npipe://") {
+        return Err(Error::InvalidSettings(format!(
+            "Unix sockets are not available on Windows, use a npipe:// host: {}",
+            address
+        )));
+    }
+
+    Ok(Docker::connect_with_named_pipe(address, timeout, version)?)
+}
+
+// Windows canonical paths are verbatim, \\?\C:\src, which the daemon and the docker cli don't
+// understand. Anything else is returned as it is
+pub fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    let path_str = path.to_string_lossy();
+
+    if let Some(rest) = path_str.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", rest));
+    }
+    if let Some(rest) = path_str.strip_prefix(r"\\?\") {
+        return PathBuf::from(rest);
+    }
+
+    path
+}
+
+// Client certificates for a TLS docker host, named like the docker cli expects them
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsCerts {
+    pub dir: PathBuf,
+}
+
+impl TlsCerts {
+    // --tls-cert-path, DOCKER_CERT_PATH or ~/.docker
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        TlsCerts {
+            dir: dir.unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".docker")),
+        }
+    }
+
+    pub fn key(&self) -> PathBuf {
+        self.dir.join("key.pem")
+    }
+
+    pub fn cert(&self) -> PathBuf {
+        self.dir.join("cert.pem")
+    }
+
+    pub fn ca(&self) -> PathBuf {
+        self.dir.join("ca.pem")
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        let missing: Vec<String> = [self.key(), self.cert(), self.ca()]
+            .iter()
+            .filter(|file| !file.exists())
+            .map(|file| file.display().to_string())
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::Other(format!(
+            "Missing TLS certificates: {}",
+            missing.join(", ")
+        )))
+    }
+
+    // The same certificates for the docker cli
+    pub fn cli_args(&self) -> Vec<String> {
+        vec![
+            "--tlsverify".to_string(),
+            format!("--tlscacert={}", self.ca().display()),
+            format!("--tlscert={}", self.cert().display()),
+            format!("--tlskey={}", self.key().display()),
+        ]
+    }
+
+    // And for docker-compose
+    pub fn envs(&self) -> Vec<(String, String)> {
+        vec![
+            ("DOCKER_TLS_VERIFY".to_string(), "1".to_string()),
+            (
+                "DOCKER_CERT_PATH".to_string(),
+                self.dir.display().to_string(),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,87 @@
+use bollard::errors::Error as DockerError;
+use std::path::PathBuf;
+
+use super::docker_host::*;
+
+#[test]
+fn test_is_remote() {
+    assert!(!is_remote("unix:///var/run/docker.sock"));
+    assert!(!is_remote("npipe:////./pipe/docker_engine"));
+    assert!(!is_remote("tcp://localhost:2375"));
+    assert!(!is_remote("http://127.0.0.1:2375"));
+    assert!(!is_remote("tcp://[::1]:2375"));
+    assert!(!is_remote("localhost:2375"));
+
+    assert!(is_remote("tcp://192.168.0.10:2375"));
+    assert!(is_remote("ssh://user@buildbox"));
+    assert!(is_remote("ssh://user@buildbox:2222"));
+    assert!(is_remote("buildbox:2375"));
+}
+
+#[test]
+fn test_tls_certs() {
+    let tls = TlsCerts::new(Some(PathBuf::from("/home/me/.docker/remote")));
+
+    assert_eq!(tls.ca(), PathBuf::from("/home/me/.docker/remote/ca.pem"));
+    assert_eq!(
+        tls.cli_args(),
+        vec![
+            "--tlsverify",
+            "--tlscacert=/home/me/.docker/remote/ca.pem",
+            "--tlscert=/home/me/.docker/remote/cert.pem",
+            "--tlskey=/home/me/.docker/remote/key.pem",
+        ]
+    );
+    assert_eq!(
+        tls.envs(),
+        vec![
+            ("DOCKER_TLS_VERIFY".to_string(), "1".to_string()),
+            (
+                "DOCKER_CERT_PATH".to_string(),
+                "/home/me/.docker/remote".to_string()
+            ),
+        ]
+    );
+    assert!(tls.check().is_err());
+
+    assert!(TlsCerts::new(None).dir.ends_with(".docker"));
+}
+
+#[test]
+fn test_strip_verbatim_prefix() {
+    assert_eq!(
+        strip_verbatim_prefix(PathBuf::from(r"\\?\C:\Users\me\project")),
+        PathBuf::from(r"C:\Users\me\project")
+    );
+    assert_eq!(
+        strip_verbatim_prefix(PathBuf::from(r"\\?\UNC\server\share\project")),
+        PathBuf::from(r"\\server\share\project")
+    );
+    assert_eq!(
+        strip_verbatim_prefix(PathBuf::from("/home/me/project")),
+        PathBuf::from("/home/me/project")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_connect_local() {
+    assert!(connect_local(LOCAL_SOCKET, 10, bollard::API_DEFAULT_VERSION).is_ok());
+    assert!(connect_local(
+        "npipe:////./pipe/docker_engine",
+        10,
+        bollard::API_DEFAULT_VERSION
+    )
+    .is_err());
+}
+
+#[test]
+fn test_is_disconnect() {
+    assert!(is_disconnect(&DockerError::IOError {
+        err: std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused"),
+    }));
+    assert!(is_disconnect(&DockerError::RequestTimeoutError));
+    assert!(!is_disconnect(&DockerError::DockerResponseNotFoundError {
+        message: "No such container".to_string(),
+    }));
+}
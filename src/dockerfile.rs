@@ -0,0 +1,141 @@
+// Images referenced by the FROM instructions of a Dockerfile. Build stages and images that
+// depend on build args can't be resolved without building, so they are left out
+pub fn base_images(contents: &str) -> Vec<String> {
+    let mut stages: Vec<String> = vec![];
+    let mut images: Vec<String> = vec![];
+
+    // join line continuations
+    let contents = contents.replace("\\\n", " ");
+
+    for line in contents.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some(instruction) if instruction.eq_ignore_ascii_case("FROM") => {}
+            _ => continue,
+        }
+
+        let args: Vec<&str> = words.filter(|w| !w.starts_with("--")).collect();
+        let image = match args.first() {
+            Some(image) => image.to_string(),
+            None => continue,
+        };
+
+        if args.len() >= 3 && args[1].eq_ignore_ascii_case("AS") {
+            stages.push(args[2].to_lowercase());
+        }
+
+        if image.contains('$')
+            || image.eq_ignore_ascii_case("scratch")
+            || stages.contains(&image.to_lowercase())
+            || images.contains(&image)
+        {
+            continue;
+        }
+
+        images.push(image);
+    }
+
+    images
+}
+
+// Whether the Dockerfile uses syntax only BuildKit understands: a `# syntax=` parser directive,
+// `RUN --mount`, `COPY --link` or heredocs
+pub fn needs_buildkit(contents: &str) -> bool {
+    let has_syntax_directive = contents
+        .lines()
+        .take_while(|line| line.trim_start().starts_with('#'))
+        .any(|line| {
+            line.trim_start_matches('#')
+                .trim()
+                .to_lowercase()
+                .starts_with("syntax")
+        });
+
+    let contents = contents.replace("\\\n", " ");
+    let uses_buildkit_flags = contents.lines().any(|line| {
+        let mut words = line.split_whitespace();
+        let instruction = words.next().unwrap_or_default().to_uppercase();
+        let mut rest = words;
+
+        match instruction.as_str() {
+            "RUN" => rest.any(|w| w.starts_with("--mount") || w.starts_with("<<")),
+            "COPY" | "ADD" => rest.any(|w| w.starts_with("--link") || w.starts_with("<<")),
+            _ => false,
+        }
+    });
+
+    has_syntax_directive || uses_buildkit_flags
+}
+
+// The Dockerfile up to the end of the `target` stage, so building it builds that stage. Later
+// stages can't be needed by it. None when there is no such stage
+pub fn truncate_to_target(contents: &str, target: &str) -> Option<String> {
+    let mut lines = vec![];
+    let mut found = false;
+    let mut continued = false;
+
+    for line in contents.lines() {
+        let mut words = line.split_whitespace();
+        let is_from = !continued
+            && words
+                .next()
+                .map(|w| w.eq_ignore_ascii_case("FROM"))
+                .unwrap_or(false);
+        continued = line.trim_end().ends_with('\\');
+
+        if is_from {
+            if found {
+                break;
+            }
+
+            let args: Vec<&str> = words.filter(|w| !w.starts_with("--")).collect();
+            found = args.len() >= 3
+                && args[1].eq_ignore_ascii_case("AS")
+                && args[2].eq_ignore_ascii_case(target);
+        }
+
+        lines.push(line);
+    }
+
+    if !found {
+        return None;
+    }
+
+    Some(lines.join("\n") + "\n")
+}
+
+// Layer changing the UID/GID of `user` to the ones owning the workspace on the host, so files
+// created in the bind mount keep the right owner. Skipped when another user already has the UID
+pub fn update_uid_dockerfile(
+    image: &str,
+    user: &str,
+    image_user: Option<&str>,
+    uid: u32,
+    gid: u32,
+) -> String {
+    let mut dockerfile = format!(
+        r#"FROM {image}
+USER root
+RUN set -e; \
+    REMOTE_USER={user}; NEW_UID={uid}; NEW_GID={gid}; \
+    OLD_UID=$(id -u "$REMOTE_USER"); OLD_GID=$(id -g "$REMOTE_USER"); \
+    if [ "$OLD_UID" = "$NEW_UID" ] && [ "$OLD_GID" = "$NEW_GID" ]; then exit 0; fi; \
+    if getent passwd "$NEW_UID" >/dev/null; then echo "UID $NEW_UID already in use, not updating $REMOTE_USER"; exit 0; fi; \
+    HOME_DIR=$(getent passwd "$REMOTE_USER" | cut -d: -f6); \
+    if getent group "$NEW_GID" >/dev/null; then usermod -g "$NEW_GID" "$REMOTE_USER"; \
+    else groupmod -g "$NEW_GID" "$(id -gn "$REMOTE_USER")"; fi; \
+    usermod -u "$NEW_UID" "$REMOTE_USER"; \
+    chown -R "$NEW_UID:$NEW_GID" "$HOME_DIR"
+"#,
+        image = image,
+        user = user,
+        uid = uid,
+        gid = gid
+    );
+
+    if let Some(image_user) = image_user.filter(|u| !u.is_empty()) {
+        dockerfile.push_str(&format!("USER {}\n", image_user));
+    }
+
+    dockerfile
+}
@@ -0,0 +1,83 @@
+use super::dockerfile::*;
+
+#[test]
+fn test_base_images() {
+    let contents = r#"
+ARG VARIANT=bookworm
+FROM rust:1-${VARIANT} AS builder
+FROM --platform=linux/amd64 mcr.microsoft.com/devcontainers/base:ubuntu as base
+from builder
+FROM scratch
+FROM \
+    alpine:3
+RUN echo "FROM nothing"
+"#;
+
+    assert_eq!(
+        base_images(contents),
+        vec![
+            "mcr.microsoft.com/devcontainers/base:ubuntu".to_string(),
+            "alpine:3".to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_update_uid_dockerfile() {
+    let dockerfile =
+        update_uid_dockerfile("devcontainer_abc", "vscode", Some("vscode"), 1001, 1002);
+
+    assert!(dockerfile.starts_with("FROM devcontainer_abc\nUSER root\n"));
+    assert!(dockerfile.contains("REMOTE_USER=vscode; NEW_UID=1001; NEW_GID=1002;"));
+    assert!(dockerfile.ends_with("USER vscode\n"));
+
+    let dockerfile = update_uid_dockerfile("ubuntu", "dev", None, 1000, 1000);
+    assert!(!dockerfile.contains("\nUSER dev"));
+}
+
+#[test]
+fn test_truncate_to_target() {
+    let contents = r#"FROM rust:1 AS builder
+RUN cargo build
+FROM debian AS dev
+RUN apt-get update && \
+    apt-get install -y git
+FROM debian AS release
+COPY --from=builder /app /app
+"#;
+
+    assert_eq!(
+        truncate_to_target(contents, "dev").unwrap(),
+        "FROM rust:1 AS builder\nRUN cargo build\nFROM debian AS dev\nRUN apt-get update && \\\n    apt-get install -y git\n"
+    );
+    assert_eq!(
+        truncate_to_target(contents, "BUILDER").unwrap(),
+        "FROM rust:1 AS builder\nRUN cargo build\n"
+    );
+    assert!(truncate_to_target(contents, "missing").is_none());
+}
+
+#[test]
+fn test_needs_buildkit() {
+    assert!(!needs_buildkit(
+        "# a comment\nFROM rust:latest\nRUN cargo install just\nCOPY . /src\n"
+    ));
+
+    assert!(needs_buildkit(
+        "# syntax=docker/dockerfile:1\nFROM rust:latest\n"
+    ));
+    assert!(needs_buildkit(
+        "FROM rust:latest\nRUN --mount=type=cache,target=/usr/local/cargo/registry \\\n    cargo build\n"
+    ));
+    assert!(needs_buildkit(
+        "FROM rust:latest\nRUN apt-get update && \\\n    apt-get install -y git\nCOPY --link . /src\n"
+    ));
+    assert!(needs_buildkit(
+        "FROM debian\nRUN <<EOF\napt-get update\nEOF\n"
+    ));
+
+    // not a parser directive once an instruction was seen
+    assert!(!needs_buildkit(
+        "FROM rust:latest\n# syntax=docker/dockerfile:1\n"
+    ));
+}
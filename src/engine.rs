@@ -0,0 +1,193 @@
+use bollard::system::Version;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+pub const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+// Rootful Podman. The rootless one lives under $XDG_RUNTIME_DIR
+const PODMAN_ROOTFUL_SOCKET: &str = "/run/podman/podman.sock";
+
+// What runs the containers. Podman serves a docker compatible API, the differences are in the
+// cli, compose and a few container options. containerd has no such API, it is driven through
+// the nerdctl cli
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Docker,
+    Podman,
+    Nerdctl,
+}
+
+impl Engine {
+    pub fn cli(&self) -> &'static str {
+        match self {
+            Engine::Docker => "docker",
+            Engine::Podman => "podman",
+            Engine::Nerdctl => "nerdctl",
+        }
+    }
+
+    // Arguments pointing the cli to the given host
+    pub fn host_args(&self, host: &str) -> Vec<String> {
+        let flag = match self {
+            Engine::Docker => "-H",
+            Engine::Podman => "--url",
+            Engine::Nerdctl => "--address",
+        };
+
+        vec![flag.to_string(), host.to_string()]
+    }
+
+    // The same for the compose tools, which only read it from the environment. The compose
+    // providers of `podman compose` are docker-compose or podman-compose
+    pub fn host_envs(&self, host: &str) -> Vec<(String, String)> {
+        let names: &[&str] = match self {
+            Engine::Docker => &["DOCKER_HOST"],
+            Engine::Podman => &["CONTAINER_HOST", "DOCKER_HOST"],
+            Engine::Nerdctl => &["CONTAINERD_ADDRESS"],
+        };
+
+        names
+            .iter()
+            .map(|name| (name.to_string(), host.to_string()))
+            .collect()
+    }
+
+    // The `docker compose` plugin (v2) when installed, the legacy docker-compose otherwise. For
+    // podman, podman-compose when installed, the compose provider of `podman compose` otherwise
+    pub fn compose_command(&self) -> Vec<String> {
+        self.compose_command_with(&docker_compose_plugin, &|| {
+            find_in_path("podman-compose").is_some()
+        })
+    }
+
+    // Same, with the probes of what is installed given, only the one of the engine is run
+    pub fn compose_command_with(
+        &self,
+        docker_compose_plugin: &dyn Fn() -> bool,
+        podman_compose: &dyn Fn() -> bool,
+    ) -> Vec<String> {
+        match self {
+            Engine::Docker if docker_compose_plugin() => {
+                vec!["docker".to_string(), "compose".to_string()]
+            }
+            Engine::Docker => vec!["docker-compose".to_string()],
+            Engine::Podman if podman_compose() => {
+                vec!["podman-compose".to_string()]
+            }
+            Engine::Podman => vec!["podman".to_string(), "compose".to_string()],
+            Engine::Nerdctl => vec!["nerdctl".to_string(), "compose".to_string()],
+        }
+    }
+
+    pub fn has_docker_api(&self) -> bool {
+        *self != Engine::Nerdctl
+    }
+
+    // The `host-gateway` extra host address, docker 20.10+. Docker Desktop already has
+    // host.docker.internal, podman has host.containers.internal
+    pub fn supports_host_gateway(&self) -> bool {
+        *self == Engine::Docker && cfg!(target_os = "linux")
+    }
+
+    // Podman and nerdctl reject the docker desktop only `consistency` mount option
+    pub fn supports_mount_consistency(&self) -> bool {
+        *self == Engine::Docker
+    }
+}
+
+// Rootless first, it is what a user without docker usually runs
+pub fn podman_sockets(runtime_dir: Option<&str>) -> Vec<PathBuf> {
+    let mut sockets = vec![];
+    if let Some(dir) = runtime_dir {
+        sockets.push(Path::new(dir).join("podman").join("podman.sock"));
+    }
+    sockets.push(PathBuf::from(PODMAN_ROOTFUL_SOCKET));
+
+    sockets
+}
+
+// The podman socket to use when there is no docker daemon
+pub fn podman_socket() -> Option<PathBuf> {
+    if std::env::var("DOCKER_HOST").is_ok() || Path::new(DOCKER_SOCKET).exists() {
+        return None;
+    }
+
+    podman_sockets(std::env::var("XDG_RUNTIME_DIR").ok().as_deref())
+        .into_iter()
+        .find(|socket| socket.exists())
+}
+
+// nerdctl when there is neither docker nor podman, e.g. Rancher Desktop in containerd mode or
+// k3s hosts. It finds containerd itself, in the Rancher Desktop VM too
+pub fn nerdctl_available() -> bool {
+    if std::env::var("DOCKER_HOST").is_ok() || Path::new(DOCKER_SOCKET).exists() {
+        return false;
+    }
+
+    podman_socket().is_none() && find_in_path("nerdctl").is_some()
+}
+
+// From the --host given, or the local socket found. Podman behind a --host is only known once
+// the daemon answers, see `from_version`
+pub fn detect(host: Option<&str>) -> Engine {
+    match host {
+        Some(host) if is_containerd_address(host) => Engine::Nerdctl,
+        Some(_) => Engine::Docker,
+        None if podman_socket().is_some() => Engine::Podman,
+        None if nerdctl_available() => Engine::Nerdctl,
+        None => Engine::Docker,
+    }
+}
+
+// A containerd socket, `/run/containerd/containerd.sock` or a `containerd://` address
+pub fn is_containerd_address(host: &str) -> bool {
+    host.starts_with("containerd://") || host.trim_end_matches('/').ends_with("/containerd.sock")
+}
+
+// The engine serving the docker API, Podman reports itself as a "Podman Engine" component
+pub fn from_version(version: &Version) -> Engine {
+    let podman = version
+        .components
+        .iter()
+        .flatten()
+        .map(|component| component.name.as_str())
+        .chain(
+            version
+                .platform
+                .iter()
+                .map(|platform| platform.name.as_str()),
+        )
+        .any(|name| name.contains("Podman Engine"));
+
+    if podman {
+        Engine::Podman
+    } else {
+        Engine::Docker
+    }
+}
+
+// Whether `docker compose version` works, asked once per run
+pub fn docker_compose_plugin() -> bool {
+    static PLUGIN: OnceLock<bool> = OnceLock::new();
+
+    *PLUGIN.get_or_init(|| {
+        std::process::Command::new("docker")
+            .args(["compose", "version"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    })
+}
+
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,130 @@
+use bollard::system::Version;
+use std::path::PathBuf;
+
+use super::engine::*;
+
+#[test]
+fn test_podman_sockets() {
+    assert_eq!(
+        podman_sockets(Some("/run/user/1000")),
+        vec![
+            PathBuf::from("/run/user/1000/podman/podman.sock"),
+            PathBuf::from("/run/podman/podman.sock"),
+        ]
+    );
+    assert_eq!(
+        podman_sockets(None),
+        vec![PathBuf::from("/run/podman/podman.sock")]
+    );
+}
+
+#[test]
+fn test_detect() {
+    // asked to the daemon instead
+    assert_eq!(
+        detect(Some("unix:///run/user/1000/podman/podman.sock")),
+        Engine::Docker
+    );
+    assert_eq!(
+        detect(Some("unix:///run/containerd/containerd.sock")),
+        Engine::Nerdctl
+    );
+    assert_eq!(
+        detect(Some("/run/k3s/containerd/containerd.sock")),
+        Engine::Nerdctl
+    );
+    assert_eq!(detect(Some("containerd://my-host")), Engine::Nerdctl);
+    assert_eq!(detect(Some("tcp://192.168.1.10:2375")), Engine::Docker);
+    assert_eq!(
+        detect(Some("tcp://containerd-builder.lan:2375")),
+        Engine::Docker
+    );
+    assert_eq!(
+        detect(Some("unix:///run/containerd-shim/docker.sock")),
+        Engine::Docker
+    );
+}
+
+#[test]
+fn test_from_version() {
+    let version: Version = serde_json::from_value(serde_json::json!({
+        "Platform": { "Name": "linux/amd64/fedora-39" },
+        "Components": [{ "Name": "Podman Engine", "Version": "4.9.0" }],
+    }))
+    .unwrap();
+    assert_eq!(from_version(&version), Engine::Podman);
+
+    let version: Version = serde_json::from_value(serde_json::json!({
+        "Platform": { "Name": "Docker Engine - Community" },
+        "Components": [{ "Name": "Engine", "Version": "24.0.7" }],
+    }))
+    .unwrap();
+    assert_eq!(from_version(&version), Engine::Docker);
+}
+
+#[test]
+fn test_engine_cli() {
+    assert_eq!(Engine::Docker.cli(), "docker");
+    assert_eq!(
+        Engine::Docker.host_args("tcp://remote:2375"),
+        vec!["-H", "tcp://remote:2375"]
+    );
+    assert_eq!(
+        Engine::Podman.host_args("unix:///run/podman/podman.sock"),
+        vec!["--url", "unix:///run/podman/podman.sock"]
+    );
+    assert_eq!(
+        Engine::Podman.host_envs("unix:///run/podman/podman.sock"),
+        vec![
+            (
+                "CONTAINER_HOST".to_string(),
+                "unix:///run/podman/podman.sock".to_string()
+            ),
+            (
+                "DOCKER_HOST".to_string(),
+                "unix:///run/podman/podman.sock".to_string()
+            ),
+        ]
+    );
+    assert!(Engine::Docker.supports_mount_consistency());
+    assert!(!Engine::Podman.supports_mount_consistency());
+    assert!(!Engine::Podman.supports_host_gateway());
+    assert_eq!(
+        Engine::Nerdctl.compose_command(),
+        vec!["nerdctl", "compose"]
+    );
+    assert!(!Engine::Nerdctl.has_docker_api());
+    assert!(Engine::Podman.has_docker_api());
+    assert_eq!(
+        Engine::Docker.supports_host_gateway(),
+        cfg!(target_os = "linux")
+    );
+}
+
+#[test]
+fn test_compose_command() {
+    let installed = || true;
+    let missing = || false;
+    let unexpected = || -> bool { panic!("probed the compose of another engine") };
+
+    assert_eq!(
+        Engine::Docker.compose_command_with(&installed, &unexpected),
+        vec!["docker", "compose"]
+    );
+    assert_eq!(
+        Engine::Docker.compose_command_with(&missing, &unexpected),
+        vec!["docker-compose"]
+    );
+    assert_eq!(
+        Engine::Podman.compose_command_with(&unexpected, &installed),
+        vec!["podman-compose"]
+    );
+    assert_eq!(
+        Engine::Podman.compose_command_with(&unexpected, &missing),
+        vec!["podman", "compose"]
+    );
+    assert_eq!(
+        Engine::Nerdctl.compose_command_with(&unexpected, &unexpected),
+        vec!["nerdctl", "compose"]
+    );
+}
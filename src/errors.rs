@@ -1,6 +1,7 @@
 use bollard::errors::Error as DockerError;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     ConfigDoesNotExist(String),
     InvalidConfig(String),
@@ -10,6 +11,11 @@ pub enum Error {
     NoDevContainer,
     InvalidSettings(String),
     ExecCommandError(String),
+    RegistryError(String),
+    // the docker daemon is too old for the request
+    UnsupportedApi(String),
+    // what nerdctl can't do without the docker API
+    UnsupportedEngine(String),
     Other(String),
 }
 
@@ -20,6 +26,9 @@ pub enum UpError {
     ExecCommand(String),
     ImagePull(String),
     ComposeError(String),
+    HostRequirements(String),
+    PortsInUse(String),
+    Dependencies(String),
 }
 
 #[derive(Debug)]
@@ -38,6 +47,13 @@ impl std::fmt::Display for Error {
             Error::NoDevContainer => write!(f, "Unexpected error! No devcontainer project found!"),
             Error::InvalidSettings(err) => write!(f, "Error trying to parse settings: {}", err),
             Error::ExecCommandError(err) => write!(f, "Error trying to execute command: {}", err),
+            Error::RegistryError(err) => {
+                write!(f, "Error trying to communicate with the registry: {}", err)
+            }
+            Error::UnsupportedApi(err) => write!(f, "Docker daemon is too old: {}", err),
+            Error::UnsupportedEngine(err) => {
+                write!(f, "Not available with the nerdctl engine: {}", err)
+            }
             Error::Other(err) => write!(f, "Unexpected error: {}", err),
         }
     }
@@ -53,6 +69,21 @@ impl std::fmt::Display for UpError {
                 write!(f, "Failed while trying to pull docker image: {}", err)
             }
             UpError::ComposeError(err) => write!(f, "Failed to execute docker-compose: {}", err),
+            UpError::HostRequirements(err) => {
+                write!(
+                    f,
+                    "The docker host does not meet the hostRequirements: {}",
+                    err
+                )
+            }
+            UpError::PortsInUse(ports) => write!(f, "Host ports are already in use: {}", ports),
+            UpError::Dependencies(err) => {
+                write!(
+                    f,
+                    "The depends_on of the devcontainer service are not ready: {}",
+                    err
+                )
+            }
         }
     }
 }
@@ -0,0 +1,254 @@
+use bollard::container::Config;
+use bollard::service::{Mount, MountTypeEnum, PortBinding};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use super::errors::*;
+
+// Image to build before running, for build mode
+pub struct ExportBuild {
+    pub image: String,
+    pub dockerfile: String,
+    pub context: String,
+    pub args: BTreeMap<String, String>,
+    pub target: Option<String>,
+    pub cache_from: Vec<String>,
+}
+
+pub fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=/.,:@%+".contains(c));
+
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn mount_type(mount: &Mount) -> &'static str {
+    match mount.typ {
+        Some(MountTypeEnum::VOLUME) => "volume",
+        Some(MountTypeEnum::TMPFS) => "tmpfs",
+        _ => "bind",
+    }
+}
+
+fn sorted_ports(config: &Config<String>) -> Vec<(String, PortBinding)> {
+    let mut ports: Vec<(String, PortBinding)> = config
+        .host_config
+        .as_ref()
+        .and_then(|h| h.port_bindings.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|(container_port, bindings)| {
+            bindings
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |b| (container_port.clone(), b))
+        })
+        .collect();
+    ports.sort_by(|a, b| a.0.cmp(&b.0));
+
+    ports
+}
+
+fn sorted_labels(config: &Config<String>) -> Vec<(String, String)> {
+    let mut labels: Vec<(String, String)> = config
+        .labels
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    labels.sort();
+
+    labels
+}
+
+fn port_spec(container_port: &str, binding: &PortBinding) -> String {
+    let container_port = container_port.trim_end_matches("/tcp");
+    let host_port = binding.host_port.as_deref().unwrap_or(container_port);
+
+    match binding.host_ip.as_deref() {
+        Some(ip) if !ip.is_empty() => format!("{}:{}:{}", ip, host_port, container_port),
+        _ => format!("{}:{}", host_port, container_port),
+    }
+}
+
+fn build_section(build: &ExportBuild) -> Value {
+    let mut section = Map::new();
+    section.insert("context".to_string(), json!(build.context));
+    section.insert("dockerfile".to_string(), json!(build.dockerfile));
+    if !build.args.is_empty() {
+        section.insert("args".to_string(), json!(build.args));
+    }
+    if let Some(target) = build.target.as_ref() {
+        section.insert("target".to_string(), json!(target));
+    }
+    if !build.cache_from.is_empty() {
+        section.insert("cache_from".to_string(), json!(build.cache_from));
+    }
+    Value::Object(section)
+}
+
+// A shell script running the equivalent of the container created by `up`
+pub fn to_docker_run(name: &str, config: &Config<String>, build: Option<&ExportBuild>) -> String {
+    let mut script = String::from("#!/bin/sh\nset -e\n\n");
+
+    if let Some(build) = build {
+        let mut args = vec![format!(
+            "docker build -t {} -f {}",
+            shell_quote(&build.image),
+            shell_quote(&build.dockerfile)
+        )];
+        for (key, value) in build.args.iter() {
+            args.push(format!(
+                "--build-arg {}",
+                shell_quote(&format!("{}={}", key, value))
+            ));
+        }
+        if let Some(target) = build.target.as_ref() {
+            args.push(format!("--target {}", shell_quote(target)));
+        }
+        for image in build.cache_from.iter() {
+            args.push(format!("--cache-from {}", shell_quote(image)));
+        }
+        args.push(shell_quote(&build.context));
+
+        script.push_str(&args.join(" "));
+        script.push_str("\n\n");
+    }
+
+    // one flag per line
+    let mut args: Vec<String> = vec![format!("docker run -d --name {}", shell_quote(name))];
+
+    if let Some(user) = config.user.as_ref() {
+        args.push(format!("--user {}", shell_quote(user)));
+    }
+
+    for env in config.env.iter().flatten() {
+        args.push(format!("-e {}", shell_quote(env)));
+    }
+
+    for mount in config
+        .host_config
+        .as_ref()
+        .and_then(|h| h.mounts.as_ref())
+        .into_iter()
+        .flatten()
+    {
+        let mut spec = format!("type={}", mount_type(mount));
+        if let Some(source) = mount.source.as_ref() {
+            spec.push_str(&format!(",source={}", source));
+        }
+        if let Some(target) = mount.target.as_ref() {
+            spec.push_str(&format!(",target={}", target));
+        }
+        if mount.read_only == Some(true) {
+            spec.push_str(",readonly");
+        }
+
+        args.push(format!("--mount {}", shell_quote(&spec)));
+    }
+
+    for (container_port, binding) in sorted_ports(config) {
+        args.push(format!(
+            "-p {}",
+            shell_quote(&port_spec(&container_port, &binding))
+        ));
+    }
+
+    for (key, value) in sorted_labels(config) {
+        args.push(format!(
+            "--label {}",
+            shell_quote(&format!("{}={}", key, value))
+        ));
+    }
+
+    let mut command = vec![shell_quote(config.image.as_deref().unwrap_or_default())];
+    command.extend(config.cmd.iter().flatten().map(|arg| shell_quote(arg)));
+    args.push(command.join(" "));
+
+    script.push_str(&args.join(" \\\n    "));
+    script.push('\n');
+
+    script
+}
+
+// A docker-compose.yml with a single service equivalent to the container created by `up`
+pub fn to_compose(
+    name: &str,
+    config: &Config<String>,
+    build: Option<&ExportBuild>,
+) -> Result<String, Error> {
+    let mut service = Map::new();
+
+    service.insert("image".to_string(), json!(config.image));
+    if let Some(user) = config.user.as_ref() {
+        service.insert("user".to_string(), json!(user));
+    }
+    if let Some(build) = build {
+        service.insert("build".to_string(), build_section(build));
+    }
+
+    let envs: HashMap<String, String> = config
+        .env
+        .iter()
+        .flatten()
+        .filter_map(|e| e.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    if !envs.is_empty() {
+        service.insert("environment".to_string(), json!(envs));
+    }
+
+    let volumes: Vec<Value> = config
+        .host_config
+        .as_ref()
+        .and_then(|h| h.mounts.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|m| {
+            let mut volume = json!({
+                "type": mount_type(m),
+                "source": m.source,
+                "target": m.target,
+            });
+            if m.read_only == Some(true) {
+                volume["read_only"] = json!(true);
+            }
+            volume
+        })
+        .collect();
+    if !volumes.is_empty() {
+        service.insert("volumes".to_string(), json!(volumes));
+    }
+
+    let ports: Vec<String> = sorted_ports(config)
+        .iter()
+        .map(|(container_port, binding)| port_spec(container_port, binding))
+        .collect();
+    if !ports.is_empty() {
+        service.insert("ports".to_string(), json!(ports));
+    }
+
+    let labels: Map<String, Value> = sorted_labels(config)
+        .into_iter()
+        .map(|(k, v)| (k, json!(v)))
+        .collect();
+    if !labels.is_empty() {
+        service.insert("labels".to_string(), Value::Object(labels));
+    }
+
+    if let Some(cmd) = config.cmd.as_ref() {
+        service.insert("command".to_string(), json!(cmd));
+    }
+
+    let mut services = Map::new();
+    services.insert(name.to_string(), Value::Object(service));
+
+    serde_yaml::to_string(&json!({ "version": "3", "services": services }))
+        .map_err(|err| Error::Other(err.to_string()))
+}
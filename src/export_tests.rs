@@ -0,0 +1,108 @@
+use bollard::container::Config;
+use bollard::service::{HostConfig, Mount, MountTypeEnum, PortBinding};
+use std::collections::{BTreeMap, HashMap};
+
+use super::export::*;
+
+fn sample_config() -> Config<String> {
+    let mut port_bindings = HashMap::new();
+    port_bindings.insert(
+        "8080/tcp".to_string(),
+        Some(vec![PortBinding {
+            host_ip: Some("0.0.0.0".to_string()),
+            host_port: Some("8080".to_string()),
+        }]),
+    );
+
+    let mut labels = HashMap::new();
+    labels.insert("devcontainer".to_string(), "true".to_string());
+
+    Config {
+        image: Some("rust:latest".to_string()),
+        user: Some("vscode".to_string()),
+        env: Some(vec!["EDITOR=nvim".to_string()]),
+        cmd: Some(vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "while sleep 1000; do :; done".to_string(),
+        ]),
+        labels: Some(labels),
+        host_config: Some(HostConfig {
+            mounts: Some(vec![Mount {
+                typ: Some(MountTypeEnum::BIND),
+                source: Some("/home/me/project".to_string()),
+                target: Some("/workspace".to_string()),
+                ..Default::default()
+            }]),
+            port_bindings: Some(port_bindings),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("rust:latest"), "rust:latest");
+    assert_eq!(shell_quote("a b"), "'a b'");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(shell_quote(""), "''");
+}
+
+#[test]
+fn test_to_docker_run() {
+    let script = to_docker_run("myproject", &sample_config(), None);
+
+    assert!(script.starts_with("#!/bin/sh"));
+    assert!(script.contains("--name myproject"));
+    assert!(script.contains("--user vscode"));
+    assert!(script.contains("-e EDITOR=nvim"));
+    assert!(script.contains("--mount type=bind,source=/home/me/project,target=/workspace"));
+    assert!(script.contains("-p 0.0.0.0:8080:8080"));
+    assert!(script.contains("--label devcontainer=true"));
+    assert!(script.contains("rust:latest /bin/sh -c 'while sleep 1000; do :; done'"));
+
+    let build = ExportBuild {
+        image: "devcontainer_abc".to_string(),
+        dockerfile: ".devcontainer/Dockerfile".to_string(),
+        context: ".devcontainer".to_string(),
+        args: BTreeMap::new(),
+        target: None,
+        cache_from: vec![],
+    };
+    let script = to_docker_run("myproject", &sample_config(), Some(&build));
+    assert!(script
+        .contains("docker build -t devcontainer_abc -f .devcontainer/Dockerfile .devcontainer"));
+
+    let mut args = BTreeMap::new();
+    args.insert("VARIANT".to_string(), "bookworm slim".to_string());
+    let build = ExportBuild {
+        args,
+        target: Some("dev".to_string()),
+        cache_from: vec!["ghcr.io/me/cache".to_string()],
+        ..build
+    };
+    let script = to_docker_run("myproject", &sample_config(), Some(&build));
+    assert!(script.contains(
+        "-f .devcontainer/Dockerfile --build-arg 'VARIANT=bookworm slim' --target dev --cache-from ghcr.io/me/cache .devcontainer"
+    ));
+
+    let compose = to_compose("myproject", &sample_config(), Some(&build)).unwrap();
+    let value: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+    let section = &value["services"]["myproject"]["build"];
+    assert_eq!(section["args"]["VARIANT"].as_str(), Some("bookworm slim"));
+    assert_eq!(section["target"].as_str(), Some("dev"));
+}
+
+#[test]
+fn test_to_compose() {
+    let compose = to_compose("myproject", &sample_config(), None).unwrap();
+    let value: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+
+    let service = &value["services"]["myproject"];
+    assert_eq!(service["image"].as_str(), Some("rust:latest"));
+    assert_eq!(service["user"].as_str(), Some("vscode"));
+    assert_eq!(service["environment"]["EDITOR"].as_str(), Some("nvim"));
+    assert_eq!(service["volumes"][0]["target"].as_str(), Some("/workspace"));
+    assert_eq!(service["ports"][0].as_str(), Some("0.0.0.0:8080:8080"));
+}
@@ -0,0 +1,654 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use super::devcontainer::{ForwardPort, LifecycleCommand};
+use super::errors::*;
+use super::export::shell_quote;
+use super::lock::{self, LockedFeature};
+use super::registry::*;
+use super::upgrade_config;
+
+const METADATA_ANNOTATION: &str = "dev.containers.metadata";
+const METADATA_FILE: &str = "devcontainer-feature.json";
+
+pub const METADATA_LABEL: &str = "devcontainer.metadata";
+
+#[derive(Deserialize, Debug)]
+pub struct FeatureOption {
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+
+    pub default: Option<Value>,
+
+    pub description: Option<String>,
+
+    pub proposals: Option<Vec<Value>>,
+
+    #[serde(rename = "enum")]
+    pub values: Option<Vec<Value>>,
+}
+
+// Properties of a feature applied to the container. Also stored in the
+// devcontainer.metadata label of the image the features are installed in
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureContainerOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_env: Option<BTreeMap<String, String>>,
+
+    // "source=x,target=y,type=volume" or { "source": "x", "target": "y", "type": "volume" }
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mounts: Option<Vec<Value>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub init: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cap_add: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security_opt: Option<Vec<String>>,
+}
+
+// Entry of the devcontainer.metadata label: one per installed feature, plus the ones of
+// whoever built the base image
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMetadata {
+    #[serde(flatten)]
+    pub container: FeatureContainerOpts,
+
+    pub container_user: Option<String>,
+
+    pub remote_user: Option<String>,
+
+    pub remote_env: Option<BTreeMap<String, String>>,
+
+    pub forward_ports: Option<Vec<ForwardPort>>,
+
+    pub post_create_command: Option<LifecycleCommand>,
+
+    pub post_start_command: Option<LifecycleCommand>,
+
+    pub post_attach_command: Option<LifecycleCommand>,
+}
+
+// devcontainer-feature.json
+#[derive(Deserialize, Debug)]
+pub struct FeatureMetadata {
+    pub id: String,
+
+    pub version: Option<String>,
+
+    pub name: Option<String>,
+
+    pub description: Option<String>,
+
+    #[serde(rename = "documentationURL")]
+    pub documentation_url: Option<String>,
+
+    #[serde(default)]
+    pub options: BTreeMap<String, FeatureOption>,
+
+    // ids of features that must be installed before this one, when present
+    #[serde(default, rename = "installsAfter")]
+    pub installs_after: Vec<String>,
+
+    // features (and their options) this one can't be installed without
+    #[serde(default, rename = "dependsOn")]
+    pub depends_on: BTreeMap<String, Value>,
+
+    #[serde(flatten)]
+    pub container: FeatureContainerOpts,
+}
+
+// A feature ready to be installed
+pub struct Feature {
+    pub reference: String,
+    pub metadata: FeatureMetadata,
+    // tarball with install.sh and the rest of the feature files
+    pub data: Vec<u8>,
+    pub env: BTreeMap<String, String>,
+    // registry/repository@digest it was downloaded from, None for local features
+    pub resolved: Option<String>,
+}
+
+fn parse_metadata(contents: &str) -> Result<FeatureMetadata, Error> {
+    json5::from_str(contents)
+        .map_err(|err| Error::Other(format!("Invalid {}: {}", METADATA_FILE, err)))
+}
+
+pub fn read_metadata_from_tar(data: &[u8]) -> Result<FeatureMetadata, Error> {
+    let mut archive = layer_archive(data)?;
+    let entries = archive
+        .entries()
+        .map_err(|err| Error::Other(err.to_string()))?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|err| Error::Other(err.to_string()))?;
+        let is_metadata = entry
+            .path()
+            .map(|p| p.file_name().map(|f| f == METADATA_FILE).unwrap_or(false))
+            .unwrap_or(false);
+
+        if is_metadata {
+            let mut contents = String::new();
+            entry
+                .read_to_string(&mut contents)
+                .map_err(|err| Error::Other(err.to_string()))?;
+            return parse_metadata(&contents);
+        }
+    }
+
+    Err(Error::Other(format!(
+        "{} not found in the feature",
+        METADATA_FILE
+    )))
+}
+
+pub async fn fetch_metadata(
+    registry: &RegistryClient,
+    reference: &str,
+) -> Result<FeatureMetadata, Error> {
+    let image = ImageRef::parse(reference);
+    let manifest = registry.get_manifest(&image, OCI_MANIFEST).await?;
+
+    // recent publishers copy the metadata into the manifest, no need for the layer then
+    if let Some(metadata) = manifest
+        .get("annotations")
+        .and_then(|a| a.get(METADATA_ANNOTATION))
+        .and_then(|m| m.as_str())
+    {
+        return parse_metadata(metadata);
+    }
+
+    let layer = registry.get_first_layer(&image, &manifest).await?;
+
+    read_metadata_from_tar(&layer)
+}
+
+// key=value, with true/false as booleans
+pub fn parse_option(option: &str) -> Result<(String, Value), Error> {
+    let (key, value) = option
+        .split_once('=')
+        .ok_or_else(|| Error::Other(format!("Invalid option '{}', use key=value", option)))?;
+
+    let value = match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        v => Value::String(v.to_string()),
+    };
+
+    Ok((key.to_string(), value))
+}
+
+// Adds (or replaces) a feature in the "features" object of a devcontainer.json
+pub fn add_feature(
+    config: &str,
+    reference: &str,
+    options: Map<String, Value>,
+) -> Result<String, Error> {
+    let parsed: Value =
+        json5::from_str(config).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+
+    let mut features = match parsed.get("features") {
+        Some(Value::Object(features)) => features.clone(),
+        _ => Map::new(),
+    };
+    features.insert(reference.to_string(), Value::Object(options));
+
+    upgrade_config::apply_edits(
+        config,
+        &[("features".to_string(), Some(Value::Object(features)))],
+    )
+}
+
+// Registry and repository without the version, so "ghcr.io/devcontainers/features/node:1"
+// and "ghcr.io/devcontainers/features/node" are the same feature
+pub fn feature_key(reference: &str) -> String {
+    if is_local_feature(reference) {
+        return reference.trim_end_matches('/').to_string();
+    }
+
+    let image = ImageRef::parse(reference);
+    format!("{}/{}", image.registry, image.repository)
+}
+
+// "1", "1.2" or "1.2.3"
+fn parse_version(tag: &str) -> Option<Vec<u64>> {
+    let parts: Vec<u64> = tag
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    Some(parts).filter(|parts| !parts.is_empty() && parts.len() <= 3)
+}
+
+// The latest release among the tags of a feature when `tag` doesn't follow it. "1" and "1.2"
+// follow the releases of their major or minor already, only a later one is newer for them
+pub fn newer_version(tag: &str, tags: &[String]) -> Option<String> {
+    let current = parse_version(tag)?;
+
+    let latest = tags
+        .iter()
+        .filter_map(|tag| parse_version(tag))
+        .filter(|version| version.len() == 3)
+        .max()?;
+
+    match latest[..current.len()] > current[..] {
+        true => Some(
+            latest
+                .iter()
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>()
+                .join("."),
+        ),
+        false => None,
+    }
+}
+
+pub fn is_local_feature(reference: &str) -> bool {
+    reference.starts_with("./") || reference.starts_with("../")
+}
+
+// Option id as seen by install.sh: "installYarn" -> "INSTALLYARN", "node-version" -> "NODE_VERSION"
+pub fn option_env_name(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let trimmed = name.trim_start_matches(|c: char| c.is_ascii_digit() || c == '_');
+    let name = if trimmed.len() == name.len() {
+        name.clone()
+    } else {
+        format!("_{}", trimmed)
+    };
+
+    name.to_uppercase()
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+// Options given to the feature in devcontainer.json on top of the defaults of the feature.
+// A string value is a shorthand for the "version" option
+pub fn feature_env(metadata: &FeatureMetadata, value: &Value) -> BTreeMap<String, String> {
+    let mut env: BTreeMap<String, String> = metadata
+        .options
+        .iter()
+        .filter_map(|(id, option)| {
+            option
+                .default
+                .as_ref()
+                .map(|d| (option_env_name(id), value_to_string(d)))
+        })
+        .collect();
+
+    match value {
+        Value::Object(options) => {
+            for (id, v) in options {
+                env.insert(option_env_name(id), value_to_string(v));
+            }
+        }
+        Value::String(version) => {
+            env.insert("VERSION".to_string(), version.clone());
+        }
+        _ => {}
+    }
+
+    env
+}
+
+// Indexes of the features in install order: dependsOn and installsAfter first, then the ones
+// listed in overrideFeatureInstallOrder, then the order of the config
+pub fn install_order(features: &[Feature], override_order: &[String]) -> Result<Vec<usize>, Error> {
+    let keys: Vec<String> = features.iter().map(|f| feature_key(&f.reference)).collect();
+    let override_keys: Vec<String> = override_order.iter().map(|r| feature_key(r)).collect();
+
+    let requires: Vec<Vec<usize>> = features
+        .iter()
+        .map(|f| {
+            f.metadata
+                .installs_after
+                .iter()
+                .chain(f.metadata.depends_on.keys())
+                .filter_map(|r| {
+                    let key = feature_key(r);
+                    keys.iter().position(|k| k == &key)
+                })
+                .collect()
+        })
+        .collect();
+
+    let priority = |i: usize| {
+        override_keys
+            .iter()
+            .position(|k| k == &keys[i])
+            .unwrap_or(override_keys.len())
+    };
+
+    let mut order = vec![];
+    let mut installed = HashSet::new();
+
+    while order.len() < features.len() {
+        let next = (0..features.len())
+            .filter(|i| !installed.contains(i))
+            .filter(|i| requires[*i].iter().all(|r| installed.contains(r) || r == i))
+            .min_by_key(|i| (priority(*i), *i))
+            .ok_or_else(|| {
+                Error::InvalidConfig("Features have circular dependencies".to_string())
+            })?;
+
+        installed.insert(next);
+        order.push(next);
+    }
+
+    Ok(order)
+}
+
+fn tar_local_feature(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut builder = tar::Builder::new(Vec::new());
+    builder
+        .append_dir_all(".", path)
+        .and_then(|_| builder.into_inner())
+        .map_err(|err| Error::Other(format!("{}: {}", path.display(), err)))
+}
+
+// Downloads the feature from the digest it is locked to, if any, and checks it is the same
+// tarball. Returns the registry/repository@digest it was downloaded from
+pub async fn fetch_feature(
+    registry: &RegistryClient,
+    devcontainer_dir: &Path,
+    reference: &str,
+    locked: Option<&LockedFeature>,
+) -> Result<(FeatureMetadata, Vec<u8>, Option<String>), Error> {
+    if is_local_feature(reference) {
+        let data = tar_local_feature(&devcontainer_dir.join(reference))?;
+        return Ok((read_metadata_from_tar(&data)?, data, None));
+    }
+
+    let image = match locked {
+        Some(locked) => ImageRef::parse(&locked.resolved),
+        None => ImageRef::parse(reference),
+    };
+    let digest = match image.digest.clone() {
+        Some(digest) => digest,
+        None => registry.get_manifest_digest(&image).await?,
+    };
+    let image = ImageRef {
+        digest: Some(digest.clone()),
+        ..image
+    };
+
+    let manifest = registry.get_manifest(&image, OCI_MANIFEST).await?;
+    let data = registry.get_first_layer(&image, &manifest).await?;
+
+    if let Some(locked) = locked {
+        if lock::integrity(&data) != locked.integrity {
+            return Err(Error::RegistryError(format!(
+                "Feature {} does not match the integrity of the lock file",
+                reference
+            )));
+        }
+    }
+
+    let resolved = format!("{}/{}@{}", image.registry, image.repository, digest);
+
+    Ok((read_metadata_from_tar(&data)?, data, Some(resolved)))
+}
+
+// Downloads the requested features and the features they depend on, in install order. With a
+// lock file (--frozen), remote features are downloaded from the digests locked in it
+pub async fn resolve(
+    registry: &RegistryClient,
+    devcontainer_dir: &Path,
+    requested: &BTreeMap<String, Value>,
+    override_order: &[String],
+    lock_file: Option<&lock::LockFile>,
+) -> Result<Vec<Feature>, Error> {
+    let mut pending: Vec<(String, Value)> = requested
+        .iter()
+        .map(|(r, v)| (r.clone(), v.clone()))
+        .collect();
+    let mut features: Vec<Feature> = vec![];
+
+    while !pending.is_empty() {
+        let (reference, value) = pending.remove(0);
+        if matches!(value, Value::Bool(false)) {
+            continue;
+        }
+
+        let key = feature_key(&reference);
+        if features.iter().any(|f| feature_key(&f.reference) == key) {
+            continue;
+        }
+
+        let locked = match lock_file {
+            Some(lock_file) if !is_local_feature(&reference) => {
+                Some(lock_file.locked_feature(&reference)?)
+            }
+            _ => None,
+        };
+
+        info!("Fetching feature: {}", reference);
+        let (metadata, data, resolved) =
+            fetch_feature(registry, devcontainer_dir, &reference, locked).await?;
+
+        for (dependency, options) in metadata.depends_on.iter() {
+            pending.push((dependency.clone(), options.clone()));
+        }
+
+        features.push(Feature {
+            env: feature_env(&metadata, &value),
+            reference,
+            metadata,
+            data,
+            resolved,
+        });
+    }
+
+    let order = install_order(&features, override_order)?;
+    let mut features: Vec<Option<Feature>> = features.into_iter().map(Some).collect();
+
+    Ok(order
+        .into_iter()
+        .filter_map(|i| features[i].take())
+        .collect())
+}
+
+// Dockerfile ENV value, ${VAR} references are still expanded by docker
+fn quote_env(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Users install.sh scripts can rely on, see the features spec
+pub struct FeatureUsers {
+    pub container_user: String,
+    pub remote_user: String,
+}
+
+fn user_home(user: &str) -> String {
+    if user == "root" {
+        "/root".to_string()
+    } else {
+        format!("/home/{}", user)
+    }
+}
+
+// Docker build context (as a tarball) installing the features on top of the base image
+pub fn build_context(
+    base_image: &str,
+    base_user: Option<&str>,
+    users: &FeatureUsers,
+    features: &[Feature],
+) -> Result<Vec<u8>, Error> {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut dockerfile = format!("FROM {}\nUSER root\n", base_image);
+
+    for (i, feature) in features.iter().enumerate() {
+        let folder = format!("feature_{}", i);
+
+        let mut archive = layer_archive(&feature.data)?;
+        let entries = archive
+            .entries()
+            .map_err(|err| Error::Other(err.to_string()))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|err| Error::Other(err.to_string()))?;
+            let path = entry
+                .path()
+                .map_err(|err| Error::Other(err.to_string()))?
+                .to_path_buf();
+            if path
+                .components()
+                .any(|c| c == std::path::Component::ParentDir)
+            {
+                return Err(Error::Other(format!(
+                    "Feature {} contains an unsafe path: {}",
+                    feature.reference,
+                    path.display()
+                )));
+            }
+
+            let mut header = entry.header().clone();
+            builder
+                .append_data(&mut header, Path::new(&folder).join(&path), &mut entry)
+                .map_err(|err| Error::Other(err.to_string()))?;
+        }
+
+        let mut env = feature.env.clone();
+        env.insert("_CONTAINER_USER".to_string(), users.container_user.clone());
+        env.insert("_REMOTE_USER".to_string(), users.remote_user.clone());
+        env.insert(
+            "_CONTAINER_USER_HOME".to_string(),
+            user_home(&users.container_user),
+        );
+        env.insert(
+            "_REMOTE_USER_HOME".to_string(),
+            user_home(&users.remote_user),
+        );
+
+        let env_file: String = env
+            .iter()
+            .map(|(k, v)| format!("{}={}\n", k, shell_quote(v)))
+            .collect();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(env_file.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("{}/devcontainer-features.env", folder),
+                env_file.as_bytes(),
+            )
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        dockerfile.push_str(&format!("\n# {}\n", feature.reference));
+        dockerfile.push_str(&format!(
+            "COPY {0}/ /tmp/devcontainer-features/{0}/\n",
+            folder
+        ));
+        dockerfile.push_str(&format!(
+            "RUN cd /tmp/devcontainer-features/{} \\\n    && chmod +x ./install.sh \\\n    && set -a && . ./devcontainer-features.env && set +a \\\n    && ./install.sh\n",
+            folder
+        ));
+
+        if let Some(container_env) = feature.metadata.container.container_env.as_ref() {
+            for (k, v) in container_env {
+                dockerfile.push_str(&format!("ENV {}={}\n", k, quote_env(v)));
+            }
+        }
+    }
+
+    if let Some(user) = base_user.filter(|u| !u.is_empty()) {
+        dockerfile.push_str(&format!("\nUSER {}\n", user));
+    }
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(dockerfile.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "Dockerfile", dockerfile.as_bytes())
+        .map_err(|err| Error::Other(err.to_string()))?;
+
+    builder
+        .into_inner()
+        .map_err(|err| Error::Other(err.to_string()))
+}
+
+// Value of the devcontainer.metadata label of the image the features are installed in
+// The entries of the base image label are kept, the features are added after them
+pub fn image_metadata_label(
+    base_label: Option<&str>,
+    features: &[Feature],
+) -> Result<String, Error> {
+    let mut entries = match base_label {
+        Some(label) => label_entries(label)?,
+        None => vec![],
+    };
+
+    for f in features {
+        let mut entry = serde_json::to_value(&f.metadata.container)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        entry["id"] = Value::String(f.reference.clone());
+        entries.push(entry);
+    }
+
+    serde_json::to_string(&entries).map_err(|err| Error::Other(err.to_string()))
+}
+
+// The label is an array of entries, or a single one
+fn label_entries(label: &str) -> Result<Vec<Value>, Error> {
+    let value: Value = serde_json::from_str(label)
+        .map_err(|err| Error::Other(format!("Invalid image metadata label: {}", err)))?;
+
+    match value {
+        Value::Array(entries) => Ok(entries),
+        Value::Object(_) => Ok(vec![value]),
+        _ => Err(Error::Other(
+            "Invalid image metadata label: expected an array or an object".to_string(),
+        )),
+    }
+}
+
+pub fn parse_image_metadata_label(label: &str) -> Result<Vec<ImageMetadata>, Error> {
+    label_entries(label)?
+        .into_iter()
+        .map(|entry| {
+            serde_json::from_value(entry)
+                .map_err(|err| Error::Other(format!("Invalid image metadata label: {}", err)))
+        })
+        .collect()
+}
+
+// Mounts of a feature in the "source=x,target=y,type=z" form
+pub fn mount_to_str(mount: &Value) -> Option<String> {
+    match mount {
+        Value::String(m) => Some(m.clone()),
+        Value::Object(m) => Some(
+            m.iter()
+                .map(|(k, v)| format!("{}={}", k, value_to_string(v)))
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        _ => None,
+    }
+}
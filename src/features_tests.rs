@@ -0,0 +1,284 @@
+use serde_json::Value;
+
+use super::features::*;
+
+fn build_tar(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, contents.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_read_metadata_from_tar() {
+    let data = build_tar(&[
+        ("./install.sh", "#!/bin/sh"),
+        (
+            "./devcontainer-feature.json",
+            r#"{
+                "id": "node",
+                "version": "1.2.0",
+                "name": "Node.js",
+                "options": {
+                    "version": { "type": "string", "default": "lts", "proposals": ["lts", "18"] }
+                }
+            }"#,
+        ),
+    ]);
+
+    let metadata = read_metadata_from_tar(&data).unwrap();
+    assert_eq!(metadata.id, "node");
+    assert_eq!(metadata.version.as_deref(), Some("1.2.0"));
+    let option = metadata.options.get("version").unwrap();
+    assert_eq!(option.typ.as_deref(), Some("string"));
+    assert_eq!(option.default, Some(serde_json::json!("lts")));
+
+    assert!(read_metadata_from_tar(&build_tar(&[("./install.sh", "")])).is_err());
+}
+
+#[test]
+fn test_parse_option() {
+    assert_eq!(
+        parse_option("version=18").unwrap(),
+        ("version".to_string(), serde_json::json!("18"))
+    );
+    assert_eq!(
+        parse_option("installYarn=false").unwrap(),
+        ("installYarn".to_string(), serde_json::json!(false))
+    );
+    assert!(parse_option("version").is_err());
+}
+
+#[test]
+fn test_add_feature() {
+    let config = r#"{
+    // my project
+    "image": "ubuntu",
+    "features": {
+        "ghcr.io/devcontainers/features/git:1": {},
+    },
+}"#;
+
+    let mut options = serde_json::Map::new();
+    options.insert("version".to_string(), serde_json::json!("lts"));
+
+    let updated = add_feature(config, "ghcr.io/devcontainers/features/node:1", options).unwrap();
+    assert!(updated.contains("// my project"));
+
+    let value: serde_json::Value = json5::from_str(&updated).unwrap();
+    assert_eq!(value["image"], "ubuntu");
+    assert_eq!(
+        value["features"]["ghcr.io/devcontainers/features/git:1"],
+        serde_json::json!({})
+    );
+    assert_eq!(
+        value["features"]["ghcr.io/devcontainers/features/node:1"]["version"],
+        "lts"
+    );
+}
+
+fn feature(reference: &str, metadata: &str) -> Feature {
+    let metadata: FeatureMetadata = json5::from_str(metadata).unwrap();
+    Feature {
+        reference: reference.to_string(),
+        env: std::collections::BTreeMap::new(),
+        metadata,
+        data: build_tar(&[("./install.sh", "#!/bin/sh\necho hi")]),
+        resolved: None,
+    }
+}
+
+#[test]
+fn test_feature_key() {
+    assert_eq!(
+        feature_key("ghcr.io/devcontainers/features/node:1"),
+        "ghcr.io/devcontainers/features/node"
+    );
+    assert_eq!(
+        feature_key("ghcr.io/devcontainers/features/node"),
+        "ghcr.io/devcontainers/features/node"
+    );
+    assert_eq!(feature_key("./local-feature/"), "./local-feature");
+}
+
+#[test]
+fn test_option_env_name() {
+    assert_eq!(option_env_name("version"), "VERSION");
+    assert_eq!(option_env_name("installYarn"), "INSTALLYARN");
+    assert_eq!(option_env_name("node-version"), "NODE_VERSION");
+    assert_eq!(option_env_name("1st"), "_ST");
+}
+
+#[test]
+fn test_feature_env() {
+    let metadata: FeatureMetadata = json5::from_str(
+        r#"{
+            "id": "node",
+            "options": {
+                "version": { "type": "string", "default": "lts" },
+                "installYarn": { "type": "boolean", "default": true }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let env = feature_env(&metadata, &serde_json::json!({ "installYarn": false }));
+    assert_eq!(env.get("VERSION").map(|s| s.as_str()), Some("lts"));
+    assert_eq!(env.get("INSTALLYARN").map(|s| s.as_str()), Some("false"));
+
+    let env = feature_env(&metadata, &serde_json::json!("18"));
+    assert_eq!(env.get("VERSION").map(|s| s.as_str()), Some("18"));
+}
+
+#[test]
+fn test_install_order() {
+    let features = vec![
+        feature(
+            "ghcr.io/org/features/b:1",
+            r#"{ "id": "b", "installsAfter": ["ghcr.io/org/features/c"] }"#,
+        ),
+        feature("ghcr.io/org/features/a:1", r#"{ "id": "a" }"#),
+        feature(
+            "ghcr.io/org/features/c:1",
+            r#"{ "id": "c", "dependsOn": { "ghcr.io/org/features/a:1": {} } }"#,
+        ),
+    ];
+
+    assert_eq!(install_order(&features, &[]).unwrap(), vec![1, 2, 0]);
+
+    // installsAfter features that are not installed are ignored
+    let features = vec![
+        feature(
+            "ghcr.io/org/features/b:1",
+            r#"{ "id": "b", "installsAfter": ["ghcr.io/org/features/z"] }"#,
+        ),
+        feature("ghcr.io/org/features/a:1", r#"{ "id": "a" }"#),
+    ];
+    assert_eq!(install_order(&features, &[]).unwrap(), vec![0, 1]);
+    assert_eq!(
+        install_order(&features, &["ghcr.io/org/features/a".to_string()]).unwrap(),
+        vec![1, 0]
+    );
+
+    let features = vec![
+        feature(
+            "ghcr.io/org/features/a:1",
+            r#"{ "id": "a", "installsAfter": ["ghcr.io/org/features/b"] }"#,
+        ),
+        feature(
+            "ghcr.io/org/features/b:1",
+            r#"{ "id": "b", "installsAfter": ["ghcr.io/org/features/a"] }"#,
+        ),
+    ];
+    assert!(install_order(&features, &[]).is_err());
+}
+
+#[test]
+fn test_build_context() {
+    let mut node = feature(
+        "ghcr.io/devcontainers/features/node:1",
+        r#"{ "id": "node", "containerEnv": { "PATH": "/usr/local/share/nvm/bin:${PATH}" } }"#,
+    );
+    node.env
+        .insert("VERSION".to_string(), "it's lts".to_string());
+
+    let users = FeatureUsers {
+        container_user: "root".to_string(),
+        remote_user: "vscode".to_string(),
+    };
+    let context = build_context("ubuntu:22.04", Some("vscode"), &users, &[node]).unwrap();
+
+    let mut files = std::collections::BTreeMap::new();
+    let mut archive = tar::Archive::new(context.as_slice());
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut contents).unwrap();
+        files.insert(entry.path().unwrap().display().to_string(), contents);
+    }
+
+    assert_eq!(
+        files.get("feature_0/install.sh").map(|s| s.as_str()),
+        Some("#!/bin/sh\necho hi")
+    );
+
+    let env = files.get("feature_0/devcontainer-features.env").unwrap();
+    assert!(env.contains("VERSION='it'\\''s lts'\n"));
+    assert!(env.contains("_REMOTE_USER=vscode\n"));
+    assert!(env.contains("_REMOTE_USER_HOME=/home/vscode\n"));
+
+    let dockerfile = files.get("Dockerfile").unwrap();
+    assert!(dockerfile.starts_with("FROM ubuntu:22.04\nUSER root\n"));
+    assert!(dockerfile.contains("COPY feature_0/ /tmp/devcontainer-features/feature_0/\n"));
+    assert!(dockerfile.contains("ENV PATH=\"/usr/local/share/nvm/bin:${PATH}\"\n"));
+    assert!(dockerfile.ends_with("USER vscode\n"));
+}
+
+#[test]
+fn test_image_metadata_label() {
+    let features = vec![feature(
+        "ghcr.io/devcontainers/features/docker-in-docker:2",
+        r#"{
+            "id": "docker-in-docker",
+            "privileged": true,
+            "mounts": [{ "source": "dind-var-lib-docker", "target": "/var/lib/docker", "type": "volume" }]
+        }"#,
+    )];
+
+    let label = image_metadata_label(None, &features).unwrap();
+    let entries = parse_image_metadata_label(&label).unwrap();
+
+    assert_eq!(entries.len(), 1);
+    let opts = &entries[0].container;
+    assert_eq!(opts.privileged, Some(true));
+    assert_eq!(
+        mount_to_str(&opts.mounts.as_ref().unwrap()[0]).as_deref(),
+        Some("source=dind-var-lib-docker,target=/var/lib/docker,type=volume")
+    );
+}
+
+#[test]
+fn test_image_metadata_label_keeps_base_entries() {
+    let features = vec![feature(
+        "ghcr.io/devcontainers/features/node:1",
+        r#"{ "id": "node", "init": true }"#,
+    )];
+
+    let base =
+        r#"{ "remoteUser": "vscode", "postCreateCommand": "make setup", "customizations": {} }"#;
+    let label = image_metadata_label(Some(base), &features).unwrap();
+
+    let value: Value = serde_json::from_str(&label).unwrap();
+    assert!(value[0]["customizations"].is_object());
+
+    let entries = parse_image_metadata_label(&label).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].remote_user.as_deref(), Some("vscode"));
+    assert!(entries[0].post_create_command.is_some());
+    assert_eq!(entries[1].container.init, Some(true));
+
+    assert!(parse_image_metadata_label("42").is_err());
+}
+
+#[test]
+fn test_newer_version() {
+    let tags: Vec<String> = ["1", "1.2", "1.2.3", "1.3.0", "2.0.1", "latest", "2.1.0-rc"]
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+
+    assert_eq!(newer_version("1.2.3", &tags), Some("2.0.1".to_string()));
+    assert_eq!(newer_version("1.3", &tags), Some("2.0.1".to_string()));
+    assert_eq!(newer_version("1", &tags), Some("2.0.1".to_string()));
+    assert_eq!(newer_version("2", &tags), None);
+    assert_eq!(newer_version("2.0.1", &tags), None);
+    assert_eq!(newer_version("latest", &tags), None);
+    assert_eq!(newer_version("1", &[]), None);
+}
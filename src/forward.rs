@@ -0,0 +1,186 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::process::Stdio;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+
+use super::errors::*;
+
+// "5432" forwards the same port, "15432:5432" forwards host port 15432 to container port 5432
+pub fn parse_port_spec(spec: &str) -> Result<(u16, u16), Error> {
+    let invalid = || Error::Other(format!("Invalid port: '{}'", spec));
+
+    match spec.split_once(':') {
+        Some((host, container)) => Ok((
+            host.parse().map_err(|_| invalid())?,
+            container.parse().map_err(|_| invalid())?,
+        )),
+        None => {
+            let port = spec.parse().map_err(|_| invalid())?;
+            Ok((port, port))
+        }
+    }
+}
+
+// Whether nothing is listening on the host port yet
+pub fn is_port_available(port: u16) -> bool {
+    std::net::TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+// A host port `up` is about to publish, and what asks for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostPort {
+    pub port: u16,
+    // "tcp" or "udp"
+    pub protocol: String,
+    pub origin: String,
+}
+
+impl HostPort {
+    pub fn is_available(&self) -> bool {
+        match self.protocol.as_str() {
+            "udp" => UdpSocket::bind(("0.0.0.0", self.port)).is_ok(),
+            _ => is_port_available(self.port),
+        }
+    }
+}
+
+// Fails with every port of `ports` already taken, instead of the first one docker can't bind.
+// A port asked for twice is only checked once
+pub fn check_ports(
+    ports: &[HostPort],
+    is_available: &dyn Fn(&HostPort) -> bool,
+) -> Result<(), Error> {
+    let mut checked: Vec<(u16, &str)> = vec![];
+    let mut busy = vec![];
+
+    for port in ports {
+        if checked.contains(&(port.port, port.protocol.as_str())) {
+            continue;
+        }
+        checked.push((port.port, port.protocol.as_str()));
+
+        if !is_available(port) {
+            busy.push(format!("{}/{} ({})", port.port, port.protocol, port.origin));
+        }
+    }
+
+    if !busy.is_empty() {
+        return Err(Error::UpError(UpError::PortsInUse(busy.join(", "))));
+    }
+
+    Ok(())
+}
+
+// How a forwarded connection reaches the container port
+#[derive(Debug, Clone, PartialEq)]
+pub enum Route {
+    // The address of the container, when the host can reach it
+    Direct(SocketAddr),
+    // A relay run in the container for each connection, through `exec -i` of the engine cli
+    Exec { program: String, args: Vec<String> },
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Route::Direct(target) => write!(f, "{}", target),
+            Route::Exec { .. } => write!(f, "a relay in the container"),
+        }
+    }
+}
+
+// The container addresses are only routable from a linux host running the daemon itself: not
+// through the VM of Docker Desktop (`vm_socket`), nor on a remote (ssh, tcp) host
+pub fn is_container_ip_routable(host: Option<&str>, vm_socket: bool) -> bool {
+    if !cfg!(target_os = "linux") || vm_socket {
+        return false;
+    }
+
+    match host {
+        None => true,
+        Some(host) => host.starts_with("unix://") && !host.contains("/.docker/desktop/"),
+    }
+}
+
+// Command run in the container to relay stdin and stdout to the port, with socat or nc
+pub fn relay_command(container_port: u16) -> Vec<String> {
+    let script = "if command -v socat >/dev/null 2>&1; then exec socat - TCP:127.0.0.1:$0; \
+                  elif command -v nc >/dev/null 2>&1; then exec nc 127.0.0.1 $0; \
+                  else echo 'forward needs socat or nc in the container' >&2; exit 127; fi";
+
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+        container_port.to_string(),
+    ]
+}
+
+async fn proxy(mut inbound: TcpStream, target: SocketAddr) -> std::io::Result<()> {
+    let mut outbound = TcpStream::connect(target).await?;
+
+    let (mut ri, mut wi) = inbound.split();
+    let (mut ro, mut wo) = outbound.split();
+
+    tokio::try_join!(
+        tokio::io::copy(&mut ri, &mut wo),
+        tokio::io::copy(&mut ro, &mut wi)
+    )?;
+
+    Ok(())
+}
+
+async fn proxy_exec(mut inbound: TcpStream, program: &str, args: &[String]) -> std::io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+
+    let (mut ri, mut wi) = inbound.split();
+
+    // a client done sending still waits for the answer, the connection ends with the relay
+    let upload = async {
+        tokio::io::copy(&mut ri, &mut stdin).await?;
+        drop(stdin);
+        futures::future::pending::<std::io::Result<()>>().await
+    };
+
+    tokio::select! {
+        res = upload => res?,
+        res = tokio::io::copy(&mut stdout, &mut wi) => { res?; }
+    }
+
+    Ok(())
+}
+
+// Accepts connections on the host port and relays them through `route` until cancelled
+pub async fn forward(host_port: u16, route: Route) -> Result<(), Error> {
+    let mut listener = TcpListener::bind(("127.0.0.1", host_port))
+        .await
+        .map_err(|err| Error::Other(format!("Failed to listen on {}: {}", host_port, err)))?;
+
+    info!("Forwarding 127.0.0.1:{} -> {}", host_port, route);
+
+    loop {
+        let (inbound, peer) = listener
+            .accept()
+            .await
+            .map_err(|err| Error::Other(err.to_string()))?;
+        debug!("New connection from {}", peer);
+
+        let route = route.clone();
+        tokio::spawn(async move {
+            let res = match &route {
+                Route::Direct(target) => proxy(inbound, *target).await,
+                Route::Exec { program, args } => proxy_exec(inbound, program, args).await,
+            };
+            if let Err(err) = res {
+                warn!("Forwarded connection failed: {}", err);
+            }
+        });
+    }
+}
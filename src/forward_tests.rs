@@ -0,0 +1,65 @@
+use super::forward::*;
+
+#[test]
+fn test_check_ports() {
+    let port = |port: u16, protocol: &str, origin: &str| HostPort {
+        port,
+        protocol: protocol.to_string(),
+        origin: origin.to_string(),
+    };
+    let ports = vec![
+        port(3000, "tcp", "appPort"),
+        port(8080, "tcp", "forwardPorts"),
+        port(8080, "tcp", "service app"),
+        port(8080, "udp", "service app"),
+        port(5432, "tcp", "service db"),
+    ];
+
+    assert!(check_ports(&ports, &|_| true).is_ok());
+
+    let err = check_ports(&ports, &|p| p.port == 3000).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error trying to start project: Host ports are already in use: 8080/tcp (forwardPorts), 8080/udp (service app), 5432/tcp (service db)"
+    );
+}
+
+#[test]
+fn test_parse_port_spec() {
+    assert_eq!(parse_port_spec("5432").unwrap(), (5432, 5432));
+    assert_eq!(parse_port_spec("15432:5432").unwrap(), (15432, 5432));
+    assert!(parse_port_spec("abc").is_err());
+    assert!(parse_port_spec("1:abc").is_err());
+    assert!(parse_port_spec("70000").is_err());
+}
+
+#[test]
+fn test_is_container_ip_routable() {
+    let linux = cfg!(target_os = "linux");
+
+    assert_eq!(is_container_ip_routable(None, false), linux);
+    assert_eq!(
+        is_container_ip_routable(Some("unix:///var/run/docker.sock"), false),
+        linux
+    );
+    assert!(!is_container_ip_routable(None, true));
+    assert!(!is_container_ip_routable(
+        Some("unix:///home/dev/.docker/desktop/docker.sock"),
+        false
+    ));
+    assert!(!is_container_ip_routable(Some("ssh://dev@server"), false));
+    assert!(!is_container_ip_routable(
+        Some("tcp://127.0.0.1:2376"),
+        false
+    ));
+}
+
+#[test]
+fn test_relay_command() {
+    let command = relay_command(5432);
+
+    assert_eq!(command[..2], ["sh".to_string(), "-c".to_string()]);
+    assert!(command[2].contains("socat - TCP:127.0.0.1:$0"));
+    assert!(command[2].contains("nc 127.0.0.1 $0"));
+    assert_eq!(command[3], "5432");
+}
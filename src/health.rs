@@ -0,0 +1,231 @@
+use bollard::{
+    container::InspectContainerOptions,
+    service::{ContainerState, ContainerStateStatusEnum, HealthStatusEnum},
+    Docker,
+};
+use std::time::Duration;
+
+use super::errors::*;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Health {
+    // no HEALTHCHECK in the image, nothing to wait for
+    None,
+    Starting,
+    Healthy,
+    Unhealthy,
+    // created or restarting, not up yet
+    NotRunning,
+    // exited or dead
+    Stopped,
+}
+
+impl Health {
+    pub fn from_state(state: Option<&ContainerState>) -> Self {
+        match state.and_then(|s| s.status) {
+            Some(ContainerStateStatusEnum::CREATED)
+            | Some(ContainerStateStatusEnum::RESTARTING) => return Health::NotRunning,
+            Some(ContainerStateStatusEnum::EXITED) | Some(ContainerStateStatusEnum::DEAD) => {
+                return Health::Stopped
+            }
+            _ => {}
+        }
+
+        match state.and_then(|s| s.health.as_ref()).and_then(|h| h.status) {
+            Some(HealthStatusEnum::STARTING) => Health::Starting,
+            Some(HealthStatusEnum::HEALTHY) => Health::Healthy,
+            Some(HealthStatusEnum::UNHEALTHY) => Health::Unhealthy,
+            _ => Health::None,
+        }
+    }
+
+    // Whether waiting longer could change it
+    pub fn is_settled(&self) -> bool {
+        !matches!(self, Health::Starting | Health::NotRunning)
+    }
+
+    // Whether the hooks may fail because of it
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Health::Unhealthy | Health::Stopped)
+    }
+}
+
+// What a compose service must reach before the services depending on it can be used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    Started,
+    Healthy,
+    CompletedSuccessfully,
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Started => write!(f, "started"),
+            Condition::Healthy => write!(f, "healthy"),
+            Condition::CompletedSuccessfully => write!(f, "completed successfully"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Readiness {
+    Waiting,
+    Met,
+    // waiting longer won't meet the condition
+    Failed(String),
+}
+
+impl Condition {
+    pub fn check(&self, state: Option<&ContainerState>) -> Readiness {
+        let health = Health::from_state(state);
+
+        match self {
+            // a service exiting right away was started all the same
+            Condition::Started => match health {
+                Health::NotRunning => Readiness::Waiting,
+                _ => Readiness::Met,
+            },
+            Condition::Healthy => match health {
+                Health::Healthy => Readiness::Met,
+                Health::Starting | Health::NotRunning => Readiness::Waiting,
+                Health::None => Readiness::Failed("it has no healthcheck".to_string()),
+                Health::Unhealthy => Readiness::Failed("it is unhealthy".to_string()),
+                Health::Stopped => Readiness::Failed("it has stopped".to_string()),
+            },
+            Condition::CompletedSuccessfully => match health {
+                Health::Stopped => match state.and_then(|s| s.exit_code) {
+                    Some(0) => Readiness::Met,
+                    code => {
+                        Readiness::Failed(format!("it exited with code {}", code.unwrap_or(-1)))
+                    }
+                },
+                _ => Readiness::Waiting,
+            },
+        }
+    }
+}
+
+// A `depends_on` entry of the devcontainer service, with its container
+pub struct Dependency {
+    pub service: String,
+    pub container_id: String,
+    pub condition: Condition,
+}
+
+// Waits for every dependency to meet its condition, telling which ones are still waited for.
+// Fails as soon as one can't meet it anymore, or at the timeout
+pub async fn wait_dependencies(
+    docker: &Docker,
+    dependencies: &[Dependency],
+    timeout: Duration,
+) -> Result<(), Error> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut announced: Vec<String> = vec![];
+
+    loop {
+        let mut pending = vec![];
+
+        for dependency in dependencies {
+            let inspect = docker
+                .inspect_container(&dependency.container_id, None::<InspectContainerOptions>)
+                .await?;
+
+            match dependency.condition.check(inspect.state.as_ref()) {
+                Readiness::Met => {
+                    if announced.contains(&dependency.service) {
+                        info!("{} is {}", dependency.service, dependency.condition);
+                        announced.retain(|service| service != &dependency.service);
+                    }
+                }
+                Readiness::Waiting => pending.push(dependency),
+                Readiness::Failed(reason) => {
+                    return Err(Error::UpError(UpError::Dependencies(format!(
+                        "{} must be {} but {}",
+                        dependency.service, dependency.condition, reason
+                    ))))
+                }
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            let pending: Vec<String> = pending
+                .iter()
+                .map(|dependency| format!("{} ({})", dependency.service, dependency.condition))
+                .collect();
+            return Err(Error::UpError(UpError::Dependencies(format!(
+                "still waiting after {}s for {}",
+                timeout.as_secs(),
+                pending.join(", ")
+            ))));
+        }
+
+        for dependency in pending {
+            if !announced.contains(&dependency.service) {
+                info!(
+                    "Waiting for {} to be {}",
+                    dependency.service, dependency.condition
+                );
+                announced.push(dependency.service.clone());
+            }
+        }
+
+        tokio::time::delay_for(POLL_INTERVAL).await;
+    }
+}
+
+// Waits for the containers to run and their healthchecks to pass. Returns the name and health of
+// the ones still starting at the timeout, unhealthy or stopped
+pub async fn wait_healthy(
+    docker: &Docker,
+    ids: &[String],
+    timeout: Duration,
+) -> Result<Vec<(String, Health)>, Error> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut announced = false;
+
+    loop {
+        let mut pending = vec![];
+        let mut failed = vec![];
+
+        for id in ids {
+            let inspect = docker
+                .inspect_container(id, None::<InspectContainerOptions>)
+                .await?;
+            let name = inspect
+                .name
+                .as_deref()
+                .unwrap_or(id)
+                .trim_start_matches('/')
+                .to_string();
+
+            let health = Health::from_state(inspect.state.as_ref());
+            if !health.is_settled() {
+                pending.push((name, health));
+            } else if health.is_failed() {
+                failed.push((name, health));
+            }
+        }
+
+        if pending.is_empty() || tokio::time::Instant::now() >= deadline {
+            failed.extend(pending);
+            return Ok(failed);
+        }
+
+        if !announced {
+            let names: Vec<&str> = pending.iter().map(|(name, _)| name.as_str()).collect();
+            info!("Waiting for healthchecks: {}", names.join(", "));
+            announced = true;
+        }
+
+        tokio::time::delay_for(POLL_INTERVAL).await;
+    }
+}
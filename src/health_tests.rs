@@ -0,0 +1,125 @@
+use bollard::service::{
+    ContainerState, ContainerStateStatusEnum, Health as DockerHealth, HealthStatusEnum,
+};
+
+use super::health::*;
+
+fn state(status: Option<HealthStatusEnum>) -> ContainerState {
+    ContainerState {
+        health: Some(DockerHealth {
+            status,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_health_from_state() {
+    assert_eq!(Health::from_state(None), Health::None);
+    assert_eq!(
+        Health::from_state(Some(&ContainerState::default())),
+        Health::None
+    );
+    assert_eq!(
+        Health::from_state(Some(&state(Some(HealthStatusEnum::NONE)))),
+        Health::None
+    );
+    assert_eq!(
+        Health::from_state(Some(&state(Some(HealthStatusEnum::STARTING)))),
+        Health::Starting
+    );
+    assert_eq!(
+        Health::from_state(Some(&state(Some(HealthStatusEnum::HEALTHY)))),
+        Health::Healthy
+    );
+    assert_eq!(
+        Health::from_state(Some(&state(Some(HealthStatusEnum::UNHEALTHY)))),
+        Health::Unhealthy
+    );
+
+    let status = |status| ContainerState {
+        status: Some(status),
+        ..state(Some(HealthStatusEnum::HEALTHY))
+    };
+    assert_eq!(
+        Health::from_state(Some(&status(ContainerStateStatusEnum::RUNNING))),
+        Health::Healthy
+    );
+    assert_eq!(
+        Health::from_state(Some(&status(ContainerStateStatusEnum::RESTARTING))),
+        Health::NotRunning
+    );
+    assert_eq!(
+        Health::from_state(Some(&status(ContainerStateStatusEnum::EXITED))),
+        Health::Stopped
+    );
+}
+
+#[test]
+fn test_health_is_settled() {
+    assert!(!Health::Starting.is_settled());
+    assert!(Health::Healthy.is_settled());
+    assert!(Health::Unhealthy.is_settled());
+    assert!(Health::None.is_settled());
+    assert!(!Health::NotRunning.is_settled());
+    assert!(Health::Stopped.is_settled());
+
+    assert!(Health::Stopped.is_failed());
+    assert!(Health::Unhealthy.is_failed());
+    assert!(!Health::None.is_failed());
+}
+
+#[test]
+fn test_condition_check() {
+    let running = |status| ContainerState {
+        status: Some(ContainerStateStatusEnum::RUNNING),
+        ..state(status)
+    };
+    let exited = |code| ContainerState {
+        status: Some(ContainerStateStatusEnum::EXITED),
+        exit_code: Some(code),
+        ..Default::default()
+    };
+    let created = ContainerState {
+        status: Some(ContainerStateStatusEnum::CREATED),
+        ..Default::default()
+    };
+
+    assert_eq!(Condition::Started.check(Some(&created)), Readiness::Waiting);
+    assert_eq!(
+        Condition::Started.check(Some(&running(None))),
+        Readiness::Met
+    );
+    assert_eq!(Condition::Started.check(Some(&exited(1))), Readiness::Met);
+
+    assert_eq!(
+        Condition::Healthy.check(Some(&running(Some(HealthStatusEnum::STARTING)))),
+        Readiness::Waiting
+    );
+    assert_eq!(
+        Condition::Healthy.check(Some(&running(Some(HealthStatusEnum::HEALTHY)))),
+        Readiness::Met
+    );
+    assert!(matches!(
+        Condition::Healthy.check(Some(&running(Some(HealthStatusEnum::UNHEALTHY)))),
+        Readiness::Failed(_)
+    ));
+    assert!(matches!(
+        Condition::Healthy.check(Some(&running(None))),
+        Readiness::Failed(_)
+    ));
+
+    assert_eq!(
+        Condition::CompletedSuccessfully.check(Some(&running(None))),
+        Readiness::Waiting
+    );
+    assert_eq!(
+        Condition::CompletedSuccessfully.check(Some(&exited(0))),
+        Readiness::Met
+    );
+    assert_eq!(
+        Condition::CompletedSuccessfully.check(Some(&exited(2))),
+        Readiness::Failed("it exited with code 2".to_string())
+    );
+}
@@ -0,0 +1,126 @@
+use crate::devcontainer::{GpuRequirement, HostRequirements};
+use crate::errors::*;
+
+// What the docker host provides. None when it could not be detected
+#[derive(Debug, Default)]
+pub struct HostFacts {
+    pub cpus: Option<u64>,
+    pub memory: Option<u64>,
+    pub storage: Option<u64>,
+    pub gpu: bool,
+}
+
+impl HostFacts {
+    // From the output of `docker info --format '{{json .}}'` (or `podman info`, which nests them
+    // under "host"). Storage is not part of it, it is filled in separately
+    pub fn from_info(info: &serde_json::Value) -> Self {
+        HostFacts {
+            cpus: info["NCPU"]
+                .as_u64()
+                .or_else(|| info["host"]["cpus"].as_u64()),
+            memory: info["MemTotal"]
+                .as_u64()
+                .or_else(|| info["host"]["memTotal"].as_u64()),
+            storage: None,
+            gpu: info["Runtimes"]
+                .as_object()
+                .map(|runtimes| runtimes.contains_key("nvidia"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+// "4gb", "512mb", "1.5tb", or "4g" like docker -> bytes. Units are powers of 1024, no unit
+// means bytes
+pub fn parse_size(size: &str) -> Result<u64, Error> {
+    let invalid = || Error::InvalidConfig(format!("Invalid size: '{}'", size));
+
+    let lower = size.trim().to_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+
+    let multiplier: u64 = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        "t" | "tb" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1}gb", bytes as f64 / (1u64 << 30) as f64)
+}
+
+// Returns the unmet requirements and the warnings for the ones that could not be verified
+pub fn check(
+    reqs: &HostRequirements,
+    facts: &HostFacts,
+) -> Result<(Vec<String>, Vec<String>), Error> {
+    let mut unmet = vec![];
+    let mut warnings = vec![];
+
+    if let Some(cpus) = reqs.cpus {
+        match facts.cpus {
+            Some(available) if available < cpus => {
+                unmet.push(format!("{} cpus required, {} available", cpus, available))
+            }
+            Some(_) => {}
+            None => warnings.push("Could not detect the number of cpus".to_string()),
+        }
+    }
+
+    let sizes = [
+        ("memory", reqs.memory.as_ref(), facts.memory),
+        ("storage", reqs.storage.as_ref(), facts.storage),
+    ];
+    for (name, required, available) in sizes.iter() {
+        let required = match required {
+            Some(size) => parse_size(size)?,
+            None => continue,
+        };
+
+        match available {
+            Some(available) if *available < required => unmet.push(format!(
+                "{} {} required, {} available",
+                format_size(required),
+                name,
+                format_size(*available)
+            )),
+            Some(_) => {}
+            None => warnings.push(format!("Could not detect the available {}", name)),
+        }
+    }
+
+    if let Some(gpu) = reqs.gpu.as_ref() {
+        if gpu.is_required() && !facts.gpu {
+            unmet.push("a gpu is required, none available".to_string());
+        } else if gpu.is_wanted() && !facts.gpu {
+            warnings.push("No gpu available, continuing without it".to_string());
+        }
+
+        if let GpuRequirement::Detailed { .. } = gpu {
+            warnings.push("gpu cores and memory requirements are not verified".to_string());
+        }
+    }
+
+    Ok((unmet, warnings))
+}
+
+// Available bytes from the output of `df -Pk <path>`
+pub fn parse_df_available(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+}
@@ -0,0 +1,86 @@
+use crate::devcontainer::{GpuRequirement, HostRequirements};
+
+use super::host_requirements::*;
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("4gb").unwrap(), 4 * 1024 * 1024 * 1024);
+    assert_eq!(parse_size("512MB").unwrap(), 512 * 1024 * 1024);
+    assert_eq!(parse_size("1.5kb").unwrap(), 1536);
+    assert_eq!(parse_size("100").unwrap(), 100);
+    assert_eq!(parse_size("4g").unwrap(), 4 * 1024 * 1024 * 1024);
+    assert!(parse_size("4 parsecs").is_err());
+    assert!(parse_size("gb").is_err());
+}
+
+#[test]
+fn test_from_info() {
+    let info = serde_json::json!({
+        "NCPU": 8,
+        "MemTotal": 16777216000u64,
+        "Runtimes": { "runc": {}, "nvidia": {} }
+    });
+    let facts = HostFacts::from_info(&info);
+    assert_eq!(facts.cpus, Some(8));
+    assert_eq!(facts.memory, Some(16777216000));
+    assert!(facts.gpu);
+
+    let facts = HostFacts::from_info(&serde_json::json!({ "NCPU": 2 }));
+    assert_eq!(facts.memory, None);
+    assert!(!facts.gpu);
+}
+
+#[test]
+fn test_parse_df_available() {
+    let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/sda1        102400000  51200000  51200000      50% /\n";
+    assert_eq!(parse_df_available(output), Some(51200000 * 1024));
+    assert_eq!(parse_df_available(""), None);
+}
+
+#[test]
+fn test_check() {
+    let reqs = HostRequirements {
+        cpus: Some(4),
+        memory: Some("8gb".to_string()),
+        storage: Some("32gb".to_string()),
+        gpu: Some(GpuRequirement::Optional("optional".to_string())),
+    };
+
+    let facts = HostFacts {
+        cpus: Some(2),
+        memory: Some(16 * 1024 * 1024 * 1024),
+        storage: None,
+        gpu: false,
+    };
+
+    let (unmet, warnings) = check(&reqs, &facts).unwrap();
+    assert_eq!(unmet, vec!["4 cpus required, 2 available".to_string()]);
+    assert_eq!(warnings.len(), 2);
+
+    let reqs = HostRequirements {
+        gpu: Some(GpuRequirement::Required(true)),
+        ..Default::default()
+    };
+    let (unmet, _) = check(&reqs, &facts).unwrap();
+    assert_eq!(unmet.len(), 1);
+
+    let reqs = HostRequirements {
+        gpu: Some(GpuRequirement::Required(false)),
+        ..Default::default()
+    };
+    let (unmet, warnings) = check(&reqs, &facts).unwrap();
+    assert!(unmet.is_empty() && warnings.is_empty());
+}
+
+#[test]
+fn test_host_facts_from_podman_info() {
+    let info = serde_json::json!({
+        "host": { "cpus": 8, "memTotal": 16_000_000_000u64 },
+        "store": { "graphRoot": "/home/me/.local/share/containers/storage" },
+    });
+
+    let facts = HostFacts::from_info(&info);
+    assert_eq!(facts.cpus, Some(8));
+    assert_eq!(facts.memory, Some(16_000_000_000));
+    assert!(!facts.gpu);
+}
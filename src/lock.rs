@@ -0,0 +1,116 @@
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::errors::*;
+use super::registry::ImageRef;
+
+// Next to devcontainer.json
+pub const LOCK_FILENAME: &str = "devcontainer.lock.json";
+
+// Digests resolved by `up` and `build`, reused as they are with --frozen
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockFile {
+    // `image` of the config, or the FROM images of the Dockerfile, to their digest
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub images: BTreeMap<String, String>,
+
+    // by the reference used in `features`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, LockedFeature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedFeature {
+    // registry/repository@digest of the feature manifest
+    pub resolved: String,
+    // sha256 of the feature tarball
+    pub integrity: String,
+}
+
+impl LockFile {
+    // None when there is no lock file yet
+    pub fn load(path: &Path) -> Result<Option<Self>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents =
+            std::fs::read_to_string(path).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|err| Error::InvalidConfig(format!("{}: {}", path.display(), err)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|err| Error::Other(err.to_string()))?;
+
+        std::fs::write(path, contents + "\n").map_err(|err| Error::Other(err.to_string()))
+    }
+
+    // The image pinned to its locked digest
+    pub fn pinned_image(&self, image: &str) -> Result<String, Error> {
+        self.images
+            .get(image)
+            .map(|digest| pin_image(image, digest))
+            .ok_or_else(|| not_locked(image))
+    }
+
+    pub fn locked_feature(&self, reference: &str) -> Result<&LockedFeature, Error> {
+        self.features
+            .get(reference)
+            .ok_or_else(|| not_locked(reference))
+    }
+}
+
+fn not_locked(reference: &str) -> Error {
+    Error::InvalidConfig(format!(
+        "{} is not in {}, run without --frozen to update it",
+        reference, LOCK_FILENAME
+    ))
+}
+
+// "ubuntu:22.04" and "sha256:..." -> "ubuntu@sha256:...". The daemon ignores a tag next to a
+// digest, so it is dropped
+pub fn pin_image(image: &str, digest: &str) -> String {
+    let name = image.split('@').next().unwrap_or(image);
+    let name = match name.rfind(':') {
+        Some(idx) if !name[idx..].contains('/') => &name[..idx],
+        _ => name,
+    };
+
+    format!("{}@{}", name, digest)
+}
+
+// The digest of the image among the RepoDigests of its local copy. An image pushed to several
+// repositories has one per repository
+pub fn repo_digest(image: &str, repo_digests: &[String]) -> Option<String> {
+    let wanted = repository(image);
+
+    repo_digests
+        .iter()
+        .find(|entry| repository(entry) == wanted)
+        .and_then(|entry| ImageRef::parse(entry).digest)
+}
+
+// registry/repository, with the names of Docker Hub made the same
+fn repository(image: &str) -> String {
+    let image = ImageRef::parse(image);
+    let registry = match image.registry.as_str() {
+        "docker.io" | "index.docker.io" => "registry-1.docker.io",
+        registry => registry,
+    };
+
+    format!("{}/{}", registry, image.repository)
+}
+
+// sha256 of the feature tarball, as recorded in `integrity`
+pub fn integrity(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(data);
+
+    format!("sha256:{}", hasher.result_str())
+}
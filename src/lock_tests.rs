@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use super::lock::*;
+
+const DIGEST: &str = "sha256:4b1f5e0a7f6ad2e4f9a3b1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6";
+
+#[test]
+fn test_pin_image() {
+    assert_eq!(
+        pin_image("ubuntu:22.04", DIGEST),
+        format!("ubuntu@{}", DIGEST)
+    );
+    assert_eq!(
+        pin_image("localhost:5000/devcontainers/base", DIGEST),
+        format!("localhost:5000/devcontainers/base@{}", DIGEST)
+    );
+    assert_eq!(
+        pin_image("ghcr.io/org/img:1@sha256:old", DIGEST),
+        format!("ghcr.io/org/img@{}", DIGEST)
+    );
+}
+
+#[test]
+fn test_repo_digest() {
+    let repo_digests = vec![
+        "ghcr.io/org/ubuntu@sha256:other".to_string(),
+        format!("ubuntu@{}", DIGEST),
+    ];
+
+    assert_eq!(
+        repo_digest("ubuntu:22.04", &repo_digests),
+        Some(DIGEST.to_string())
+    );
+    assert_eq!(
+        repo_digest("docker.io/library/ubuntu:latest", &repo_digests),
+        Some(DIGEST.to_string())
+    );
+    assert_eq!(repo_digest("mcr.microsoft.com/base:1", &repo_digests), None);
+}
+
+#[test]
+fn test_integrity() {
+    assert_eq!(
+        integrity(b"hello"),
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_save_load() {
+    let path = PathBuf::from("/tmp/devcontainers-rs-lock-test.json");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(LockFile::load(&path).unwrap(), None);
+
+    let mut lock_file = LockFile::default();
+    lock_file
+        .images
+        .insert("ubuntu:22.04".to_string(), DIGEST.to_string());
+    lock_file.features.insert(
+        "ghcr.io/devcontainers/features/node:1".to_string(),
+        LockedFeature {
+            resolved: format!("ghcr.io/devcontainers/features/node@{}", DIGEST),
+            integrity: integrity(b"feature"),
+        },
+    );
+    lock_file.save(&path).unwrap();
+
+    let loaded = LockFile::load(&path).unwrap().unwrap();
+    assert_eq!(loaded, lock_file);
+    assert_eq!(
+        loaded.pinned_image("ubuntu:22.04").unwrap(),
+        format!("ubuntu@{}", DIGEST)
+    );
+    assert!(loaded.pinned_image("debian:12").is_err());
+    assert!(loaded
+        .locked_feature("ghcr.io/devcontainers/features/go:1")
+        .is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,65 @@
+// Colors of the service prefixes, in the order docker-compose gives them
+const COLORS: &[u8] = &[36, 33, 32, 35, 34, 96, 93, 92, 95, 94];
+
+// Puts `<service> | ` in front of each line of the logs of a service. The chunks of the log
+// stream are not split on lines, the end of a line is kept until the rest of it comes
+pub struct LinePrefixer {
+    prefix: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+impl LinePrefixer {
+    pub fn new(prefix: String) -> Self {
+        LinePrefixer {
+            prefix: prefix.into_bytes(),
+            pending: vec![],
+        }
+    }
+
+    // The complete lines of what came so far
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+
+        let end = match self.pending.iter().rposition(|b| *b == b'\n') {
+            Some(end) => end + 1,
+            None => return vec![],
+        };
+        let lines: Vec<u8> = self.pending.drain(..end).collect();
+
+        let mut output = vec![];
+        for line in lines.split_inclusive(|b| *b == b'\n') {
+            output.extend_from_slice(&self.prefix);
+            output.extend_from_slice(line);
+        }
+        output
+    }
+
+    // What is left once the stream ended, as a last line
+    pub fn flush(&mut self) -> Vec<u8> {
+        if self.pending.is_empty() {
+            return vec![];
+        }
+
+        let mut output = self.prefix.clone();
+        output.append(&mut self.pending);
+        output.push(b'\n');
+        output
+    }
+}
+
+// `<service> | ` for each service, the names padded to the same width
+pub fn prefixes(services: &[String], color: bool) -> Vec<String> {
+    let width = services.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    services
+        .iter()
+        .enumerate()
+        .map(|(i, service)| {
+            let prefix = format!("{:width$} | ", service, width = width);
+            match color {
+                true => format!("\x1b[{}m{}\x1b[0m", COLORS[i % COLORS.len()], prefix),
+                false => prefix,
+            }
+        })
+        .collect()
+}
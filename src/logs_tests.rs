@@ -0,0 +1,32 @@
+use super::logs::*;
+
+#[test]
+fn test_line_prefixer() {
+    let mut prefixer = LinePrefixer::new("app | ".to_string());
+
+    assert_eq!(
+        prefixer.push(b"first line\nsec"),
+        b"app | first line\n".to_vec()
+    );
+    assert_eq!(prefixer.push(b"ond"), b"".to_vec());
+    assert_eq!(
+        prefixer.push(b" line\nthird\nlast"),
+        b"app | second line\napp | third\n".to_vec()
+    );
+    assert_eq!(prefixer.flush(), b"app | last\n".to_vec());
+    assert_eq!(prefixer.flush(), b"".to_vec());
+}
+
+#[test]
+fn test_prefixes() {
+    let services = vec!["app".to_string(), "postgres".to_string()];
+
+    assert_eq!(
+        prefixes(&services, false),
+        vec!["app      | ", "postgres | "]
+    );
+    assert_eq!(
+        prefixes(&services, true),
+        vec!["\x1b[36mapp      | \x1b[0m", "\x1b[33mpostgres | \x1b[0m"]
+    );
+}
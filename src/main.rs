@@ -1,35 +1,152 @@
 #[macro_use]
 extern crate log;
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, Shell, SubCommand};
 use std::path::PathBuf;
-use tokio;
+
+mod features;
+#[cfg(test)]
+mod features_tests;
+
+mod forward;
+#[cfg(test)]
+mod forward_tests;
+
+mod health;
+#[cfg(test)]
+mod health_tests;
+
+mod host_requirements;
+#[cfg(test)]
+mod host_requirements_tests;
+
+mod lock;
+#[cfg(test)]
+mod lock_tests;
+
+mod logs;
+#[cfg(test)]
+mod logs_tests;
 
 mod mount_from_str;
 #[cfg(test)]
 mod mount_from_str_tests;
 
+mod dockerfile;
+#[cfg(test)]
+mod dockerfile_tests;
+
+mod docker_context;
+#[cfg(test)]
+mod docker_context_tests;
+
+mod docker_host;
+#[cfg(test)]
+mod docker_host_tests;
+
+mod api_version;
+#[cfg(test)]
+mod api_version_tests;
+
+mod config_merge;
+#[cfg(test)]
+mod config_merge_tests;
+
 mod devcontainer;
 #[cfg(test)]
 mod devcontainer_tests;
 
+mod registry;
+#[cfg(test)]
+mod registry_tests;
+
+mod registry_auth;
+#[cfg(test)]
+mod registry_auth_tests;
+
+mod resources;
+#[cfg(test)]
+mod resources_tests;
+
+mod retry;
+#[cfg(test)]
+mod retry_tests;
+
+mod run_args;
+#[cfg(test)]
+mod run_args_tests;
+
 mod settings;
 mod settings_compose_model;
+#[cfg(test)]
+mod settings_tests;
+
+mod ssh_tunnel;
+#[cfg(test)]
+mod ssh_tunnel_tests;
+
+mod state;
+#[cfg(test)]
+mod state_tests;
 
 mod project;
 #[cfg(test)]
 mod project_tests;
 
+mod engine;
+#[cfg(test)]
+mod engine_tests;
+
+mod nerdctl;
+#[cfg(test)]
+mod nerdctl_tests;
+
+mod compose;
+#[cfg(test)]
+mod compose_tests;
+
 mod errors;
 
-#[tokio::main]
-async fn main() {
-    let env = env_logger::Env::default()
-        .filter_or("LOG_LEVEL", "info")
-        .write_style_or("LOG_STYLE", "always");
-    env_logger::init_from_env(env);
+mod archive;
+#[cfg(test)]
+mod archive_tests;
+
+mod export;
+#[cfg(test)]
+mod export_tests;
 
-    let matches = App::new("devcontainer-rs")
+mod prompt;
+#[cfg(test)]
+mod prompt_tests;
+
+mod secrets;
+#[cfg(test)]
+mod secrets_tests;
+
+mod substitution;
+#[cfg(test)]
+mod substitution_tests;
+
+mod templates;
+#[cfg(test)]
+mod templates_tests;
+
+mod tui;
+
+mod upgrade_config;
+#[cfg(test)]
+mod upgrade_config_tests;
+
+mod watcher;
+#[cfg(test)]
+mod watcher_tests;
+
+mod wsl;
+#[cfg(test)]
+mod wsl_tests;
+
+fn build_cli() -> App<'static, 'static> {
+    App::new("devcontainer-rs")
         .version("0.1")
         .author("Gustavo Sampaio <gbritosampaio@gmail.com>")
         .about("An open-source runner for the devcontainer format")
@@ -38,7 +155,84 @@ async fn main() {
                 .short("a")
                 .long("host")
                 .value_name("STRING")
-                .help("Use the specified address to connect to docker")
+                .help("Use the specified address to connect to docker (tcp://, unix:// or ssh://user@host). Defaults to DOCKER_HOST")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .value_name("NAME")
+                .conflicts_with("docker-host")
+                .help("Use the endpoint of a docker cli context. Defaults to DOCKER_CONTEXT or the current context")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tlsverify")
+                .long("tlsverify")
+                .help("Use TLS with client certificates to connect to the docker host (also DOCKER_TLS_VERIFY)")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("tls-cert-path")
+                .long("tls-cert-path")
+                .value_name("DIR")
+                .env("DOCKER_CERT_PATH")
+                .help("Folder with the ca.pem, cert.pem and key.pem used with --tlsverify. Defaults to ~/.docker")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("docker-timeout")
+                .long("docker-timeout")
+                .value_name("SECONDS")
+                .help("Timeout of the requests to docker. Defaults to 120")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("platform")
+                .long("platform")
+                .value_name("OS/ARCH")
+                .env("DOCKER_DEFAULT_PLATFORM")
+                .help("Platform of the images pulled and built, e.g. linux/amd64 or linux/arm64")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ssh-forward")
+                .long("ssh-forward")
+                .help("Forward the ssh agent to the image builds, for RUN --mount=type=ssh")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("lock")
+                .long("lock")
+                .help("Record the image and feature digests in devcontainer.lock.json")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("frozen")
+                .long("frozen")
+                .help("Use the digests of devcontainer.lock.json instead of resolving them again")
+                .conflicts_with("lock")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("pull-retries")
+                .long("pull-retries")
+                .value_name("COUNT")
+                .help("Times a failed image pull is retried on network errors. Defaults to 2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pull-retry-delay")
+                .long("pull-retry-delay")
+                .value_name("SECONDS")
+                .help("Seconds before the first pull retry, doubled for every next one. Defaults to 2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("secrets-file")
+                .long("secrets-file")
+                .value_name("FILE")
+                .help("JSON or dotenv file with secrets given to the hooks, exec, shell and application as environment variables. They are not stored in the container")
                 .takes_value(true),
         )
         .arg(
@@ -48,6 +242,27 @@ async fn main() {
                 .help("Ignore global user settings")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Answer yes to every question")
+                .conflicts_with("no-input")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("no-input")
+                .long("no-input")
+                .help("Never ask questions, use the default answers")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("NAME")
+                .help("Use the configuration in .devcontainer/NAME/devcontainer.json")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("path")
                 .short("c")
@@ -65,25 +280,617 @@ async fn main() {
                         .long("no-wait")
                         .help("Do not wait for the client")
                         .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("skip-post-create")
+                        .long("skip-post-create")
+                        .help("Do not run postCreateCommand")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("skip-post-start")
+                        .long("skip-post-start")
+                        .help("Do not run postStartCommand")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("skip-post-attach")
+                        .long("skip-post-attach")
+                        .help("Do not run postAttachCommand")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("auto-rebuild")
+                        .long("auto-rebuild")
+                        .help(
+                            "Rebuild the container when the configuration changes, without asking",
+                        )
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("on-exit")
+                        .long("on-exit")
+                        .value_name("POLICY")
+                        .possible_values(&["restart", "prompt", "exit"])
+                        .help("When the container dies while waiting: restart it and run postStartCommand again, ask, or stop waiting (default)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("transfer-workspace")
+                        .long("transfer-workspace")
+                        .alias("workspace-in-volume")
+                        .help("Copy the workspace into a volume instead of bind mounting it. Needed for remote docker hosts, faster on macOS and Windows")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("clone-workspace")
+                        .long("clone-workspace")
+                        .help("Like --transfer-workspace, with a git clone of the workspace origin instead of a copy")
+                        .conflicts_with("transfer-workspace")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("ignore-host-requirements")
+                        .long("ignore-host-requirements")
+                        .help("Only warn when the docker host does not meet hostRequirements")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("tui")
+                        .long("tui")
+                        .help("Show a dashboard while waiting for the client")
+                        .conflicts_with("no-wait")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("no-hooks")
+                        .long("no-hooks")
+                        .help("Do not run any lifecycle hook")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("container-id")
+                        .long("container-id")
+                        .value_name("ID")
+                        .help("Use an already running container (id or name) instead of creating one. It is left running on exit")
+                        .conflicts_with_all(&["recreate", "transfer-workspace", "clone-workspace"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("recreate")
+                        .long("recreate")
+                        .alias("remove-existing-container")
+                        .help("Remove the existing container and create a new one from the current config"),
+                )
+                .arg(
+                    Arg::with_name("fresh")
+                        .long("fresh")
+                        .help("Remove the snapshot saved by down --snapshot and start from the image"),
+                )
+                .arg(
+                    Arg::with_name("build")
+                        .long("build")
+                        .help("Build the images of the compose services before starting them"),
+                )
+                .arg(
+                    Arg::with_name("no-cache")
+                        .long("no-cache")
+                        .help("Build the images of the compose services without the build cache. Implies --build"),
+                )
+                .arg(
+                    Arg::with_name("pull")
+                        .long("pull")
+                        .help("Pull newer versions of the base images of the compose services. Implies --build"),
                 ),
         )
-        .subcommand(SubCommand::with_name("down").about("stops the devcontainer"))
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("down")
+                .about("stops the devcontainer")
+                .arg(
+                    Arg::with_name("remove")
+                        .long("remove")
+                        .help("Remove the containers, networks, volumes and temp files created for the project")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("volumes")
+                        .long("volumes")
+                        .help("Also remove the container volumes (compose volumes in compose mode). Implies --remove"),
+                )
+                .arg(
+                    Arg::with_name("rmi")
+                        .long("rmi")
+                        .help("Also remove the images built for the project. Implies --remove"),
+                )
+                .arg(
+                    Arg::with_name("remove-orphans")
+                        .long("remove-orphans")
+                        .help("Also remove the containers of services no longer in the compose files. Implies --remove"),
+                )
+                .arg(
+                    Arg::with_name("snapshot")
+                        .long("snapshot")
+                        .help("Commit the container to an image and remove the container. The next up starts from the image, skipping postCreateCommand")
+                        .conflicts_with_all(&["remove", "volumes", "rmi", "remove-orphans"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("exec")
+                .about("executes a command inside the devcontainer")
+                .setting(AppSettings::TrailingVarArg)
+                .arg(
+                    Arg::with_name("service")
+                        .long("service")
+                        .value_name("NAME")
+                        .help("Compose service to run the command in, as its own user. Defaults to the devcontainer service")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("cmd")
+                        .multiple(true)
+                        .required(true)
+                        .help("Command to execute"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("run-user-commands")
+                .about("runs the lifecycle commands again in the existing container. Runs all of them when none is selected")
+                .arg(
+                    Arg::with_name("post-create")
+                        .long("post-create")
+                        .help("Run postCreateCommand"),
+                )
+                .arg(
+                    Arg::with_name("post-start")
+                        .long("post-start")
+                        .help("Run postStartCommand"),
+                )
+                .arg(
+                    Arg::with_name("post-attach")
+                        .long("post-attach")
+                        .help("Run postAttachCommand"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("shell")
+                .about("opens an interactive shell inside the devcontainer")
+                .alias("attach")
+                .arg(
+                    Arg::with_name("service")
+                        .long("service")
+                        .value_name("NAME")
+                        .help("Compose service to open the shell in, as its own user. Defaults to the devcontainer service")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("shell")
+                        .help("Shell to run. Defaults to bash when available, sh otherwise"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("init")
+                .about("creates a .devcontainer folder for the project")
+                .arg(
+                    Arg::with_name("template")
+                        .long("template")
+                        .value_name("NAME")
+                        .possible_values(&["base", "rust", "node", "python", "go"])
+                        .default_value("base")
+                        .help("Template to start from"),
+                )
+                .arg(
+                    Arg::with_name("dockerfile")
+                        .long("dockerfile")
+                        .help("Builds from a Dockerfile based on the template image"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrites an existing config"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("templates")
+                .about("applies Dev Container Templates published to OCI registries")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("apply")
+                        .about("writes the files of a template into the project")
+                        .arg(
+                            Arg::with_name("template")
+                                .required(true)
+                                .help("Template reference, e.g. ghcr.io/devcontainers/templates/rust:latest"),
+                        )
+                        .arg(
+                            Arg::with_name("option")
+                                .short("o")
+                                .long("option")
+                                .value_name("KEY=VALUE")
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("Template option"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Overwrites existing files"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("builds or pulls the devcontainer images without starting any container")
+                .arg(
+                    Arg::with_name("no-cache")
+                        .long("no-cache")
+                        .help("Builds the image without using the cache"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("read-configuration")
+                .about("prints the devcontainer.json merged with the user settings, as JSON")
+                .arg(
+                    Arg::with_name("customizations")
+                        .long("customizations")
+                        .value_name("TOOL")
+                        .help("Prints only the customizations of a tool, e.g. vscode or neovim")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pause")
+                .about("pauses the devcontainer, keeping its processes and memory"),
+        )
+        .subcommand(SubCommand::with_name("unpause").about("resumes a paused devcontainer"))
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("writes the devcontainer as a docker run script or a docker-compose.yml")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["run", "compose"])
+                        .default_value("run")
+                        .help("run: shell script with docker run, compose: docker-compose.yml"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("File to write to, defaults to stdout"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("shows the container, ports and mounts of the devcontainer"),
+        )
+        .subcommand(
+            SubCommand::with_name("logs")
+                .about("shows the logs of the devcontainer")
+                .arg(
+                    Arg::with_name("follow")
+                        .short("f")
+                        .long("follow")
+                        .help("Keeps streaming new logs"),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .value_name("TIME")
+                        .help("Shows logs since a unix timestamp or a relative time (e.g. 30s, 10m, 2h, 1d)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("tail")
+                        .long("tail")
+                        .value_name("LINES")
+                        .help("Number of lines to show from the end of the logs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("service")
+                        .long("service")
+                        .value_name("NAME")
+                        .help("Compose service to show the logs of, can be repeated. Defaults to the devcontainer service and runServices")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("forward")
+                .about("forwards a local port to the running devcontainer until interrupted")
+                .arg(
+                    Arg::with_name("service")
+                        .long("service")
+                        .value_name("NAME")
+                        .help("Compose service to forward to. Defaults to the devcontainer service")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .required(true)
+                        .help("PORT or HOST_PORT:CONTAINER_PORT"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("lists the devcontainers managed by this tool, across all projects")
+                .alias("ps"),
+        )
+        .subcommand(
+            SubCommand::with_name("prune")
+                .about("removes stopped devcontainers whose workspace folder no longer exists, and their unused images")
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Only lists what would be removed"),
+                )
+                .arg(
+                    Arg::with_name("older-than")
+                        .long("older-than")
+                        .value_name("DAYS")
+                        .help("Also removes the stopped devcontainers not used for this many days")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("features")
+                .about("manages the Dev Container Features of the project")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("list").about("lists the features in devcontainer.json"),
+                )
+                .subcommand(
+                    SubCommand::with_name("info")
+                        .about("shows the metadata of a feature from its registry")
+                        .arg(
+                            Arg::with_name("feature")
+                                .required(true)
+                                .help("Feature reference, e.g. ghcr.io/devcontainers/features/node:1"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("adds a feature to devcontainer.json")
+                        .arg(
+                            Arg::with_name("feature")
+                                .required(true)
+                                .help("Feature reference, e.g. ghcr.io/devcontainers/features/node:1"),
+                        )
+                        .arg(
+                            Arg::with_name("option")
+                                .short("o")
+                                .long("option")
+                                .value_name("KEY=VALUE")
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("Feature option"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("outdated")
+                .about("checks if newer versions of the devcontainer images and features are available")
+                .arg(
+                    Arg::with_name("pull")
+                        .long("pull")
+                        .help("Pulls the images that have a newer version"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sync")
+                .about("copies the workspace between the host and a transferred workspace volume")
+                .arg(
+                    Arg::with_name("direction")
+                        .possible_values(&["push", "pull"])
+                        .required(true)
+                        .help("push: host to container, pull: container to host"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("upgrade-config")
+                .about("rewrites deprecated devcontainer.json fields into their current form")
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Prints the upgraded config instead of writing it"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("prints the shell completion script")
+                .arg(
+                    Arg::with_name("shell")
+                        .possible_values(&Shell::variants())
+                        .required(true)
+                        .help("Shell to generate the completions for"),
+                ),
+        )
+}
 
-    let path = matches.value_of("path").map(PathBuf::from);
+#[tokio::main]
+async fn main() {
+    let env = env_logger::Env::default()
+        .filter_or("LOG_LEVEL", "info")
+        .write_style_or("LOG_STYLE", "always");
+    tui::DashboardLogger::init(env);
+
+    let matches = build_cli().get_matches();
+
+    if let Some(sub_matches) = matches.subcommand_matches("completions") {
+        let shell: Shell = sub_matches.value_of("shell").unwrap().parse().unwrap();
+        build_cli().gen_completions_to(env!("CARGO_PKG_NAME"), shell, &mut std::io::stdout());
+        return;
+    }
+
+    let path = matches
+        .value_of("path")
+        .map(|p| PathBuf::from(wsl::translate_path(p)));
 
     let should_load_user_settings = match matches.is_present("no-user-settings") {
         true => Some(false),
         false => None,
     };
 
+    let mut skip_hooks = vec![];
+    let mut transfer_workspace = false;
+    let mut clone_workspace = false;
+    let mut recreate = false;
+    let mut fresh = false;
+    let mut ignore_host_requirements = false;
+    let mut build_images = false;
+    let mut no_cache = false;
+    let mut pull = false;
+    if let Some(sub_matches) = matches.subcommand_matches("up") {
+        transfer_workspace = sub_matches.is_present("transfer-workspace");
+        clone_workspace = sub_matches.is_present("clone-workspace");
+        recreate = sub_matches.is_present("recreate");
+        fresh = sub_matches.is_present("fresh");
+        no_cache = sub_matches.is_present("no-cache");
+        pull = sub_matches.is_present("pull");
+        build_images = sub_matches.is_present("build") || no_cache || pull;
+        ignore_host_requirements = sub_matches.is_present("ignore-host-requirements");
+        let no_hooks = sub_matches.is_present("no-hooks");
+        if no_hooks || sub_matches.is_present("skip-post-create") {
+            skip_hooks.push(project::CommandHook::PostCreate);
+        }
+        if no_hooks || sub_matches.is_present("skip-post-start") {
+            skip_hooks.push(project::CommandHook::PostStart);
+        }
+        if no_hooks || sub_matches.is_present("skip-post-attach") {
+            skip_hooks.push(project::CommandHook::PostAttach);
+        }
+    }
+
+    let prompt_mode = if matches.is_present("yes") {
+        prompt::PromptMode::AssumeYes
+    } else if matches.is_present("no-input") {
+        prompt::PromptMode::NoInput
+    } else {
+        prompt::PromptMode::Interactive
+    };
+
+    let env_host = std::env::var("DOCKER_HOST").ok();
+    let env_context = std::env::var("DOCKER_CONTEXT").ok();
+    let endpoint = docker_context::resolve_endpoint(
+        &docker_context::EndpointSources {
+            flag_host: matches.value_of("docker-host"),
+            flag_context: matches.value_of("context"),
+            env_host: env_host.as_deref(),
+            env_context: env_context.as_deref(),
+        },
+        &docker_context::config_dir(),
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    // like the docker cli, any value of DOCKER_TLS_VERIFY enables it. A context brings its own
+    // certificates otherwise
+    let tls = match matches.is_present("tlsverify")
+        || std::env::var("DOCKER_TLS_VERIFY")
+            .map(|v| !v.is_empty())
+            .unwrap_or(false)
+    {
+        true => Some(docker_host::TlsCerts::new(
+            matches.value_of("tls-cert-path").map(PathBuf::from),
+        )),
+        false => endpoint.tls,
+    };
+
+    let docker_timeout = matches.value_of("docker-timeout").map(|timeout| {
+        timeout
+            .parse::<u64>()
+            .unwrap_or_else(|_| panic!("Invalid --docker-timeout: '{}'", timeout))
+    });
+
+    let pull_retries = matches.value_of("pull-retries").map(|retries| {
+        retries
+            .parse::<u32>()
+            .unwrap_or_else(|_| panic!("Invalid --pull-retries: '{}'", retries))
+    });
+    let pull_retry_delay = matches.value_of("pull-retry-delay").map(|delay| {
+        delay
+            .parse::<u64>()
+            .unwrap_or_else(|_| panic!("Invalid --pull-retry-delay: '{}'", delay))
+    });
+
     let mut project = project::Project::new(project::ProjectOpts {
         path,
         should_load_user_settings,
+        skip_hooks,
+        prompt_mode,
+        transfer_workspace,
+        clone_workspace,
+        recreate,
+        fresh,
+        build_images,
+        no_cache,
+        pull,
+        ignore_host_requirements,
+        secrets_file: matches.value_of("secrets-file").map(PathBuf::from),
+        config: matches.value_of("config").map(|s| s.to_string()),
+        tls,
+        docker_timeout,
+        pull_retries,
+        pull_retry_delay,
+        platform: matches.value_of("platform").map(|s| s.to_string()),
+        ssh_forward: matches.is_present("ssh-forward"),
+        lock: matches.is_present("lock"),
+        frozen: matches.is_present("frozen"),
         ..project::ProjectOpts::default()
     })
     .unwrap();
-    project.docket_host = matches.value_of("docker-host").map(|s| s.to_string());
+    project.docket_host = endpoint.host;
+
+    // these don't depend on an existing config
+    match matches.subcommand() {
+        ("init", Some(sub_matches)) => {
+            project
+                .init(
+                    sub_matches.value_of("template").unwrap(),
+                    sub_matches.is_present("dockerfile"),
+                    sub_matches.is_present("force"),
+                )
+                .await
+                .unwrap();
+            return;
+        }
+        ("list", Some(_)) => {
+            project.list().await.unwrap();
+            return;
+        }
+        ("templates", Some(sub_matches)) => {
+            if let ("apply", Some(apply_matches)) = sub_matches.subcommand() {
+                let options = apply_matches
+                    .values_of("option")
+                    .map(|values| values.map(|v| v.to_string()).collect())
+                    .unwrap_or_default();
+
+                project
+                    .apply_template(
+                        apply_matches.value_of("template").unwrap(),
+                        options,
+                        apply_matches.is_present("force"),
+                    )
+                    .await
+                    .unwrap();
+            }
+            return;
+        }
+        ("prune", Some(sub_matches)) => {
+            let older_than = sub_matches.value_of("older-than").map(|days| {
+                days.parse::<u64>()
+                    .unwrap_or_else(|_| panic!("Invalid --older-than: '{}'", days))
+            });
+            project
+                .prune(sub_matches.is_present("dry-run"), older_than)
+                .await
+                .unwrap();
+            return;
+        }
+        _ => {}
+    }
 
     if let Err(err) = project.load().await {
         panic!("Error found validating the config file: {}", err);
@@ -93,9 +900,153 @@ async fn main() {
         ("up", Some(sub_matches)) => {
             let should_wait = !sub_matches.is_present("no-wait");
 
-            project.up(should_wait).await
+            project
+                .up(project::UpOpts {
+                    should_wait,
+                    auto_rebuild: sub_matches.is_present("auto-rebuild"),
+                    tui: sub_matches.is_present("tui"),
+                    container_id: sub_matches.value_of("container-id").map(|s| s.to_string()),
+                    on_exit: match sub_matches.value_of("on-exit") {
+                        Some("restart") => Some(settings::ExitPolicy::Restart),
+                        Some("prompt") => Some(settings::ExitPolicy::Prompt),
+                        Some("exit") => Some(settings::ExitPolicy::Exit),
+                        _ => None,
+                    },
+                })
+                .await
+        }
+        ("down", Some(sub_matches)) => {
+            project
+                .down(
+                    None,
+                    false,
+                    &project::DownOpts {
+                        remove: sub_matches.is_present("remove"),
+                        volumes: sub_matches.is_present("volumes"),
+                        rmi: sub_matches.is_present("rmi"),
+                        remove_orphans: sub_matches.is_present("remove-orphans"),
+                        snapshot: sub_matches.is_present("snapshot"),
+                    },
+                )
+                .await
+        }
+        ("exec", Some(sub_matches)) => {
+            let service = sub_matches.value_of("service").map(|s| s.to_string());
+            let args = sub_matches
+                .values_of("cmd")
+                .unwrap()
+                .map(|s| s.to_string())
+                .collect();
+
+            match project.exec(service, args).await {
+                Ok(exit_code) => std::process::exit(exit_code as i32),
+                Err(err) => Err(err),
+            }
+        }
+        ("run-user-commands", Some(sub_matches)) => {
+            let all = [
+                ("post-create", project::CommandHook::PostCreate),
+                ("post-start", project::CommandHook::PostStart),
+                ("post-attach", project::CommandHook::PostAttach),
+            ];
+            let selected: Vec<project::CommandHook> = all
+                .iter()
+                .filter(|(flag, _)| sub_matches.is_present(flag))
+                .map(|(_, hook)| *hook)
+                .collect();
+
+            let hooks = if selected.is_empty() {
+                all.iter().map(|(_, hook)| *hook).collect()
+            } else {
+                selected
+            };
+
+            project.run_user_commands(hooks).await
+        }
+        ("shell", Some(sub_matches)) => {
+            let service = sub_matches.value_of("service").map(|s| s.to_string());
+            let shell = sub_matches.value_of("shell").map(|s| s.to_string());
+
+            match project.shell(service, shell).await {
+                Ok(exit_code) => std::process::exit(exit_code),
+                Err(err) => Err(err),
+            }
+        }
+        ("build", Some(sub_matches)) => project.build(sub_matches.is_present("no-cache")).await,
+        ("read-configuration", Some(sub_matches)) => {
+            project
+                .read_configuration(sub_matches.value_of("customizations"))
+                .await
+        }
+        ("forward", Some(sub_matches)) => {
+            project
+                .forward(
+                    sub_matches.value_of("service").map(|s| s.to_string()),
+                    sub_matches.value_of("port").unwrap(),
+                )
+                .await
+        }
+        ("export", Some(sub_matches)) => {
+            let format = match sub_matches.value_of("format") {
+                Some("compose") => project::ExportFormat::Compose,
+                _ => project::ExportFormat::Run,
+            };
+
+            project
+                .export(
+                    format,
+                    sub_matches.value_of("output").map(|s| s.to_string()),
+                )
+                .await
+        }
+        ("pause", Some(_)) => project.pause(true).await,
+        ("unpause", Some(_)) => project.pause(false).await,
+        ("status", Some(_)) => project.status().await,
+        ("logs", Some(sub_matches)) => {
+            project
+                .logs(
+                    sub_matches
+                        .values_of("service")
+                        .map(|values| values.map(|s| s.to_string()).collect())
+                        .unwrap_or_default(),
+                    sub_matches.is_present("follow"),
+                    sub_matches.value_of("since").map(|s| s.to_string()),
+                    sub_matches.value_of("tail").map(|s| s.to_string()),
+                )
+                .await
+        }
+        ("features", Some(sub_matches)) => match sub_matches.subcommand() {
+            ("info", Some(feature_matches)) => {
+                project
+                    .features_info(feature_matches.value_of("feature").unwrap())
+                    .await
+            }
+            ("add", Some(feature_matches)) => {
+                let options = feature_matches
+                    .values_of("option")
+                    .map(|values| values.map(|v| v.to_string()).collect())
+                    .unwrap_or_default();
+
+                project
+                    .features_add(feature_matches.value_of("feature").unwrap(), options)
+                    .await
+            }
+            _ => project.features_list().await,
+        },
+        ("outdated", Some(sub_matches)) => project.outdated(sub_matches.is_present("pull")).await,
+        ("sync", Some(sub_matches)) => {
+            let direction = match sub_matches.value_of("direction") {
+                Some("pull") => project::SyncDirection::Pull,
+                _ => project::SyncDirection::Push,
+            };
+
+            project.sync(direction).await
+        }
+        ("upgrade-config", Some(sub_matches)) => {
+            project
+                .upgrade_config(sub_matches.is_present("dry-run"))
+                .await
         }
-        ("down", Some(_)) => project.down(None, false).await,
         _ => Ok(()),
     };
 
@@ -29,7 +29,7 @@ impl MountExt for Mount {
     fn from_comma_string(s: &str) -> Result<Self, Error> {
         let parts: Vec<&str> = s.split(",").collect();
 
-        if parts.len() == 0 {
+        if parts.is_empty() {
             return Err(Error::InvalidConfig("Invalid mount point".to_string()));
         }
 
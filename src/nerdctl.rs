@@ -0,0 +1,210 @@
+use bollard::container::Config;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use super::errors::*;
+
+// A line of `nerdctl ps --format '{{json .}}'`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PsEntry {
+    #[serde(rename = "ID")]
+    pub id: String,
+    // "Up", "Created" or "Exited (0) 2 minutes ago"
+    #[serde(rename = "Status", default)]
+    pub status: String,
+    // "key=value,key=value"
+    #[serde(rename = "Labels", default)]
+    pub labels: String,
+}
+
+impl PsEntry {
+    pub fn is_running(&self) -> bool {
+        self.status.starts_with("Up")
+    }
+
+    pub fn labels(&self) -> HashMap<String, String> {
+        self.labels
+            .split(',')
+            .filter_map(|label| label.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+// What `nerdctl inspect` tells of a container, in its docker compatible output
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Inspect {
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "Config", default)]
+    pub config: Option<InspectConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct InspectConfig {
+    #[serde(rename = "Env", default)]
+    pub env: Option<Vec<String>>,
+}
+
+impl Inspect {
+    pub fn env(&self) -> HashMap<String, String> {
+        self.config
+            .as_ref()
+            .and_then(|config| config.env.as_ref())
+            .into_iter()
+            .flatten()
+            .filter_map(|env| env.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+pub fn parse_inspect(output: &str) -> Result<Inspect, Error> {
+    let inspect: Vec<Inspect> = serde_json::from_str(output)
+        .map_err(|err| Error::Other(format!("Unexpected nerdctl inspect output: {}", err)))?;
+
+    inspect
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::Other("No container found for this project".to_string()))
+}
+
+pub fn parse_ps(output: &str) -> Result<Vec<PsEntry>, Error> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|err| Error::Other(format!("Unexpected nerdctl ps output: {}", err)))
+        })
+        .collect()
+}
+
+// The `nerdctl run` arguments creating the container the docker API would from `config`. The
+// image and the command go last
+pub fn run_args(config: &Config<String>) -> Vec<String> {
+    let mut args = vec![];
+    let flag = |args: &mut Vec<String>, name: &str, value: String| {
+        args.push(name.to_string());
+        args.push(value);
+    };
+
+    // sorted, so the same config gives the same command line
+    let labels: BTreeMap<_, _> = config.labels.iter().flatten().collect();
+    for (key, value) in labels {
+        flag(&mut args, "--label", format!("{}={}", key, value));
+    }
+    for env in config.env.iter().flatten() {
+        flag(&mut args, "--env", env.clone());
+    }
+    if let Some(user) = config.user.as_ref() {
+        flag(&mut args, "--user", user.clone());
+    }
+    if let Some(dir) = config.working_dir.as_ref() {
+        flag(&mut args, "--workdir", dir.clone());
+    }
+    if let Some(hostname) = config.hostname.as_ref() {
+        flag(&mut args, "--hostname", hostname.clone());
+    }
+
+    if let Some(host_config) = config.host_config.as_ref() {
+        for mount in host_config.mounts.iter().flatten() {
+            let mut spec = vec![format!(
+                "type={}",
+                mount.typ.map(|t| t.to_string()).unwrap_or_default()
+            )];
+            if let Some(source) = mount.source.as_ref() {
+                spec.push(format!("source={}", source));
+            }
+            if let Some(target) = mount.target.as_ref() {
+                spec.push(format!("target={}", target));
+            }
+            if mount.read_only == Some(true) {
+                spec.push("readonly".to_string());
+            }
+            flag(&mut args, "--mount", spec.join(","));
+        }
+
+        let ports: BTreeMap<_, _> = host_config.port_bindings.iter().flatten().collect();
+        for (container_port, bindings) in ports {
+            for binding in bindings.iter().flatten() {
+                let host_port = binding.host_port.clone().unwrap_or_default();
+                let publish = match binding.host_ip.as_deref() {
+                    Some(ip) if ip.contains(':') => format!("[{}]:{}", ip, host_port),
+                    Some(ip) if !ip.is_empty() => format!("{}:{}", ip, host_port),
+                    _ => host_port,
+                };
+                flag(
+                    &mut args,
+                    "--publish",
+                    format!("{}:{}", publish, container_port),
+                );
+            }
+        }
+
+        for cap in host_config.cap_add.iter().flatten() {
+            flag(&mut args, "--cap-add", cap.clone());
+        }
+        for opt in host_config.security_opt.iter().flatten() {
+            flag(&mut args, "--security-opt", opt.clone());
+        }
+        for host in host_config.extra_hosts.iter().flatten() {
+            flag(&mut args, "--add-host", host.clone());
+        }
+        if let Some(network) = host_config.network_mode.as_ref() {
+            flag(&mut args, "--network", network.clone());
+        }
+        if let Some(policy) = host_config.restart_policy.as_ref() {
+            if let Some(name) = policy.name {
+                let name = name.to_string();
+                match policy.maximum_retry_count {
+                    Some(count) if count > 0 => {
+                        flag(&mut args, "--restart", format!("{}:{}", name, count))
+                    }
+                    _ if !name.is_empty() => flag(&mut args, "--restart", name),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(memory) = host_config.memory {
+            flag(&mut args, "--memory", memory.to_string());
+        }
+        if let (Some(period), Some(quota)) = (host_config.cpu_period, host_config.cpu_quota) {
+            flag(
+                &mut args,
+                "--cpus",
+                format!("{}", quota as f64 / period as f64),
+            );
+        }
+        if let Some(shm_size) = host_config.shm_size {
+            flag(&mut args, "--shm-size", shm_size.to_string());
+        }
+        for ulimit in host_config.ulimits.iter().flatten() {
+            if let (Some(name), Some(soft), Some(hard)) = (&ulimit.name, ulimit.soft, ulimit.hard) {
+                flag(&mut args, "--ulimit", format!("{}={}:{}", name, soft, hard));
+            }
+        }
+        if host_config
+            .device_requests
+            .as_ref()
+            .map(|requests| !requests.is_empty())
+            .unwrap_or(false)
+        {
+            flag(&mut args, "--gpus", "all".to_string());
+        }
+
+        if host_config.privileged == Some(true) {
+            args.push("--privileged".to_string());
+        }
+        if host_config.init == Some(true) {
+            args.push("--init".to_string());
+        }
+    }
+
+    if let Some(image) = config.image.as_ref() {
+        args.push(image.clone());
+    }
+    args.extend(config.cmd.iter().flatten().cloned());
+
+    args
+}
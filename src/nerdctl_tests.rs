@@ -0,0 +1,96 @@
+use bollard::container::Config;
+use bollard::service::{HostConfig, Mount, MountTypeEnum, PortBinding};
+use std::collections::HashMap;
+
+use super::nerdctl::*;
+
+#[test]
+fn test_parse_ps() {
+    let output = r#"{"Command":"\"sleep infinity\"","ID":"4f3a","Image":"ubuntu:22.04","Labels":"devcontainer=true,devcontainer_name=project","Names":"ubuntu-4f3a","Status":"Up"}
+{"ID":"9b2c","Image":"alpine:3","Labels":"","Status":"Exited (0) 2 minutes ago"}
+"#;
+
+    let entries = parse_ps(output).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].id, "4f3a");
+    assert!(entries[0].is_running());
+    assert_eq!(
+        entries[0].labels().get("devcontainer_name"),
+        Some(&"project".to_string())
+    );
+
+    assert!(!entries[1].is_running());
+    assert!(entries[1].labels().is_empty());
+
+    assert!(parse_ps("").unwrap().is_empty());
+    assert!(parse_ps("not json").is_err());
+}
+
+#[test]
+fn test_parse_inspect() {
+    let output =
+        r#"[{"Id":"4f3a","Name":"ubuntu-4f3a","Config":{"Env":["PATH=/usr/bin","LANG=C.UTF-8"]}}]"#;
+
+    let inspect = parse_inspect(output).unwrap();
+    assert_eq!(inspect.name, "ubuntu-4f3a");
+    assert_eq!(inspect.env().get("LANG"), Some(&"C.UTF-8".to_string()));
+
+    assert!(parse_inspect("[]").is_err());
+}
+
+#[test]
+fn test_run_args() {
+    let mut labels = HashMap::new();
+    labels.insert("devcontainer".to_string(), "true".to_string());
+
+    let mut port_bindings = HashMap::new();
+    port_bindings.insert(
+        "8080/tcp".to_string(),
+        Some(vec![PortBinding {
+            host_ip: Some("127.0.0.1".to_string()),
+            host_port: Some("8080".to_string()),
+        }]),
+    );
+
+    let config = Config {
+        image: Some("ubuntu:22.04".to_string()),
+        env: Some(vec!["LANG=C.UTF-8".to_string()]),
+        labels: Some(labels),
+        cmd: Some(vec!["sleep".to_string(), "infinity".to_string()]),
+        host_config: Some(HostConfig {
+            mounts: Some(vec![Mount {
+                typ: Some(MountTypeEnum::BIND),
+                source: Some("/home/me/project".to_string()),
+                target: Some("/workspaces/project".to_string()),
+                read_only: Some(true),
+                ..Default::default()
+            }]),
+            port_bindings: Some(port_bindings),
+            cap_add: Some(vec!["SYS_PTRACE".to_string()]),
+            init: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        run_args(&config),
+        vec![
+            "--label",
+            "devcontainer=true",
+            "--env",
+            "LANG=C.UTF-8",
+            "--mount",
+            "type=bind,source=/home/me/project,target=/workspaces/project,readonly",
+            "--publish",
+            "127.0.0.1:8080:8080/tcp",
+            "--cap-add",
+            "SYS_PTRACE",
+            "--init",
+            "ubuntu:22.04",
+            "sleep",
+            "infinity",
+        ]
+    );
+}
@@ -0,0 +1,56 @@
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum PromptMode {
+    #[default]
+    Interactive,
+    // --yes: accept every question
+    AssumeYes,
+    // --no-input: never ask, go with the default answer
+    NoInput,
+}
+
+pub struct Prompt {
+    pub mode: PromptMode,
+}
+
+impl Prompt {
+    pub fn new(mode: PromptMode) -> Self {
+        Prompt { mode }
+    }
+
+    pub fn parse_answer(answer: &str, default: bool) -> bool {
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => default,
+        }
+    }
+
+    pub async fn confirm(&self, question: &str, default: bool) -> bool {
+        match self.mode {
+            PromptMode::AssumeYes => {
+                info!("{} yes (--yes)", question);
+                true
+            }
+            PromptMode::NoInput => {
+                info!(
+                    "{} {} (--no-input)",
+                    question,
+                    if default { "yes" } else { "no" }
+                );
+                default
+            }
+            PromptMode::Interactive => {
+                println!("{} {}", question, if default { "[Y/n]" } else { "[y/N]" });
+
+                let mut answer = String::new();
+                let mut stdin = BufReader::new(tokio::io::stdin());
+                match stdin.read_line(&mut answer).await {
+                    Ok(_) => Self::parse_answer(&answer, default),
+                    Err(_) => default,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use super::prompt::*;
+
+#[test]
+fn test_parse_answer() {
+    assert!(Prompt::parse_answer("y\n", false));
+    assert!(Prompt::parse_answer("YES", false));
+    assert!(!Prompt::parse_answer("n", true));
+    assert!(!Prompt::parse_answer("No\n", true));
+    assert!(Prompt::parse_answer("\n", true));
+    assert!(!Prompt::parse_answer("", false));
+    assert!(!Prompt::parse_answer("maybe", false));
+}
+
+#[tokio::test]
+async fn test_non_interactive() {
+    let prompt = Prompt::new(PromptMode::AssumeYes);
+    assert!(prompt.confirm("Continue?", false).await);
+
+    let prompt = Prompt::new(PromptMode::NoInput);
+    assert!(!prompt.confirm("Continue?", false).await);
+    assert!(prompt.confirm("Continue?", true).await);
+}
@@ -0,0 +1,390 @@
+use bollard::auth::DockerCredentials;
+use flate2::read::GzDecoder;
+use reqwest::{header, Client, Response, StatusCode};
+use std::collections::HashMap;
+use std::io::Read;
+
+use super::errors::*;
+use super::lock;
+
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+
+pub const OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.index.v1+json, application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImageRef {
+    pub registry: String,
+    pub repository: String,
+    pub tag: String,
+    pub digest: Option<String>,
+}
+
+impl ImageRef {
+    // ubuntu, user/repo:tag, mcr.microsoft.com/devcontainers/rust:1, ghcr.io/org/img@sha256:...
+    pub fn parse(image: &str) -> Self {
+        let (name, digest) = match image.find('@') {
+            Some(idx) => (&image[..idx], Some(image[idx + 1..].to_string())),
+            None => (image, None),
+        };
+
+        let (name, tag) = match name.rfind(':') {
+            Some(idx) if !name[idx..].contains('/') => (&name[..idx], name[idx + 1..].to_string()),
+            _ => (name, "latest".to_string()),
+        };
+
+        let mut parts: Vec<&str> = name.splitn(2, '/').collect();
+        let registry = if parts.len() == 2
+            && (parts[0].contains('.') || parts[0].contains(':') || parts[0] == "localhost")
+        {
+            parts.remove(0).to_string()
+        } else {
+            DOCKER_HUB_REGISTRY.to_string()
+        };
+
+        let mut repository = parts.join("/");
+        if registry == DOCKER_HUB_REGISTRY && !repository.contains('/') {
+            repository = format!("library/{}", repository);
+        }
+
+        ImageRef {
+            registry,
+            repository,
+            tag,
+            digest,
+        }
+    }
+
+    // The digest if pinned, the tag otherwise
+    pub fn reference(&self) -> &str {
+        self.digest.as_deref().unwrap_or(&self.tag)
+    }
+}
+
+// Artifact layers (features, templates) are plain tarballs, but gzipped ones show up too
+pub fn layer_archive(data: &[u8]) -> Result<tar::Archive<Box<dyn Read + '_>>, Error> {
+    let reader: Box<dyn Read + '_> = if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(GzDecoder::new(data).map_err(|err| Error::Other(err.to_string()))?)
+    } else {
+        Box::new(data)
+    };
+
+    Ok(tar::Archive::new(reader))
+}
+
+// A blob must hash to the digest it was fetched by. Only sha256 digests are checked
+pub fn verify_digest(data: &[u8], digest: &str) -> Result<(), Error> {
+    if !digest.starts_with("sha256:") {
+        return Ok(());
+    }
+
+    let actual = lock::integrity(data);
+    if actual != digest {
+        return Err(Error::RegistryError(format!(
+            "Blob digest mismatch: expected {}, got {}",
+            digest, actual
+        )));
+    }
+
+    Ok(())
+}
+
+// Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/ubuntu:pull"
+pub fn parse_www_authenticate(value: &str) -> Option<(String, HashMap<String, String>)> {
+    let params = parse_auth_params(value.strip_prefix("Bearer ")?)?;
+
+    let mut others = HashMap::new();
+    let mut realm = None;
+    for (key, value) in params {
+        if key == "realm" {
+            realm = Some(value);
+        } else {
+            others.insert(key, value);
+        }
+    }
+
+    Some((realm?, others))
+}
+
+// `key=value, key="quoted, \"value\""` pairs of a challenge. Commas inside quotes are part of
+// the value, `scope="repository:foo:pull,push"`
+fn parse_auth_params(params: &str) -> Option<Vec<(String, String)>> {
+    let mut pairs = vec![];
+    let mut chars = params.chars().peekable();
+
+    loop {
+        while matches!(chars.peek(), Some(c) if *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Some(pairs);
+        }
+
+        let mut key = String::new();
+        loop {
+            match chars.next()? {
+                '=' => break,
+                c => key.push(c),
+            }
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.peek() {
+                if *c == ',' {
+                    break;
+                }
+                value.push(*c);
+                chars.next();
+            }
+        }
+
+        pairs.push((key.trim().to_string(), value.trim().to_string()));
+    }
+}
+
+pub struct RegistryClient {
+    client: Client,
+    // of the registry of the requests, anonymous without them
+    credentials: Option<DockerCredentials>,
+}
+
+impl RegistryClient {
+    pub fn new() -> Self {
+        RegistryClient {
+            client: Client::new(),
+            credentials: None,
+        }
+    }
+
+    pub fn with_credentials(mut self, credentials: Option<DockerCredentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    fn basic_auth(&self) -> Option<(&str, &str)> {
+        let credentials = self.credentials.as_ref()?;
+        Some((
+            credentials.username.as_deref()?,
+            credentials.password.as_deref()?,
+        ))
+    }
+
+    async fn get_token(&self, www_authenticate: &str) -> Result<String, Error> {
+        let (realm, mut params) = parse_www_authenticate(www_authenticate).ok_or_else(|| {
+            Error::RegistryError(format!(
+                "Unsupported authentication challenge: {}",
+                www_authenticate
+            ))
+        })?;
+
+        let identity_token = self
+            .credentials
+            .as_ref()
+            .and_then(|c| c.identitytoken.as_ref());
+        let request = match (identity_token, self.basic_auth()) {
+            // the OAuth2 exchange of `docker login` identity tokens
+            (Some(token), _) => {
+                params.insert("grant_type".to_string(), "refresh_token".to_string());
+                params.insert("refresh_token".to_string(), token.clone());
+                params.insert("client_id".to_string(), "devcontainers-rs".to_string());
+                self.client.post(realm.as_str()).form(&params)
+            }
+            (None, Some((username, password))) => self
+                .client
+                .get(realm.as_str())
+                .query(&params)
+                .basic_auth(username, Some(password)),
+            (None, None) => self.client.get(realm.as_str()).query(&params),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::RegistryError(format!(
+                "{} returned {}",
+                realm,
+                response.status()
+            )));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+
+        body.get("token")
+            .or_else(|| body.get("access_token"))
+            .and_then(|t| t.as_str())
+            .map(|t| t.to_string())
+            .ok_or_else(|| Error::RegistryError("No token returned by the registry".to_string()))
+    }
+
+    // Anonymous requests first, retried with a token when the registry asks for one
+    pub async fn request(
+        &self,
+        method: reqwest::Method,
+        image: &ImageRef,
+        path: &str,
+        accept: &str,
+    ) -> Result<Response, Error> {
+        let url = format!(
+            "https://{}/v2/{}/{}",
+            image.registry, image.repository, path
+        );
+
+        let response = self
+            .client
+            .request(method.clone(), url.as_str())
+            .header(header::ACCEPT, accept)
+            .send()
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+
+        let response = if response.status() == StatusCode::UNAUTHORIZED {
+            let challenge = response
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .and_then(|h| h.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            let retry = self
+                .client
+                .request(method, url.as_str())
+                .header(header::ACCEPT, accept);
+
+            // registries without a token service take the credentials as they are
+            let retry = match self.basic_auth() {
+                Some((username, password)) if challenge.starts_with("Basic") => {
+                    retry.basic_auth(username, Some(password))
+                }
+                _ => retry.bearer_auth(self.get_token(challenge.as_str()).await?),
+            };
+
+            retry
+                .send()
+                .await
+                .map_err(|err| Error::RegistryError(err.to_string()))?
+        } else {
+            response
+        };
+
+        if !response.status().is_success() {
+            return Err(Error::RegistryError(format!(
+                "{} returned {}",
+                url,
+                response.status()
+            )));
+        }
+
+        Ok(response)
+    }
+
+    pub async fn get_manifest_digest(&self, image: &ImageRef) -> Result<String, Error> {
+        let response = self
+            .request(
+                reqwest::Method::HEAD,
+                image,
+                format!("manifests/{}", image.reference()).as_str(),
+                MANIFEST_ACCEPT,
+            )
+            .await?;
+
+        response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.to_string())
+            .ok_or_else(|| {
+                Error::RegistryError("Registry did not return a manifest digest".to_string())
+            })
+    }
+
+    pub async fn get_manifest(
+        &self,
+        image: &ImageRef,
+        accept: &str,
+    ) -> Result<serde_json::Value, Error> {
+        let response = self
+            .request(
+                reqwest::Method::GET,
+                image,
+                format!("manifests/{}", image.reference()).as_str(),
+                accept,
+            )
+            .await?;
+
+        response
+            .json()
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))
+    }
+
+    pub async fn list_tags(&self, image: &ImageRef) -> Result<Vec<String>, Error> {
+        let response = self
+            .request(reqwest::Method::GET, image, "tags/list", "application/json")
+            .await?;
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+
+        Ok(body
+            .get("tags")
+            .and_then(|tags| tags.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| tag.as_str())
+            .map(|tag| tag.to_string())
+            .collect())
+    }
+
+    pub async fn get_blob(&self, image: &ImageRef, digest: &str) -> Result<Vec<u8>, Error> {
+        let response = self
+            .request(
+                reqwest::Method::GET,
+                image,
+                format!("blobs/{}", digest).as_str(),
+                "*/*",
+            )
+            .await?;
+
+        let data = response
+            .bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+        verify_digest(&data, digest)?;
+
+        Ok(data)
+    }
+
+    // First layer of an artifact manifest, where features and templates keep their files
+    pub async fn get_first_layer(
+        &self,
+        image: &ImageRef,
+        manifest: &serde_json::Value,
+    ) -> Result<Vec<u8>, Error> {
+        let digest = manifest
+            .get("layers")
+            .and_then(|l| l.get(0))
+            .and_then(|l| l.get("digest"))
+            .and_then(|d| d.as_str())
+            .ok_or_else(|| Error::RegistryError(format!("{} has no layers", image.repository)))?;
+
+        self.get_blob(image, digest).await
+    }
+}
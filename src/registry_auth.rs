@@ -0,0 +1,203 @@
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::errors::*;
+
+// How the docker cli names Docker Hub in config.json
+const DOCKER_HUB_KEY: &str = "https://index.docker.io/v1/";
+
+// Credential helpers answer with this username when the secret is an identity token
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+// `registryAuth` entries of the user settings, by registry host
+#[derive(Deserialize, Clone, Debug)]
+pub struct RegistryCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DockerConfigAuths {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+
+    #[serde(rename = "credsStore")]
+    creds_store: Option<String>,
+
+    #[serde(rename = "credHelpers", default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+struct AuthEntry {
+    auth: Option<String>,
+    identitytoken: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HelperOutput {
+    #[serde(rename = "Username")]
+    username: String,
+
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+impl DockerConfigAuths {
+    pub fn load(config_dir: &Path) -> Self {
+        std::fs::read_to_string(config_dir.join("config.json"))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    // Credentials stored in the file itself, the ones written by `docker login` without a store
+    fn stored(&self, registry: &str) -> Option<DockerCredentials> {
+        let entry = self
+            .auths
+            .iter()
+            .find(|(key, _)| normalize_key(key) == normalize_key(registry))
+            .map(|(_, entry)| entry)?;
+
+        if let Some(token) = entry.identitytoken.as_ref() {
+            return Some(DockerCredentials {
+                identitytoken: Some(token.clone()),
+                serveraddress: Some(registry.to_string()),
+                ..Default::default()
+            });
+        }
+
+        let (username, password) = decode_auth(entry.auth.as_ref()?)?;
+        Some(DockerCredentials {
+            username: Some(username),
+            password: Some(password),
+            serveraddress: Some(registry.to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn helper(&self, registry: &str) -> Option<&String> {
+        self.cred_helpers
+            .iter()
+            .find(|(key, _)| normalize_key(key) == normalize_key(registry))
+            .map(|(_, helper)| helper)
+            .or(self.creds_store.as_ref())
+    }
+}
+
+// The key of a registry in config.json. Docker Hub has its own
+pub fn server_key(registry: &str) -> String {
+    match registry {
+        "docker.io" | "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB_KEY.to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Keys are written with and without scheme and path: https://ghcr.io/v1/ == ghcr.io
+fn normalize_key(key: &str) -> String {
+    let key = server_key(key);
+    let key = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+
+    key.split('/').next().unwrap_or(key).to_string()
+}
+
+// base64 of user:password
+pub fn decode_auth(auth: &str) -> Option<(String, String)> {
+    let decoded = base64::decode(auth.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+
+    Some((username.to_string(), password.to_string()))
+}
+
+// Runs docker-credential-<helper> like the docker cli does. None when it has nothing stored
+async fn from_helper(helper: &str, registry: &str) -> Result<Option<DockerCredentials>, Error> {
+    let program = format!("docker-credential-{}", helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| Error::RegistryError(format!("Failed to run {}: {}", program, err)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(registry.as_bytes())
+            .await
+            .map_err(|err| Error::RegistryError(err.to_string()))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| Error::RegistryError(err.to_string()))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let output: HelperOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|err| Error::RegistryError(format!("Invalid {} output: {}", program, err)))?;
+
+    Ok(Some(helper_credentials(
+        output.username,
+        output.secret,
+        registry,
+    )))
+}
+
+fn helper_credentials(username: String, secret: String, registry: &str) -> DockerCredentials {
+    if username == IDENTITY_TOKEN_USERNAME {
+        return DockerCredentials {
+            identitytoken: Some(secret),
+            serveraddress: Some(registry.to_string()),
+            ..Default::default()
+        };
+    }
+
+    DockerCredentials {
+        username: Some(username),
+        password: Some(secret),
+        serveraddress: Some(registry.to_string()),
+        ..Default::default()
+    }
+}
+
+// Credentials to pull from `registry`: the user settings, then the credential helper of the
+// registry (or the default credential store), then the ones stored in config.json
+pub async fn resolve(
+    registry: &str,
+    config: &DockerConfigAuths,
+    overrides: Option<&HashMap<String, RegistryCredentials>>,
+) -> Result<Option<DockerCredentials>, Error> {
+    let key = server_key(registry);
+
+    if let Some(creds) = overrides.and_then(|overrides| {
+        overrides
+            .iter()
+            .find(|(host, _)| normalize_key(host) == normalize_key(&key))
+            .map(|(_, creds)| creds)
+    }) {
+        return Ok(Some(DockerCredentials {
+            username: Some(creds.username.clone()),
+            password: Some(creds.password.clone()),
+            serveraddress: Some(key),
+            ..Default::default()
+        }));
+    }
+
+    if let Some(helper) = config.helper(&key) {
+        if let Some(creds) = from_helper(helper, &key).await? {
+            return Ok(Some(creds));
+        }
+    }
+
+    Ok(config.stored(&key))
+}
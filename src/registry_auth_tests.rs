@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::registry::ImageRef;
+use super::registry_auth::*;
+
+fn config_dir() -> PathBuf {
+    let mut dir = std::env::current_dir().unwrap();
+    dir.push("test_files");
+    dir.push("registry-auth");
+
+    dir
+}
+
+#[test]
+fn test_server_key() {
+    let hub = ImageRef::parse("ubuntu").registry;
+    assert_eq!(server_key(&hub), "https://index.docker.io/v1/");
+    assert_eq!(server_key("docker.io"), "https://index.docker.io/v1/");
+    assert_eq!(server_key("ghcr.io"), "ghcr.io");
+}
+
+#[test]
+fn test_decode_auth() {
+    assert_eq!(
+        decode_auth("dXNlcjpwYTpzcw=="),
+        Some(("user".to_string(), "pa:ss".to_string()))
+    );
+    assert_eq!(decode_auth("not base64!"), None);
+    assert_eq!(decode_auth("bm9jb2xvbg=="), None);
+}
+
+#[tokio::test]
+async fn test_resolve_stored() {
+    let config = DockerConfigAuths::load(&config_dir());
+
+    let hub = resolve("registry-1.docker.io", &config, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(hub.username.as_deref(), Some("hubuser"));
+    assert_eq!(hub.password.as_deref(), Some("hubpass"));
+    assert_eq!(
+        hub.serveraddress.as_deref(),
+        Some("https://index.docker.io/v1/")
+    );
+
+    let ghcr = resolve("ghcr.io", &config, None).await.unwrap().unwrap();
+    assert_eq!(ghcr.username.as_deref(), Some("octocat"));
+
+    let token = resolve("registry.example.com", &config, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.identitytoken.as_deref(), Some("refresh-token"));
+    assert_eq!(token.username, None);
+
+    assert!(resolve("quay.io", &config, None).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_resolve_override() {
+    let config = DockerConfigAuths::load(&config_dir());
+    let mut overrides = HashMap::new();
+    overrides.insert(
+        "ghcr.io".to_string(),
+        RegistryCredentials {
+            username: "bot".to_string(),
+            password: "secret".to_string(),
+        },
+    );
+
+    let ghcr = resolve("ghcr.io", &config, Some(&overrides))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ghcr.username.as_deref(), Some("bot"));
+    assert_eq!(ghcr.password.as_deref(), Some("secret"));
+}
+
+#[tokio::test]
+async fn test_resolve_missing_helper() {
+    let config = DockerConfigAuths::load(&config_dir());
+
+    assert!(resolve("private.example.com", &config, None).await.is_err());
+}
+
+#[test]
+fn test_load_missing_config() {
+    let config = DockerConfigAuths::load(&config_dir().join("missing"));
+
+    assert!(
+        futures::executor::block_on(resolve("ghcr.io", &config, None))
+            .unwrap()
+            .is_none()
+    );
+}
@@ -0,0 +1,71 @@
+use super::registry::*;
+
+#[test]
+fn test_parse_image_ref() {
+    assert_eq!(
+        ImageRef::parse("ubuntu"),
+        ImageRef {
+            registry: "registry-1.docker.io".to_string(),
+            repository: "library/ubuntu".to_string(),
+            tag: "latest".to_string(),
+            digest: None,
+        }
+    );
+
+    let image = ImageRef::parse("user/repo:1.0");
+    assert_eq!(image.registry, "registry-1.docker.io");
+    assert_eq!(image.repository, "user/repo");
+    assert_eq!(image.tag, "1.0");
+
+    let image = ImageRef::parse("mcr.microsoft.com/devcontainers/rust:1");
+    assert_eq!(image.registry, "mcr.microsoft.com");
+    assert_eq!(image.repository, "devcontainers/rust");
+    assert_eq!(image.reference(), "1");
+
+    let image = ImageRef::parse("localhost:5000/myimage");
+    assert_eq!(image.registry, "localhost:5000");
+    assert_eq!(image.repository, "myimage");
+    assert_eq!(image.tag, "latest");
+
+    let image = ImageRef::parse("ghcr.io/org/img@sha256:abc");
+    assert_eq!(image.registry, "ghcr.io");
+    assert_eq!(image.repository, "org/img");
+    assert_eq!(image.reference(), "sha256:abc");
+}
+
+#[test]
+fn test_parse_www_authenticate() {
+    let (realm, params) = parse_www_authenticate(
+        r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/ubuntu:pull""#,
+    )
+    .unwrap();
+
+    assert_eq!(realm, "https://auth.docker.io/token");
+    assert_eq!(params.get("service").unwrap(), "registry.docker.io");
+    assert_eq!(
+        params.get("scope").unwrap(),
+        "repository:library/ubuntu:pull"
+    );
+
+    assert!(parse_www_authenticate("Basic realm=\"x\"").is_none());
+
+    let (realm, params) = parse_www_authenticate(
+        r#"Bearer realm="https://ghcr.io/token", service=ghcr.io, scope="repository:org/app:pull,push", error="say \"hi\"""#,
+    )
+    .unwrap();
+    assert_eq!(realm, "https://ghcr.io/token");
+    assert_eq!(params.get("service").unwrap(), "ghcr.io");
+    assert_eq!(params.get("scope").unwrap(), "repository:org/app:pull,push");
+    assert_eq!(params.get("error").unwrap(), "say \"hi\"");
+
+    assert!(parse_www_authenticate(r#"Bearer realm="unterminated"#).is_none());
+}
+
+#[test]
+fn test_verify_digest() {
+    // sha256 of "hello"
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    assert!(verify_digest(b"hello", digest).is_ok());
+    assert!(verify_digest(b"tampered", digest).is_err());
+    assert!(verify_digest(b"hello", "sha512:abc").is_ok());
+}
@@ -0,0 +1,129 @@
+use bollard::service::{HostConfig, ResourcesUlimits};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use super::errors::*;
+use super::host_requirements::parse_size;
+use super::run_args;
+
+// The period docker uses for --cpus
+const CPU_PERIOD: i64 = 100_000;
+
+// "4096" or "1024:4096", like `--ulimit nofile=1024:4096`
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Ulimit {
+    Limit(i64),
+    Range(String),
+}
+
+impl Ulimit {
+    pub fn soft_hard(&self) -> Result<(i64, i64), Error> {
+        let invalid = || Error::InvalidConfig(format!("Invalid ulimit: '{:?}'", self));
+
+        match self {
+            Ulimit::Limit(limit) => Ok((*limit, *limit)),
+            Ulimit::Range(range) => {
+                let (soft, hard) = match range.split_once(':') {
+                    Some((soft, hard)) => (soft, hard),
+                    None => (range.as_str(), range.as_str()),
+                };
+
+                Ok((
+                    soft.trim().parse().map_err(|_| invalid())?,
+                    hard.trim().parse().map_err(|_| invalid())?,
+                ))
+            }
+        }
+    }
+}
+
+// Limits of the container. Sizes as in `docker run`: "8g", "512m"
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    pub memory: Option<String>,
+
+    pub cpus: Option<f64>,
+
+    #[serde(rename = "shmSize")]
+    pub shm_size: Option<String>,
+
+    pub ulimits: Option<BTreeMap<String, Ulimit>>,
+}
+
+impl ResourceLimits {
+    // --memory, --cpus, --shm-size and --ulimit of the runArgs
+    pub fn from_run_args(args: &[String]) -> Result<Self, Error> {
+        let cpus = match run_args::flag_value(args, &["--cpus"]) {
+            Some(cpus) => Some(
+                cpus.parse()
+                    .map_err(|_| Error::InvalidConfig(format!("Invalid --cpus: '{}'", cpus)))?,
+            ),
+            None => None,
+        };
+
+        let mut ulimits = BTreeMap::new();
+        for ulimit in run_args::flag_values(args, &["--ulimit"]) {
+            let (name, value) = ulimit
+                .split_once('=')
+                .ok_or_else(|| Error::InvalidConfig(format!("Invalid --ulimit: '{}'", ulimit)))?;
+            ulimits.insert(name.to_string(), Ulimit::Range(value.to_string()));
+        }
+
+        Ok(ResourceLimits {
+            memory: run_args::flag_value(args, &["--memory", "-m"]),
+            cpus,
+            shm_size: run_args::flag_value(args, &["--shm-size"]),
+            ulimits: Some(ulimits).filter(|u| !u.is_empty()),
+        })
+    }
+
+    // The limits of `other` win, ulimits are merged by name
+    pub fn merge(mut self, other: &ResourceLimits) -> Self {
+        self.memory = other.memory.clone().or(self.memory);
+        self.cpus = other.cpus.or(self.cpus);
+        self.shm_size = other.shm_size.clone().or(self.shm_size);
+
+        if let Some(other_ulimits) = other.ulimits.as_ref() {
+            let mut ulimits = self.ulimits.unwrap_or_default();
+            ulimits.extend(other_ulimits.clone());
+            self.ulimits = Some(ulimits);
+        }
+
+        self
+    }
+
+    pub fn apply(&self, host_config: &mut HostConfig) -> Result<(), Error> {
+        if let Some(memory) = self.memory.as_ref() {
+            host_config.memory = Some(parse_size(memory)? as i64);
+        }
+
+        if let Some(cpus) = self.cpus {
+            if cpus <= 0.0 {
+                return Err(Error::InvalidConfig(format!("Invalid cpus: {}", cpus)));
+            }
+            host_config.cpu_period = Some(CPU_PERIOD);
+            host_config.cpu_quota = Some((cpus * CPU_PERIOD as f64) as i64);
+        }
+
+        if let Some(shm_size) = self.shm_size.as_ref() {
+            host_config.shm_size = Some(parse_size(shm_size)? as usize);
+        }
+
+        if let Some(ulimits) = self.ulimits.as_ref() {
+            let mut host_ulimits = host_config.ulimits.clone().unwrap_or_default();
+            for (name, ulimit) in ulimits {
+                let (soft, hard) = ulimit.soft_hard()?;
+                host_ulimits.retain(|u| u.name.as_deref() != Some(name.as_str()));
+                host_ulimits.push(ResourcesUlimits {
+                    name: Some(name.clone()),
+                    soft: Some(soft),
+                    hard: Some(hard),
+                });
+            }
+            host_config.ulimits = Some(host_ulimits);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use bollard::service::{HostConfig, ResourcesUlimits};
+use std::collections::BTreeMap;
+
+use super::resources::*;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_from_run_args() {
+    let limits = ResourceLimits::from_run_args(&args(&[
+        "--memory=8g",
+        "--cpus",
+        "2.5",
+        "--shm-size",
+        "1g",
+        "--ulimit",
+        "nofile=1024:4096",
+    ]))
+    .unwrap();
+
+    let mut ulimits = BTreeMap::new();
+    ulimits.insert("nofile".to_string(), Ulimit::Range("1024:4096".to_string()));
+    assert_eq!(
+        limits,
+        ResourceLimits {
+            memory: Some("8g".to_string()),
+            cpus: Some(2.5),
+            shm_size: Some("1g".to_string()),
+            ulimits: Some(ulimits),
+        }
+    );
+
+    assert!(ResourceLimits::from_run_args(&args(&["--cpus", "many"])).is_err());
+    assert!(ResourceLimits::from_run_args(&args(&["--ulimit", "nofile"])).is_err());
+}
+
+#[test]
+fn test_apply() {
+    let limits = ResourceLimits::from_run_args(&args(&[
+        "-m",
+        "512m",
+        "--cpus=2",
+        "--shm-size=64mb",
+        "--ulimit=nproc=512",
+    ]))
+    .unwrap();
+
+    let mut host_config = HostConfig::default();
+    limits.apply(&mut host_config).unwrap();
+
+    assert_eq!(host_config.memory, Some(512 * 1024 * 1024));
+    assert_eq!(host_config.cpu_period, Some(100_000));
+    assert_eq!(host_config.cpu_quota, Some(200_000));
+    assert_eq!(host_config.shm_size, Some(64 * 1024 * 1024));
+    assert_eq!(
+        host_config.ulimits,
+        Some(vec![ResourcesUlimits {
+            name: Some("nproc".to_string()),
+            soft: Some(512),
+            hard: Some(512),
+        }])
+    );
+}
+
+#[test]
+fn test_merge_settings() {
+    let run_args = ResourceLimits::from_run_args(&args(&[
+        "--memory=8g",
+        "--cpus=4",
+        "--ulimit=nofile=1024",
+        "--ulimit=nproc=512",
+    ]))
+    .unwrap();
+    let settings: ResourceLimits =
+        serde_json::from_str(r#"{ "cpus": 2, "ulimits": { "nofile": 65536 } }"#).unwrap();
+
+    let limits = run_args.merge(&settings);
+    assert_eq!(limits.memory.as_deref(), Some("8g"));
+    assert_eq!(limits.cpus, Some(2.0));
+
+    let ulimits = limits.ulimits.unwrap();
+    assert_eq!(ulimits["nofile"], Ulimit::Limit(65536));
+    assert_eq!(ulimits["nproc"], Ulimit::Range("512".to_string()));
+}
+
+#[test]
+fn test_invalid_limits() {
+    let mut host_config = HostConfig::default();
+
+    let limits = ResourceLimits {
+        memory: Some("lots".to_string()),
+        ..Default::default()
+    };
+    assert!(limits.apply(&mut host_config).is_err());
+
+    let limits = ResourceLimits {
+        cpus: Some(0.0),
+        ..Default::default()
+    };
+    assert!(limits.apply(&mut host_config).is_err());
+}
@@ -0,0 +1,109 @@
+use bollard::errors::Error as DockerError;
+use std::time::Duration;
+
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+pub const DEFAULT_DELAY_SECS: u64 = 2;
+
+// The backoff doesn't grow past this
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+// Retrying these would fail the same way: bad credentials, or an image that doesn't exist
+const FATAL_MESSAGES: &[&str] = &[
+    "unauthorized",
+    "authentication required",
+    "access denied",
+    "denied:",
+    "forbidden",
+    "no basic auth credentials",
+    "manifest unknown",
+    "not found",
+    "invalid reference format",
+];
+
+// How the daemon reports network failures of its own requests to the registry
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "timeout",
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "broken pipe",
+    "unexpected eof",
+    "no such host",
+    "temporary failure",
+    "network is unreachable",
+    "too many requests",
+    "service unavailable",
+    "bad gateway",
+    "gateway timeout",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    // tries in total, the first one included
+    pub attempts: u32,
+    // before the first retry, doubled for every next one
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: DEFAULT_ATTEMPTS,
+            delay: Duration::from_secs(DEFAULT_DELAY_SECS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // The wait after the failed `attempt` (starting at 1), None when it was the last one
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.attempts {
+            return None;
+        }
+
+        let factor = 2u32.saturating_pow(attempt - 1);
+        Some(
+            self.delay
+                .checked_mul(factor)
+                .unwrap_or(MAX_DELAY)
+                .min(MAX_DELAY),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PullFailure {
+    Transient,
+    Fatal,
+}
+
+// From an error reported by the daemon in the pull progress
+pub fn classify_message(message: &str) -> PullFailure {
+    let message = message.to_lowercase();
+
+    if FATAL_MESSAGES.iter().any(|m| message.contains(m)) {
+        PullFailure::Fatal
+    } else if TRANSIENT_MESSAGES.iter().any(|m| message.contains(m)) {
+        PullFailure::Transient
+    } else {
+        PullFailure::Fatal
+    }
+}
+
+// From an error talking to the daemon. Losing the connection is worth a retry
+pub fn classify_error(err: &DockerError) -> PullFailure {
+    match err {
+        DockerError::IOError { .. }
+        | DockerError::HyperResponseError { .. }
+        | DockerError::RequestTimeoutError => PullFailure::Transient,
+        DockerError::DockerResponseNotFoundError { .. } => PullFailure::Fatal,
+        DockerError::DockerResponseServerError {
+            status_code,
+            message,
+        } => match status_code {
+            401 | 403 => PullFailure::Fatal,
+            _ => classify_message(message),
+        },
+        _ => PullFailure::Fatal,
+    }
+}
@@ -0,0 +1,81 @@
+use bollard::errors::Error as DockerError;
+use std::time::Duration;
+
+use super::retry::*;
+
+#[test]
+fn test_backoff() {
+    let policy = RetryPolicy {
+        attempts: 4,
+        delay: Duration::from_secs(2),
+    };
+
+    assert_eq!(policy.backoff(1), Some(Duration::from_secs(2)));
+    assert_eq!(policy.backoff(2), Some(Duration::from_secs(4)));
+    assert_eq!(policy.backoff(3), Some(Duration::from_secs(8)));
+    assert_eq!(policy.backoff(4), None);
+}
+
+#[test]
+fn test_backoff_limits() {
+    let policy = RetryPolicy {
+        attempts: 100,
+        delay: Duration::from_secs(10),
+    };
+    assert_eq!(policy.backoff(50), Some(Duration::from_secs(60)));
+
+    let no_retries = RetryPolicy {
+        attempts: 1,
+        delay: Duration::from_secs(10),
+    };
+    assert_eq!(no_retries.backoff(1), None);
+}
+
+#[test]
+fn test_classify_message() {
+    assert_eq!(
+        classify_message("Get https://registry-1.docker.io/v2/: net/http: TLS handshake timeout"),
+        PullFailure::Transient
+    );
+    assert_eq!(
+        classify_message("read tcp 10.0.0.2:4321->1.2.3.4:443: read: connection reset by peer"),
+        PullFailure::Transient
+    );
+    assert_eq!(
+        classify_message("unauthorized: authentication required"),
+        PullFailure::Fatal
+    );
+    assert_eq!(
+        classify_message("manifest for ghcr.io/org/img:v9 not found: manifest unknown"),
+        PullFailure::Fatal
+    );
+    assert_eq!(classify_message("something else"), PullFailure::Fatal);
+}
+
+#[test]
+fn test_classify_error() {
+    assert_eq!(
+        classify_error(&DockerError::RequestTimeoutError),
+        PullFailure::Transient
+    );
+    assert_eq!(
+        classify_error(&DockerError::DockerResponseServerError {
+            status_code: 500,
+            message: "Get https://ghcr.io/v2/: dial tcp: i/o timeout".to_string(),
+        }),
+        PullFailure::Transient
+    );
+    assert_eq!(
+        classify_error(&DockerError::DockerResponseServerError {
+            status_code: 500,
+            message: "Head https://ghcr.io/v2/org/img/manifests/1: denied: denied".to_string(),
+        }),
+        PullFailure::Fatal
+    );
+    assert_eq!(
+        classify_error(&DockerError::DockerResponseNotFoundError {
+            message: "pull access denied for nope".to_string(),
+        }),
+        PullFailure::Fatal
+    );
+}
@@ -0,0 +1,77 @@
+use bollard::service::{RestartPolicy, RestartPolicyNameEnum};
+
+use super::errors::*;
+
+// The values of a `docker run` flag in runArgs, for each time it is given: `--flag value`,
+// `--flag=value`, and `-fvalue` for short flags
+pub fn flag_values(args: &[String], names: &[&str]) -> Vec<String> {
+    let mut values = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        for name in names {
+            if arg == name {
+                if let Some(value) = args.next() {
+                    values.push(value.clone());
+                }
+                break;
+            }
+
+            let inline = match name.starts_with("--") {
+                true => arg.strip_prefix(&format!("{}=", name)),
+                false => arg.strip_prefix(name).filter(|v| !v.is_empty()),
+            };
+            if let Some(value) = inline {
+                values.push(value.to_string());
+                break;
+            }
+        }
+    }
+
+    values
+}
+
+// The last value of a flag, the one docker uses
+pub fn flag_value(args: &[String], names: &[&str]) -> Option<String> {
+    flag_values(args, names).pop()
+}
+
+// --add-host entries as the API takes them: "name:ip". Docker accepts "name=ip" too, which
+// reads better with ipv6 addresses
+pub fn add_hosts(args: &[String]) -> Vec<String> {
+    flag_values(args, &["--add-host"])
+        .into_iter()
+        .map(|host| normalize_host(&host))
+        .collect()
+}
+
+pub fn normalize_host(host: &str) -> String {
+    match host.split_once('=') {
+        Some((name, ip)) => format!("{}:{}", name, ip),
+        None => host.to_string(),
+    }
+}
+
+// The name of an extra host entry
+pub fn host_name(host: &str) -> &str {
+    host.split([':', '=']).next().unwrap_or(host)
+}
+
+// --restart: "no", "always", "unless-stopped", "on-failure[:max-retries]"
+pub fn parse_restart_policy(policy: &str) -> Result<RestartPolicy, Error> {
+    let invalid = || Error::InvalidConfig(format!("Invalid restart policy: '{}'", policy));
+
+    let (name, retries) = match policy.split_once(':') {
+        Some((name, retries)) => (name, Some(retries.parse::<i64>().map_err(|_| invalid())?)),
+        None => (policy, None),
+    };
+    let name: RestartPolicyNameEnum = name.parse().map_err(|_| invalid())?;
+    if retries.is_some() && name != RestartPolicyNameEnum::ON_FAILURE {
+        return Err(invalid());
+    }
+
+    Ok(RestartPolicy {
+        name: Some(name),
+        maximum_retry_count: retries,
+    })
+}
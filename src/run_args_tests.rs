@@ -0,0 +1,71 @@
+use bollard::service::RestartPolicyNameEnum;
+
+use super::run_args::*;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_flag_values() {
+    let run_args = args(&[
+        "--ulimit",
+        "nofile=1024",
+        "--ulimit=nproc=512",
+        "-m4g",
+        "--cap-add=SYS_PTRACE",
+    ]);
+
+    assert_eq!(
+        flag_values(&run_args, &["--ulimit"]),
+        vec!["nofile=1024", "nproc=512"]
+    );
+    assert_eq!(
+        flag_value(&run_args, &["--memory", "-m"]),
+        Some("4g".to_string())
+    );
+    assert_eq!(flag_value(&run_args, &["--cpus"]), None);
+}
+
+#[test]
+fn test_flag_value_last_wins() {
+    let run_args = args(&["--cpus", "2", "--cpus=4"]);
+
+    assert_eq!(flag_value(&run_args, &["--cpus"]), Some("4".to_string()));
+}
+
+#[test]
+fn test_flag_without_value() {
+    let run_args = args(&["--init", "--cpus"]);
+
+    assert_eq!(flag_value(&run_args, &["--cpus"]), None);
+    // a long flag is not a prefix of another
+    assert_eq!(flag_value(&args(&["--cpuset-cpus=0"]), &["--cpus"]), None);
+}
+
+#[test]
+fn test_add_hosts() {
+    let run_args = args(&["--add-host", "db:10.0.0.5", "--add-host=v6=::1", "--init"]);
+
+    assert_eq!(add_hosts(&run_args), vec!["db:10.0.0.5", "v6:::1"]);
+    assert_eq!(host_name("v6:::1"), "v6");
+    assert_eq!(
+        host_name("host.docker.internal=host-gateway"),
+        "host.docker.internal"
+    );
+}
+
+#[test]
+fn test_parse_restart_policy() {
+    let policy = parse_restart_policy("unless-stopped").unwrap();
+    assert_eq!(policy.name, Some(RestartPolicyNameEnum::UNLESS_STOPPED));
+    assert_eq!(policy.maximum_retry_count, None);
+
+    let policy = parse_restart_policy("on-failure:5").unwrap();
+    assert_eq!(policy.name, Some(RestartPolicyNameEnum::ON_FAILURE));
+    assert_eq!(policy.maximum_retry_count, Some(5));
+
+    assert!(parse_restart_policy("sometimes").is_err());
+    assert!(parse_restart_policy("always:3").is_err());
+    assert!(parse_restart_policy("on-failure:many").is_err());
+}
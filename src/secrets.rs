@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::fs;
+
+use super::errors::*;
+
+const REDACTED: &str = "********";
+
+// Values given to the hooks, exec, shell and the application as remoteEnv. They are never part
+// of the container config and are redacted from the logs
+#[derive(Debug, Default, Clone)]
+pub struct Secrets {
+    values: BTreeMap<String, String>,
+}
+
+impl Secrets {
+    // A JSON object of strings, or a dotenv file
+    pub fn parse(contents: &str) -> Result<Self, Error> {
+        let trimmed = contents.trim_start();
+        let values = if trimmed.starts_with('{') {
+            serde_json::from_str(trimmed)
+                .map_err(|err| Error::InvalidSettings(format!("Invalid secrets file: {}", err)))?
+        } else {
+            parse_dotenv(contents)
+                .map_err(|err| Error::InvalidSettings(format!("Invalid secrets file: {}", err)))?
+        };
+
+        Ok(Secrets { values })
+    }
+
+    pub async fn load(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path).await.map_err(|err| {
+            Error::InvalidSettings(format!(
+                "Failed to read secrets file {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+
+        Self::parse(&contents)
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.values.iter()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.values
+            .values()
+            .filter(|value| !value.is_empty())
+            .fold(text.to_string(), |text, value| {
+                text.replace(value.as_str(), REDACTED)
+            })
+    }
+}
+
+// KEY=VALUE lines. Comments, empty lines, `export ` prefixes and quotes around the value are
+// allowed. Also the rules of the env_file and .env of compose projects
+pub fn parse_dotenv(contents: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut values = BTreeMap::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {} is not KEY=VALUE", number + 1))?;
+
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|quote| {
+                value
+                    .strip_prefix(*quote)
+                    .and_then(|v| v.strip_suffix(*quote))
+            })
+            .unwrap_or(value);
+
+        values.insert(key.trim().to_string(), value.to_string());
+    }
+
+    Ok(values)
+}
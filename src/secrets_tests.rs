@@ -0,0 +1,72 @@
+use super::secrets::*;
+
+#[test]
+fn test_parse_json() {
+    let secrets =
+        Secrets::parse(r#"{ "GITHUB_TOKEN": "ghp_abc123", "NPM_TOKEN": "npm_xyz" }"#).unwrap();
+    assert!(secrets.is_secret("GITHUB_TOKEN"));
+    assert_eq!(secrets.iter().count(), 2);
+
+    assert!(Secrets::parse(r#"{ "NESTED": { "a": 1 } }"#).is_err());
+}
+
+#[test]
+fn test_parse_dotenv() {
+    let secrets = Secrets::parse(
+        r#"
+# tokens
+GITHUB_TOKEN=ghp_abc123
+export NPM_TOKEN="npm xyz"
+EMPTY=
+URL='postgres://user:pass@db/app?a=b'
+"#,
+    )
+    .unwrap();
+
+    let values: Vec<(String, String)> = secrets
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            ("EMPTY".to_string(), "".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp_abc123".to_string()),
+            ("NPM_TOKEN".to_string(), "npm xyz".to_string()),
+            (
+                "URL".to_string(),
+                "postgres://user:pass@db/app?a=b".to_string()
+            ),
+        ]
+    );
+
+    assert!(Secrets::parse("NOT A PAIR").is_err());
+}
+
+#[test]
+fn test_parse_dotenv_lines() {
+    let env = parse_dotenv("# comment\n\nA=1\nexport B=\"two words\"\nC='3'\n").unwrap();
+
+    assert_eq!(
+        env.into_iter().collect::<Vec<_>>(),
+        vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "two words".to_string()),
+            ("C".to_string(), "3".to_string()),
+        ]
+    );
+    assert_eq!(
+        parse_dotenv("A=1\nHOME\n").unwrap_err(),
+        "line 2 is not KEY=VALUE"
+    );
+}
+
+#[test]
+fn test_redact() {
+    let secrets = Secrets::parse("TOKEN=ghp_abc123\nEMPTY=").unwrap();
+    assert_eq!(
+        secrets.redact("using ghp_abc123 to login"),
+        "using ******** to login"
+    );
+    assert_eq!(secrets.redact("nothing here"), "nothing here");
+}
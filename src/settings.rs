@@ -1,19 +1,66 @@
-use dirs;
-use json5;
-use serde::{de, Deserialize, Deserializer};
-use serde_yaml;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
-use std::path::PathBuf;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 use tokio::fs;
-use tokio::prelude::*;
 
-use super::devcontainer::CommandLineVec;
+use super::devcontainer::{
+    CommandLineVec, CommandShell, ForwardPort, LifecycleCommand, ShutdownAction,
+};
+use super::engine::Engine;
 use super::errors::*;
+use super::registry_auth::RegistryCredentials;
+use super::resources::ResourceLimits;
 use super::settings_compose_model::*;
+use super::wsl::PathStyle;
 
-#[derive(Deserialize)]
+// Forwarded ports are only reachable from the host unless bindAddress says otherwise
+pub const DEFAULT_BIND_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+#[derive(Deserialize, Serialize)]
 pub struct Application {
     pub cmd: CommandLineVec,
+
+    // container port the application connects to, published on the same host port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+// How the workspace gets into its volume, instead of being bind mounted: a copy of the local
+// folder, or a git clone of its origin
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceInVolume {
+    Copy,
+    Clone,
+}
+
+// What `up` does when the container dies while it waits: start it again, ask, or stop waiting
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitPolicy {
+    Restart,
+    Prompt,
+    Exit,
+}
+
+// What runs compose projects: the compose cli, or this tool itself through the docker API
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ComposeEngine {
+    Cli,
+    Native,
+}
+
+// Compose mode only: settings applied to a specific service
+#[derive(Deserialize, Default)]
+pub struct ServiceSettings {
+    pub mounts: Option<Vec<String>>,
+
+    pub envs: Option<BTreeMap<String, String>>,
+
+    #[serde(rename = "forwardPorts")]
+    pub forward_ports: Option<Vec<ForwardPort>>,
 }
 
 #[derive(Deserialize, Default)]
@@ -25,16 +72,177 @@ pub struct Settings {
     pub envs: Option<BTreeMap<String, String>>,
 
     #[serde(rename = "postCreateCommand")]
-    pub post_create_command: Option<CommandLineVec>,
+    pub post_create_command: Option<LifecycleCommand>,
 
     #[serde(rename = "postStartCommand")]
-    pub post_start_command: Option<CommandLineVec>,
+    pub post_start_command: Option<LifecycleCommand>,
 
     #[serde(rename = "postAttachCommand")]
-    pub post_attach_command: Option<CommandLineVec>,
+    pub post_attach_command: Option<LifecycleCommand>,
 
     #[serde(rename = "forwardPorts")]
-    pub forward_ports: Option<Vec<i32>>,
+    pub forward_ports: Option<Vec<ForwardPort>>,
+
+    #[serde(rename = "commandShell")]
+    pub command_shell: Option<CommandShell>,
+
+    #[serde(rename = "shutdownAction")]
+    pub shutdown_action: Option<ShutdownAction>,
+
+    #[serde(rename = "shutdownRemoveVolumes")]
+    pub shutdown_remove_volumes: Option<bool>,
+
+    // like down --rmi, when the shutdown removes the containers
+    #[serde(rename = "shutdownRemoveImages")]
+    pub shutdown_remove_images: Option<bool>,
+
+    // like down --remove-orphans, compose mode only
+    #[serde(rename = "shutdownRemoveOrphans")]
+    pub shutdown_remove_orphans: Option<bool>,
+
+    pub services: Option<BTreeMap<String, ServiceSettings>>,
+
+    // Used when --secrets-file is not given
+    #[serde(rename = "secretsFile")]
+    pub secrets_file: Option<String>,
+
+    // "docker" or "podman". Detected when not set
+    pub engine: Option<Engine>,
+
+    // Build images with BuildKit. By default only the Dockerfiles that need it are
+    pub buildkit: Option<bool>,
+
+    // credentials by registry host, used instead of the docker cli ones
+    #[serde(rename = "registryAuth")]
+    pub registry_auth: Option<HashMap<String, RegistryCredentials>>,
+
+    // times a failed image pull is retried, when the failure looks like a network one
+    #[serde(rename = "pullRetries")]
+    pub pull_retries: Option<u32>,
+
+    // seconds before the first retry, doubled for every next one
+    #[serde(rename = "pullRetryDelay")]
+    pub pull_retry_delay: Option<u64>,
+
+    // os/arch of the images pulled and built, linux/amd64 to emulate it on arm64 hosts
+    pub platform: Option<String>,
+
+    // memory, cpus, shmSize and ulimits of the container, over the ones of the runArgs
+    pub resources: Option<ResourceLimits>,
+
+    // always keep the workspace in a volume, like --workspace-in-volume
+    #[serde(rename = "workspaceInVolume")]
+    pub workspace_in_volume: Option<WorkspaceInVolume>,
+
+    // `init` of the containers whose devcontainer.json doesn't set it
+    pub init: Option<bool>,
+
+    // "name:ip" entries added to /etc/hosts of the container, like --add-host
+    #[serde(rename = "extraHosts")]
+    pub extra_hosts: Option<Vec<String>>,
+
+    // like --restart: "unless-stopped" keeps the container running across host reboots
+    #[serde(rename = "restartPolicy")]
+    pub restart_policy: Option<String>,
+
+    pub hostname: Option<String>,
+
+    // Wait for the healthchecks of the containers before postCreateCommand and postStartCommand.
+    // Enabled by default
+    #[serde(rename = "waitForHealthy")]
+    pub wait_for_healthy: Option<bool>,
+
+    // seconds to wait for them
+    #[serde(rename = "healthTimeout")]
+    pub health_timeout: Option<u64>,
+
+    // stopped devcontainers not used for this many days are orphaned too, like prune --older-than
+    #[serde(rename = "pruneAfterDays")]
+    pub prune_after_days: Option<u64>,
+
+    // commit the container on `down`, the next `up` starts from it. Like down --snapshot
+    #[serde(rename = "snapshotOnDown")]
+    pub snapshot_on_down: Option<bool>,
+
+    // host address of the forwarded ports, 0.0.0.0 publishes them on every interface
+    #[serde(rename = "bindAddress")]
+    pub bind_address: Option<IpAddr>,
+
+    // "linux" or "windows", the paths the daemon takes as bind mount sources. Detected when
+    // running inside WSL or on Windows
+    #[serde(rename = "bindMountPaths")]
+    pub bind_mount_paths: Option<PathStyle>,
+
+    // like up --on-exit
+    #[serde(rename = "onContainerExit")]
+    pub on_container_exit: Option<ExitPolicy>,
+
+    // "cli" or "native". The cli when it is installed
+    #[serde(rename = "composeEngine")]
+    pub compose_engine: Option<ComposeEngine>,
+
+    // the compose cli, "docker compose" or "docker-compose". Detected when not set
+    #[serde(rename = "composeCommand")]
+    pub compose_command: Option<String>,
+
+    // compose profiles to activate, COMPOSE_PROFILES is used when not set
+    #[serde(rename = "composeProfiles")]
+    pub compose_profiles: Option<Vec<String>>,
+
+    // over the composeProjectName of the devcontainer
+    #[serde(rename = "composeProjectName")]
+    pub compose_project_name: Option<String>,
+}
+
+fn apply_service_settings(
+    service: &mut Service,
+    mounts: Option<&Vec<String>>,
+    envs: Option<&BTreeMap<String, String>>,
+) {
+    if let Some(mounts) = mounts {
+        service
+            .volumes
+            .get_or_insert_with(Vec::new)
+            .extend(mounts.iter().cloned());
+    }
+
+    if let Some(envs) = envs {
+        service
+            .environment
+            .get_or_insert_with(HashMap::new)
+            .extend(envs.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+// Ports go to the service they name ("db:5432"), `default_service` otherwise. `bind_address`
+// gives the host address of each container port, `host_port` its host port, None for a free one
+fn add_forward_ports(
+    services: &mut HashMap<String, Service>,
+    default_service: &str,
+    ports: &[ForwardPort],
+    bind_address: &dyn Fn(u16) -> IpAddr,
+    host_port: &dyn Fn(&ForwardPort) -> Option<u16>,
+) {
+    for port in ports {
+        let service_name = port.service.as_deref().unwrap_or(default_service);
+        let host_ip = match bind_address(port.container_port) {
+            IpAddr::V6(ip) => format!("[{}]", ip),
+            ip => ip.to_string(),
+        };
+        let host_port = host_port(port)
+            .map(|port| port.to_string())
+            .unwrap_or_default();
+        let mapping = format!("{}:{}:{}", host_ip, host_port, port.container_port);
+
+        let ports = services
+            .entry(service_name.to_string())
+            .or_default()
+            .ports
+            .get_or_insert_with(Vec::new);
+        if !ports.contains(&mapping) {
+            ports.push(mapping);
+        }
+    }
 }
 
 impl Settings {
@@ -57,53 +265,106 @@ impl Settings {
         Ok(settings)
     }
 
-    pub async fn generate_compose_override(
+    pub fn get_bind_address(&self) -> IpAddr {
+        self.bind_address.unwrap_or(DEFAULT_BIND_ADDRESS)
+    }
+
+    pub fn get_application_port(&self) -> Option<ForwardPort> {
+        let port = self.application.as_ref()?.port?;
+
+        Some(ForwardPort {
+            service: None,
+            host_port: port,
+            container_port: port,
+        })
+    }
+
+    // The top level settings go to the main service, `services` to each named service
+    // `main_service` holds what the devcontainer itself sets on its service (envs, labels, user...),
+    // the user settings are applied on top of it
+    pub fn build_compose_override(
         &self,
-        service_name: String,
+        service_name: &str,
         version: String,
-        envs: Option<HashMap<String, String>>,
-    ) -> Result<PathBuf, Error> {
-        let mut envs = envs.unwrap_or(HashMap::new());
-
-        if let Some(settings_envs) = self.envs.as_ref() {
-            for (key, value) in settings_envs.iter() {
-                envs.insert(key.clone(), value.clone());
-            }
+        mut main_service: Service,
+        forward_ports: &[ForwardPort],
+        bind_address: &dyn Fn(u16) -> IpAddr,
+        host_port: &dyn Fn(&ForwardPort) -> Option<u16>,
+    ) -> SettingsComposeModel {
+        if main_service.environment.is_none() {
+            main_service.environment = Some(HashMap::new());
         }
-
-        let service = Service {
-            ports: self
-                .forward_ports
-                .clone()
-                .map(|ports| ports.iter().map(|p| format!("{}:{}", p, p)).collect()),
-            volumes: self.mounts.clone(),
-            environment: Some(envs),
-            ..Service::default()
-        };
+        apply_service_settings(&mut main_service, self.mounts.as_ref(), self.envs.as_ref());
 
         let mut services = HashMap::new();
-        services.insert(service_name.clone(), service);
+        services.insert(service_name.to_string(), main_service);
 
-        let compose_model = SettingsComposeModel {
-            version,
-            services,
-            ..SettingsComposeModel::default()
-        };
+        add_forward_ports(
+            &mut services,
+            service_name,
+            forward_ports,
+            bind_address,
+            host_port,
+        );
+        add_forward_ports(
+            &mut services,
+            service_name,
+            self.forward_ports.as_deref().unwrap_or_default(),
+            bind_address,
+            host_port,
+        );
+        add_forward_ports(
+            &mut services,
+            service_name,
+            &self.get_application_port().into_iter().collect::<Vec<_>>(),
+            bind_address,
+            host_port,
+        );
 
-        let mut path = std::env::temp_dir();
-        path.push(format!("{}-compose.yml", service_name));
-
-        let mut file = tokio::fs::File::create(&path)
-            .await
-            .map_err(|err| Error::Other(err.to_string()))?;
+        if let Some(services_settings) = self.services.as_ref() {
+            for (name, service_settings) in services_settings.iter() {
+                let service = services.entry(name.clone()).or_default();
+                apply_service_settings(
+                    service,
+                    service_settings.mounts.as_ref(),
+                    service_settings.envs.as_ref(),
+                );
+                add_forward_ports(
+                    &mut services,
+                    name,
+                    service_settings
+                        .forward_ports
+                        .as_deref()
+                        .unwrap_or_default(),
+                    bind_address,
+                    host_port,
+                );
+            }
+        }
 
-        let data =
-            serde_yaml::to_vec(&compose_model).map_err(|err| Error::Other(err.to_string()))?;
+        SettingsComposeModel { version, services }
+    }
+}
 
-        file.write_all(data.as_slice())
-            .await
-            .map_err(|err| Error::Other(err.to_string()))?;
+// Writes the override into the state folder of the project, where it goes after the files of
+// the project. Left untouched when it did not change
+pub async fn write_compose_override(
+    state_dir: &Path,
+    compose_model: &SettingsComposeModel,
+) -> Result<PathBuf, Error> {
+    let path = state_dir.join("compose-override.yml");
+    let data = serde_yaml::to_vec(compose_model).map_err(|err| Error::Other(err.to_string()))?;
 
-        Ok(path)
+    if fs::read(&path).await.ok().as_ref() == Some(&data) {
+        return Ok(path);
     }
+
+    fs::create_dir_all(state_dir)
+        .await
+        .map_err(|err| Error::Other(err.to_string()))?;
+    fs::write(&path, data)
+        .await
+        .map_err(|err| Error::Other(err.to_string()))?;
+
+    Ok(path)
 }
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SettingsComposeModel {
+    // empty for files without one, the compose specification doesn't need it
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub version: String,
     pub services: HashMap<String, Service>,
 }
@@ -15,4 +17,26 @@ pub struct Service {
     pub ports: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub environment: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub init: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_hosts: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    // only written, the compose files have their own forms of it
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub build: Option<ServiceBuild>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ServiceBuild {
+    pub args: HashMap<String, String>,
 }
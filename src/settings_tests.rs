@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use super::devcontainer::ForwardPort;
+use super::settings::*;
+use super::settings_compose_model::*;
+
+#[test]
+fn test_compose_override_services() {
+    let settings: Settings = json5::from_str(
+        r#"{
+            envs: { EDITOR: "nvim" },
+            forwardPorts: [9797],
+            services: {
+                app: { mounts: ["/home/me/.config/nvim:/root/.config/nvim"] },
+                db: { envs: { POSTGRES_PASSWORD: "secret" }, forwardPorts: [5432] },
+            },
+        }"#,
+    )
+    .unwrap();
+
+    let mut envs = HashMap::new();
+    envs.insert("DEVCONTAINER_PROJECT".to_string(), "myproject".to_string());
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service {
+            environment: Some(envs),
+            ..Service::default()
+        },
+        &[],
+        &|_| DEFAULT_BIND_ADDRESS,
+        &|port| Some(port.host_port),
+    );
+
+    assert_eq!(model.services.len(), 2);
+
+    let app = model.services.get("app").unwrap();
+    let app_envs = app.environment.as_ref().unwrap();
+    assert_eq!(app_envs.get("EDITOR"), Some(&"nvim".to_string()));
+    assert_eq!(
+        app_envs.get("DEVCONTAINER_PROJECT"),
+        Some(&"myproject".to_string())
+    );
+    assert_eq!(app.ports, Some(vec!["127.0.0.1:9797:9797".to_string()]));
+    assert_eq!(
+        app.volumes,
+        Some(vec!["/home/me/.config/nvim:/root/.config/nvim".to_string()])
+    );
+
+    let db = model.services.get("db").unwrap();
+    assert_eq!(
+        db.environment.as_ref().unwrap().get("POSTGRES_PASSWORD"),
+        Some(&"secret".to_string())
+    );
+    assert_eq!(db.ports, Some(vec!["127.0.0.1:5432:5432".to_string()]));
+    assert_eq!(db.volumes, None);
+}
+
+#[test]
+fn test_compose_override_user() {
+    let settings = Settings::default();
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service {
+            user: Some("vscode".to_string()),
+            ..Service::default()
+        },
+        &[],
+        &|_| DEFAULT_BIND_ADDRESS,
+        &|port| Some(port.host_port),
+    );
+
+    let app = model.services.get("app").unwrap();
+    assert_eq!(app.user.as_deref(), Some("vscode"));
+    assert!(serde_yaml::to_string(&model)
+        .unwrap()
+        .contains("user: vscode"));
+}
+
+#[test]
+fn test_compose_override_forward_ports() {
+    let settings: Settings = json5::from_str(
+        r#"{ forwardPorts: [9797, "db:5432"], application: { cmd: "nvim-qt", port: 6666 } }"#,
+    )
+    .unwrap();
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service::default(),
+        &[
+            ForwardPort::parse("8000:8080").unwrap(),
+            ForwardPort::parse("redis:6379").unwrap(),
+        ],
+        &|_| DEFAULT_BIND_ADDRESS,
+        // taken, published on a free port
+        &|port| Some(port.host_port).filter(|port| *port != 6379),
+    );
+
+    let app = model.services.get("app").unwrap();
+    assert_eq!(
+        app.ports,
+        Some(vec![
+            "127.0.0.1:8000:8080".to_string(),
+            "127.0.0.1:9797:9797".to_string(),
+            "127.0.0.1:6666:6666".to_string()
+        ])
+    );
+    assert_eq!(
+        model.services.get("db").unwrap().ports,
+        Some(vec!["127.0.0.1:5432:5432".to_string()])
+    );
+    assert_eq!(
+        model.services.get("redis").unwrap().ports,
+        Some(vec!["127.0.0.1::6379".to_string()])
+    );
+}
+
+#[test]
+fn test_compose_override_bind_address() {
+    let settings: Settings =
+        json5::from_str(r#"{ bindAddress: "0.0.0.0", forwardPorts: [9797] }"#).unwrap();
+    assert_eq!(settings.get_bind_address().to_string(), "0.0.0.0");
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service::default(),
+        &[ForwardPort::parse("5432").unwrap()],
+        &|port| match port {
+            5432 => "::1".parse().unwrap(),
+            _ => settings.get_bind_address(),
+        },
+        &|port| Some(port.host_port),
+    );
+    assert_eq!(
+        model.services.get("app").unwrap().ports,
+        Some(vec![
+            "[::1]:5432:5432".to_string(),
+            "0.0.0.0:9797:9797".to_string()
+        ])
+    );
+
+    assert!(json5::from_str::<Settings>(r#"{ bindAddress: "localhost" }"#).is_err());
+    assert_eq!(Settings::default().get_bind_address(), DEFAULT_BIND_ADDRESS);
+}
+
+#[test]
+fn test_workspace_in_volume() {
+    let settings: Settings = json5::from_str(r#"{ workspaceInVolume: "clone" }"#).unwrap();
+    assert_eq!(settings.workspace_in_volume, Some(WorkspaceInVolume::Clone));
+
+    let settings: Settings = json5::from_str(r#"{ workspaceInVolume: "copy" }"#).unwrap();
+    assert_eq!(settings.workspace_in_volume, Some(WorkspaceInVolume::Copy));
+
+    assert!(json5::from_str::<Settings>(r#"{ workspaceInVolume: "rsync" }"#).is_err());
+}
+
+#[test]
+fn test_on_container_exit() {
+    let settings: Settings = json5::from_str(r#"{ onContainerExit: "restart" }"#).unwrap();
+    assert_eq!(settings.on_container_exit, Some(ExitPolicy::Restart));
+
+    let settings: Settings = json5::from_str(r#"{ onContainerExit: "prompt" }"#).unwrap();
+    assert_eq!(settings.on_container_exit, Some(ExitPolicy::Prompt));
+
+    assert_eq!(Settings::default().on_container_exit, None);
+    assert!(json5::from_str::<Settings>(r#"{ onContainerExit: "always" }"#).is_err());
+}
+
+#[test]
+fn test_compose_cli() {
+    let settings: Settings =
+        json5::from_str(r#"{ composeCommand: "docker-compose", composeEngine: "cli" }"#).unwrap();
+    assert_eq!(settings.compose_command, Some("docker-compose".to_string()));
+    assert_eq!(settings.compose_engine, Some(ComposeEngine::Cli));
+
+    assert!(json5::from_str::<Settings>(r#"{ composeEngine: "v2" }"#).is_err());
+}
+
+#[test]
+fn test_compose_override_init() {
+    let settings = Settings::default();
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service {
+            init: Some(true),
+            ..Service::default()
+        },
+        &[],
+        &|_| DEFAULT_BIND_ADDRESS,
+        &|port| Some(port.host_port),
+    );
+
+    let yaml = serde_yaml::to_string(&model).unwrap();
+    assert!(yaml.contains("init: true"));
+
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service::default(),
+        &[],
+        &|_| DEFAULT_BIND_ADDRESS,
+        &|port| Some(port.host_port),
+    );
+    assert!(!serde_yaml::to_string(&model).unwrap().contains("init"));
+}
+
+#[tokio::test]
+async fn test_write_compose_override() {
+    let dir = std::env::temp_dir().join("devcontainers-rs-override-test");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let settings = Settings::default();
+    let model = settings.build_compose_override(
+        "app",
+        "3".to_string(),
+        Service::default(),
+        &[],
+        &|_| DEFAULT_BIND_ADDRESS,
+        &|port| Some(port.host_port),
+    );
+
+    let path = write_compose_override(&dir, &model).await.unwrap();
+    assert_eq!(path, dir.join("compose-override.yml"));
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+    // the same override is not written again
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(write_compose_override(&dir, &model).await.unwrap(), path);
+    assert_eq!(
+        std::fs::metadata(&path).unwrap().modified().unwrap(),
+        modified
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
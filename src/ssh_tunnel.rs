@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command};
+
+use super::engine::DOCKER_SOCKET;
+use super::errors::*;
+
+// How long ssh gets to authenticate and open the forward
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ssh://[user@]host[:port][/path/to/docker.sock]
+#[derive(Debug, PartialEq)]
+pub struct SshHost {
+    // user@host, as given to ssh
+    pub destination: String,
+    pub port: Option<u16>,
+    // the daemon socket on the remote machine
+    pub socket: String,
+}
+
+impl SshHost {
+    pub fn parse(host: &str) -> Option<Self> {
+        let address = host.strip_prefix("ssh://")?;
+        let (address, socket) = match address.find('/') {
+            Some(idx) => (&address[..idx], address[idx..].to_string()),
+            None => (address, DOCKER_SOCKET.to_string()),
+        };
+
+        let (user, hostport) = match address.rsplit_once('@') {
+            Some((user, hostport)) => (Some(user), hostport),
+            None => (None, address),
+        };
+        // [::1]:22 for ipv6 addresses
+        let (hostname, port) = match hostport.strip_prefix('[') {
+            Some(rest) => {
+                let (hostname, rest) = rest.split_once(']')?;
+                (hostname, rest.strip_prefix(':'))
+            }
+            None => match hostport.split_once(':') {
+                Some((hostname, port)) => (hostname, Some(port)),
+                None => (hostport, None),
+            },
+        };
+        let port = match port {
+            Some(port) => Some(port.parse().ok()?),
+            None => None,
+        };
+        if hostname.is_empty() {
+            return None;
+        }
+
+        Some(SshHost {
+            destination: match user {
+                Some(user) => format!("{}@{}", user, hostname),
+                None => hostname.to_string(),
+            },
+            port,
+            socket,
+        })
+    }
+
+    // Forwards `local_socket` to the remote daemon socket
+    pub fn tunnel_args(&self, local_socket: &Path) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "-nNT",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "StreamLocalBindUnlink=yes",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        if let Some(port) = self.port {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+
+        args.push("-L".to_string());
+        args.push(format!("{}:{}", local_socket.display(), self.socket));
+        args.push(self.destination.clone());
+
+        args
+    }
+}
+
+// The docker API of a ssh host, through a local unix socket forwarded by ssh. The tunnel is
+// closed when dropped
+pub struct SshTunnel {
+    _child: Child,
+    pub socket: PathBuf,
+}
+
+impl SshTunnel {
+    pub async fn open(host: &SshHost, socket: PathBuf) -> Result<Self, Error> {
+        info!("Opening ssh tunnel to {}", host.destination);
+
+        let mut child = Command::new("ssh")
+            .args(host.tunnel_args(&socket))
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| Error::Other(format!("Failed to run ssh: {}", err)))?;
+        let mut stderr = child.stderr.take();
+
+        let deadline = tokio::time::Instant::now() + TUNNEL_TIMEOUT;
+        while !socket.exists() {
+            if tokio::time::Instant::now() > deadline {
+                return Err(Error::Other(format!(
+                    "Timed out opening the ssh tunnel to {}",
+                    host.destination
+                )));
+            }
+
+            tokio::select! {
+                _ = &mut child => {
+                    let mut output = String::new();
+                    if let Some(stderr) = stderr.as_mut() {
+                        stderr.read_to_string(&mut output).await.ok();
+                    }
+                    return Err(Error::Other(format!(
+                        "ssh tunnel to {} failed: {}",
+                        host.destination,
+                        output.trim()
+                    )));
+                }
+                _ = tokio::time::delay_for(Duration::from_millis(100)) => {}
+            }
+        }
+
+        Ok(SshTunnel {
+            _child: child,
+            socket,
+        })
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.socket).ok();
+    }
+}
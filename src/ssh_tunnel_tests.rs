@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use super::ssh_tunnel::*;
+
+#[test]
+fn test_parse_ssh_host() {
+    assert_eq!(
+        SshHost::parse("ssh://me@build-server"),
+        Some(SshHost {
+            destination: "me@build-server".to_string(),
+            port: None,
+            socket: "/var/run/docker.sock".to_string(),
+        })
+    );
+
+    assert_eq!(
+        SshHost::parse("ssh://me@build-server:2222/run/user/1000/podman/podman.sock"),
+        Some(SshHost {
+            destination: "me@build-server".to_string(),
+            port: Some(2222),
+            socket: "/run/user/1000/podman/podman.sock".to_string(),
+        })
+    );
+
+    assert_eq!(
+        SshHost::parse("ssh://[::1]:22"),
+        Some(SshHost {
+            destination: "::1".to_string(),
+            port: Some(22),
+            socket: "/var/run/docker.sock".to_string(),
+        })
+    );
+
+    assert_eq!(SshHost::parse("tcp://build-server:2375"), None);
+    assert_eq!(SshHost::parse("ssh://build-server:port"), None);
+    assert_eq!(SshHost::parse("ssh://"), None);
+}
+
+#[test]
+fn test_tunnel_args() {
+    let host = SshHost::parse("ssh://me@build-server:2222").unwrap();
+
+    assert_eq!(
+        host.tunnel_args(Path::new("/tmp/docker.sock")),
+        vec![
+            "-nNT",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "StreamLocalBindUnlink=yes",
+            "-p",
+            "2222",
+            "-L",
+            "/tmp/docker.sock:/var/run/docker.sock",
+            "me@build-server",
+        ]
+    );
+}
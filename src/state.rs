@@ -0,0 +1,113 @@
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use super::errors::*;
+
+// Bookkeeping of the resources created for a project that can't be found through docker labels
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProjectState {
+    #[serde(default)]
+    pub temp_files: Vec<PathBuf>,
+}
+
+impl ProjectState {
+    // One state folder per project, keyed by the project path
+    pub fn get_state_dir(project_path: &Path) -> PathBuf {
+        let mut hasher = Sha1::new();
+        hasher.input_str(project_path.to_str().unwrap());
+
+        let mut dir = dirs::cache_dir().unwrap_or_else(std::env::temp_dir);
+        dir.push("devcontainers-rs");
+        dir.push(&hasher.result_str()[0..10]);
+
+        dir
+    }
+
+    fn get_state_file(project_path: &Path) -> PathBuf {
+        Self::get_state_dir(project_path).join("state.json")
+    }
+
+    pub async fn load(project_path: &Path) -> Result<Self, Error> {
+        let path = Self::get_state_file(project_path);
+
+        if !path.exists() {
+            return Ok(ProjectState::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .await
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        serde_json::from_str(&contents).map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub async fn save(&self, project_path: &Path) -> Result<(), Error> {
+        fs::create_dir_all(Self::get_state_dir(project_path))
+            .await
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|err| Error::Other(err.to_string()))?;
+
+        fs::write(Self::get_state_file(project_path), contents)
+            .await
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub fn track_file(&mut self, path: PathBuf) {
+        if !self.temp_files.contains(&path) {
+            self.temp_files.push(path);
+        }
+    }
+}
+
+// Bookkeeping shared by every project
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GlobalState {
+    // unix time of the last orphaned containers check of `up`
+    #[serde(default)]
+    pub last_orphans_check: i64,
+}
+
+impl GlobalState {
+    fn get_state_file() -> PathBuf {
+        let mut path = dirs::cache_dir().unwrap_or_else(std::env::temp_dir);
+        path.push("devcontainers-rs");
+        path.push("global.json");
+
+        path
+    }
+
+    pub async fn load() -> Result<Self, Error> {
+        let path = Self::get_state_file();
+
+        if !path.exists() {
+            return Ok(GlobalState::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .await
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        serde_json::from_str(&contents).map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub async fn save(&self) -> Result<(), Error> {
+        let path = Self::get_state_file();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .await
+                .map_err(|err| Error::Other(err.to_string()))?;
+        }
+
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|err| Error::Other(err.to_string()))?;
+
+        fs::write(path, contents)
+            .await
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use super::state::*;
+
+#[tokio::test]
+async fn test_save_load() {
+    let project_path = PathBuf::from("/tmp/devcontainers-rs-state-test");
+
+    let mut state = ProjectState::default();
+    state.track_file(PathBuf::from("/tmp/app-compose.yml"));
+    state.track_file(PathBuf::from("/tmp/app-compose.yml"));
+    assert_eq!(state.temp_files.len(), 1);
+
+    state.save(&project_path).await.unwrap();
+    assert_eq!(ProjectState::load(&project_path).await.unwrap(), state);
+
+    ProjectState::default().save(&project_path).await.unwrap();
+    assert!(ProjectState::load(&project_path)
+        .await
+        .unwrap()
+        .temp_files
+        .is_empty());
+}
+
+#[test]
+fn test_state_dir() {
+    let a = ProjectState::get_state_dir(&PathBuf::from("/home/me/project-a"));
+    let b = ProjectState::get_state_dir(&PathBuf::from("/home/me/project-b"));
+
+    assert_ne!(a, b);
+    assert_eq!(
+        a,
+        ProjectState::get_state_dir(&PathBuf::from("/home/me/project-a"))
+    );
+}
@@ -0,0 +1,206 @@
+use bollard::service::Mount;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::errors::*;
+use super::mount_from_str::*;
+
+// Values of the ${...} variables known before the container exists
+pub struct SubstitutionContext {
+    pub local_workspace_folder: String,
+    pub container_workspace_folder: String,
+    pub devcontainer_id: String,
+    pub local_env: HashMap<String, String>,
+}
+
+fn basename(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+// Replaces every ${...} in the text with the value returned by `resolve`.
+// Variables `resolve` doesn't know about are kept as is
+pub fn replace_variables<F>(text: &str, resolve: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        result.push_str(&rest[..start]);
+        let variable = &rest[start + 2..end];
+        match resolve(variable) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+// "VAR" or "VAR:default", looked up in `env`. Missing variables without default are empty
+fn lookup_env(reference: &str, env: &HashMap<String, String>) -> String {
+    let (name, default) = match reference.split_once(':') {
+        Some((name, default)) => (name, default),
+        None => (reference, ""),
+    };
+
+    env.get(name)
+        .cloned()
+        .unwrap_or_else(|| default.to_string())
+}
+
+impl SubstitutionContext {
+    fn resolve(&self, variable: &str) -> Option<String> {
+        match variable.split_once(':') {
+            Some(("localEnv", reference)) | Some(("env", reference)) => {
+                Some(lookup_env(reference, &self.local_env))
+            }
+            Some(_) => None,
+            None => match variable {
+                "localWorkspaceFolder" => Some(self.local_workspace_folder.clone()),
+                "localWorkspaceFolderBasename" => Some(basename(&self.local_workspace_folder)),
+                "containerWorkspaceFolder" => Some(self.container_workspace_folder.clone()),
+                "containerWorkspaceFolderBasename" => {
+                    Some(basename(&self.container_workspace_folder))
+                }
+                "devcontainerId" => Some(self.devcontainer_id.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn substitute_str(&self, text: &str) -> String {
+        replace_variables(text, |variable| self.resolve(variable))
+    }
+
+    // Substitutes all the string values of a devcontainer.json. ${containerEnv:VAR} is kept, it
+    // can only be resolved once the container exists
+    pub fn substitute(&self, value: Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.substitute_str(&s)),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.substitute(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, self.substitute(v)))
+                    .collect(),
+            ),
+            v => v,
+        }
+    }
+}
+
+// ${containerEnv:VAR}, resolved with the environment of the running container
+pub fn substitute_container_env(text: &str, container_env: &HashMap<String, String>) -> String {
+    replace_variables(text, |variable| match variable.split_once(':') {
+        Some(("containerEnv", reference)) => Some(lookup_env(reference, container_env)),
+        _ => None,
+    })
+}
+
+// Names referenced through ${containerEnv:NAME} in the text
+fn container_env_references(text: &str) -> Vec<String> {
+    let names = RefCell::new(vec![]);
+    replace_variables(text, |variable| {
+        if let Some(("containerEnv", reference)) = variable.split_once(':') {
+            let name = reference.split(':').next().unwrap_or(reference);
+            names.borrow_mut().push(name.to_string());
+        }
+        None
+    });
+
+    names.into_inner()
+}
+
+// Resolves the ${containerEnv:VAR} references of containerEnv/remoteEnv. A reference to another
+// entry of `envs` gets that entry resolved first, a reference to the entry itself (or to a
+// variable not in `envs`) gets the value from `base`, the environment the entries are applied on.
+// With no `base` those references are kept as is
+pub fn resolve_env_references(
+    envs: &BTreeMap<String, String>,
+    base: Option<&HashMap<String, String>>,
+) -> Result<BTreeMap<String, String>, Error> {
+    let mut resolved = BTreeMap::new();
+    for key in envs.keys() {
+        resolve_env_entry(key, envs, base, &mut resolved, &mut vec![])?;
+    }
+
+    Ok(resolved)
+}
+
+fn resolve_env_entry(
+    key: &str,
+    envs: &BTreeMap<String, String>,
+    base: Option<&HashMap<String, String>>,
+    resolved: &mut BTreeMap<String, String>,
+    visiting: &mut Vec<String>,
+) -> Result<(), Error> {
+    if resolved.contains_key(key) {
+        return Ok(());
+    }
+
+    if visiting.iter().any(|k| k == key) {
+        visiting.push(key.to_string());
+        return Err(Error::InvalidConfig(format!(
+            "Environment variables reference each other: {}",
+            visiting.join(" -> ")
+        )));
+    }
+
+    visiting.push(key.to_string());
+    let value = &envs[key];
+    for name in container_env_references(value) {
+        if name != key && envs.contains_key(&name) {
+            resolve_env_entry(&name, envs, base, resolved, visiting)?;
+        }
+    }
+    visiting.pop();
+
+    let value = replace_variables(value, |variable| match variable.split_once(':') {
+        Some(("containerEnv", reference)) => {
+            let name = reference.split(':').next().unwrap_or(reference);
+            resolved.get(name).filter(|_| name != key).cloned()
+        }
+        _ => None,
+    });
+    let value = match base {
+        Some(base) => substitute_container_env(&value, base),
+        None => value,
+    };
+    resolved.insert(key.to_string(), value);
+
+    Ok(())
+}
+
+// Workspace folder inside the container as declared by the (already substituted) config
+pub fn container_workspace_folder(config: &Value) -> String {
+    if let Some(folder) = config.get("workspaceFolder").and_then(|f| f.as_str()) {
+        return folder.to_string();
+    }
+
+    if config.get("dockerComposeFile").is_some() {
+        return "/".to_string();
+    }
+
+    config
+        .get("workspaceMount")
+        .and_then(|m| m.as_str())
+        .and_then(|m| Mount::parse_from_str(m).ok())
+        .and_then(|m| m.target)
+        .unwrap_or_else(|| "/workspace".to_string())
+}
@@ -0,0 +1,138 @@
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+use super::substitution::*;
+
+fn context() -> SubstitutionContext {
+    let mut local_env = HashMap::new();
+    local_env.insert("HOME".to_string(), "/home/me".to_string());
+
+    SubstitutionContext {
+        local_workspace_folder: "/home/me/project".to_string(),
+        container_workspace_folder: "/workspaces/project".to_string(),
+        devcontainer_id: "abc123".to_string(),
+        local_env,
+    }
+}
+
+#[test]
+fn test_substitute_str() {
+    let context = context();
+
+    assert_eq!(
+        context.substitute_str("source=${localWorkspaceFolder},target=${containerWorkspaceFolder}"),
+        "source=/home/me/project,target=/workspaces/project"
+    );
+    assert_eq!(
+        context.substitute_str("${localWorkspaceFolderBasename}-${devcontainerId}"),
+        "project-abc123"
+    );
+    assert_eq!(
+        context.substitute_str("${localEnv:HOME}/.ssh"),
+        "/home/me/.ssh"
+    );
+    assert_eq!(context.substitute_str("${env:HOME}"), "/home/me");
+    assert_eq!(context.substitute_str("${localEnv:MISSING}"), "");
+    assert_eq!(
+        context.substitute_str("${localEnv:MISSING:fallback}"),
+        "fallback"
+    );
+
+    // only known once the container exists
+    assert_eq!(
+        context.substitute_str("${containerEnv:PATH}:/opt/bin"),
+        "${containerEnv:PATH}:/opt/bin"
+    );
+    assert_eq!(context.substitute_str("${unknown} ${"), "${unknown} ${");
+}
+
+#[test]
+fn test_substitute() {
+    let config = json!({
+        "mounts": ["source=${localEnv:HOME}/.cache,target=/cache,type=bind"],
+        "containerEnv": { "PROJECT": "${localWorkspaceFolderBasename}" },
+        "forwardPorts": [8080],
+    });
+
+    assert_eq!(
+        context().substitute(config),
+        json!({
+            "mounts": ["source=/home/me/.cache,target=/cache,type=bind"],
+            "containerEnv": { "PROJECT": "project" },
+            "forwardPorts": [8080],
+        })
+    );
+}
+
+#[test]
+fn test_substitute_container_env() {
+    let mut env = HashMap::new();
+    env.insert("PATH".to_string(), "/usr/bin".to_string());
+
+    assert_eq!(
+        substitute_container_env("${containerEnv:PATH}:/opt/bin", &env),
+        "/usr/bin:/opt/bin"
+    );
+    assert_eq!(
+        substitute_container_env("${localEnv:HOME}", &env),
+        "${localEnv:HOME}"
+    );
+}
+
+#[test]
+fn test_container_workspace_folder() {
+    assert_eq!(container_workspace_folder(&json!({})), "/workspace");
+    assert_eq!(
+        container_workspace_folder(&json!({ "workspaceFolder": "/src" })),
+        "/src"
+    );
+    assert_eq!(
+        container_workspace_folder(
+            &json!({ "workspaceMount": "source=/home/me/project,target=/code,type=bind" })
+        ),
+        "/code"
+    );
+    assert_eq!(
+        container_workspace_folder(&json!({ "dockerComposeFile": "docker-compose.yml" })),
+        "/"
+    );
+}
+
+#[test]
+fn test_resolve_env_references() {
+    let mut image_env = HashMap::new();
+    image_env.insert("PATH".to_string(), "/usr/bin".to_string());
+    image_env.insert("HOME".to_string(), "/root".to_string());
+
+    let mut envs = BTreeMap::new();
+    envs.insert(
+        "PATH".to_string(),
+        "${containerEnv:TOOL_HOME}/bin:${containerEnv:PATH}".to_string(),
+    );
+    envs.insert(
+        "TOOL_HOME".to_string(),
+        "${containerEnv:HOME}/.tool".to_string(),
+    );
+    envs.insert(
+        "EDITOR".to_string(),
+        "${containerEnv:VISUAL:vim}".to_string(),
+    );
+
+    let resolved = resolve_env_references(&envs, Some(&image_env)).unwrap();
+    assert_eq!(resolved["TOOL_HOME"], "/root/.tool");
+    assert_eq!(resolved["PATH"], "/root/.tool/bin:/usr/bin");
+    assert_eq!(resolved["EDITOR"], "vim");
+
+    // without the base environment only the references between entries are resolved
+    let resolved = resolve_env_references(&envs, None).unwrap();
+    assert_eq!(resolved["TOOL_HOME"], "${containerEnv:HOME}/.tool");
+    assert_eq!(
+        resolved["PATH"],
+        "${containerEnv:HOME}/.tool/bin:${containerEnv:PATH}"
+    );
+
+    let mut envs = BTreeMap::new();
+    envs.insert("A".to_string(), "${containerEnv:B}".to_string());
+    envs.insert("B".to_string(), "x${containerEnv:A}".to_string());
+    assert!(resolve_env_references(&envs, None).is_err());
+}
@@ -0,0 +1,238 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Component, PathBuf};
+
+use super::errors::*;
+use super::registry::*;
+
+const TEMPLATE_METADATA_FILE: &str = "devcontainer-template.json";
+
+// Files of a published template that describe it and don't belong in the workspace
+const TEMPLATE_SKIP_FILES: &[&str] = &[TEMPLATE_METADATA_FILE, "README.md", "NOTES.md"];
+
+// Built-in starting points for `init`
+pub struct Template {
+    pub name: &'static str,
+    pub image: &'static str,
+    pub post_create_command: Option<&'static str>,
+    pub forward_ports: &'static [u32],
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "base",
+        image: "mcr.microsoft.com/devcontainers/base:ubuntu",
+        post_create_command: None,
+        forward_ports: &[],
+    },
+    Template {
+        name: "rust",
+        image: "mcr.microsoft.com/devcontainers/rust:1",
+        post_create_command: Some("cargo --version"),
+        forward_ports: &[],
+    },
+    Template {
+        name: "node",
+        image: "mcr.microsoft.com/devcontainers/javascript-node:20",
+        post_create_command: Some("npm install"),
+        forward_ports: &[3000],
+    },
+    Template {
+        name: "python",
+        image: "mcr.microsoft.com/devcontainers/python:3",
+        post_create_command: Some("pip install --user -r requirements.txt"),
+        forward_ports: &[8000],
+    },
+    Template {
+        name: "go",
+        image: "mcr.microsoft.com/devcontainers/go:1",
+        post_create_command: Some("go mod download"),
+        forward_ports: &[],
+    },
+];
+
+pub fn get_template(name: &str) -> Result<&'static Template, Error> {
+    TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names: Vec<&str> = TEMPLATES.iter().map(|t| t.name).collect();
+        Error::Other(format!(
+            "Unknown template '{}'. Available templates: {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+impl Template {
+    // With `with_dockerfile` the config builds from a Dockerfile based on the template image
+    pub fn devcontainer_json(&self, project_name: &str, with_dockerfile: bool) -> String {
+        let mut lines = vec![format!("    \"name\": \"{}\",", project_name)];
+
+        if with_dockerfile {
+            lines.push("    \"build\": { \"dockerfile\": \"Dockerfile\" },".to_string());
+        } else {
+            lines.push(format!("    \"image\": \"{}\",", self.image));
+        }
+
+        let ports: Vec<String> = self.forward_ports.iter().map(|p| p.to_string()).collect();
+        lines.push(format!("    \"forwardPorts\": [{}],", ports.join(", ")));
+
+        match self.post_create_command {
+            Some(cmd) => lines.push(format!("    \"postCreateCommand\": \"{}\",", cmd)),
+            None => lines.push("    // \"postCreateCommand\": \"\",".to_string()),
+        }
+
+        format!("{{\n{}\n}}\n", lines.join("\n"))
+    }
+
+    pub fn dockerfile(&self) -> String {
+        format!("FROM {}\n", self.image)
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct TemplateOption {
+    pub default: Option<Value>,
+}
+
+// devcontainer-template.json of a template published to an OCI registry
+#[derive(Deserialize, Default)]
+pub struct TemplateMetadata {
+    pub id: Option<String>,
+
+    #[serde(default)]
+    pub options: BTreeMap<String, TemplateOption>,
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Replaces ${templateOption:name} with the given value or the option default
+pub fn substitute_options(
+    text: &str,
+    metadata: &TemplateMetadata,
+    options: &HashMap<String, String>,
+) -> String {
+    let mut result = text.to_string();
+
+    for (name, option) in metadata.options.iter() {
+        let value = options
+            .get(name)
+            .cloned()
+            .or_else(|| option.default.as_ref().map(value_to_string))
+            .unwrap_or_default();
+
+        result = result.replace(&format!("${{templateOption:{}}}", name), &value);
+    }
+
+    for (name, value) in options.iter() {
+        result = result.replace(&format!("${{templateOption:{}}}", name), value);
+    }
+
+    result
+}
+
+// Relative paths inside the workspace only
+fn is_safe_path(path: &std::path::Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+// Files of the template layer, relative to the workspace, with the options applied
+pub fn extract_template(
+    data: &[u8],
+    options: &HashMap<String, String>,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, Error> {
+    let mut files = vec![];
+    let mut metadata = None;
+
+    let mut archive = layer_archive(data)?;
+    let entries = archive
+        .entries()
+        .map_err(|err| Error::Other(err.to_string()))?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|err| Error::Other(err.to_string()))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .map_err(|err| Error::Other(err.to_string()))?
+            .into_owned();
+        if !is_safe_path(&path) {
+            return Err(Error::Other(format!(
+                "Template contains an invalid path: {}",
+                path.display()
+            )));
+        }
+        let path: PathBuf = path
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+
+        let mut contents = vec![];
+        entry
+            .read_to_end(&mut contents)
+            .map_err(|err| Error::Other(err.to_string()))?;
+
+        if path.to_str() == Some(TEMPLATE_METADATA_FILE) {
+            let metadata_str = String::from_utf8_lossy(&contents).to_string();
+            metadata = Some(
+                json5::from_str::<TemplateMetadata>(&metadata_str).map_err(|err| {
+                    Error::Other(format!("Invalid {}: {}", TEMPLATE_METADATA_FILE, err))
+                })?,
+            );
+        }
+
+        if path.components().count() == 1
+            && TEMPLATE_SKIP_FILES.contains(&path.to_str().unwrap_or_default())
+        {
+            continue;
+        }
+
+        files.push((path, contents));
+    }
+
+    let metadata = metadata.unwrap_or_default();
+    if let Some(unknown) = options
+        .keys()
+        .find(|k| !metadata.options.is_empty() && !metadata.options.contains_key(*k))
+    {
+        return Err(Error::Other(format!(
+            "Template '{}' has no option '{}'",
+            metadata.id.as_deref().unwrap_or_default(),
+            unknown
+        )));
+    }
+
+    // binary files are copied as is
+    Ok(files
+        .into_iter()
+        .map(|(path, contents)| match String::from_utf8(contents) {
+            Ok(text) => (
+                path,
+                substitute_options(&text, &metadata, options).into_bytes(),
+            ),
+            Err(err) => (path, err.into_bytes()),
+        })
+        .collect())
+}
+
+pub async fn fetch_template(
+    registry: &RegistryClient,
+    reference: &str,
+    options: &HashMap<String, String>,
+) -> Result<Vec<(PathBuf, Vec<u8>)>, Error> {
+    let image = ImageRef::parse(reference);
+    let manifest = registry.get_manifest(&image, OCI_MANIFEST).await?;
+    let layer = registry.get_first_layer(&image, &manifest).await?;
+
+    extract_template(&layer, options)
+}
@@ -0,0 +1,70 @@
+use super::devcontainer::*;
+use super::templates::*;
+
+#[test]
+fn test_get_template() {
+    assert_eq!(get_template("rust").unwrap().name, "rust");
+    assert!(get_template("cobol").is_err());
+}
+
+#[test]
+fn test_templates_are_valid() {
+    for template in TEMPLATES {
+        for with_dockerfile in [false, true] {
+            let json = template.devcontainer_json("myproject", with_dockerfile);
+            let devcontainer: DevContainer = json5::from_str(&json).unwrap();
+            devcontainer.validate().unwrap();
+
+            assert_eq!(devcontainer.build.is_some(), with_dockerfile);
+            assert_eq!(devcontainer.image.is_some(), !with_dockerfile);
+        }
+    }
+}
+
+fn build_tar(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, contents.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_extract_template() {
+    let data = build_tar(&[
+        (
+            "./devcontainer-template.json",
+            r#"{ "id": "rust", "options": { "variant": { "type": "string", "default": "bookworm" }, "edition": { "type": "string", "default": "2021" } } }"#,
+        ),
+        ("./README.md", "# Rust"),
+        (
+            "./.devcontainer/devcontainer.json",
+            r#"{ "image": "mcr.microsoft.com/devcontainers/rust:1-${templateOption:variant}", "edition": "${templateOption:edition}" }"#,
+        ),
+    ]);
+
+    let mut options = std::collections::HashMap::new();
+    options.insert("variant".to_string(), "bullseye".to_string());
+
+    let files = extract_template(&data, &options).unwrap();
+    assert_eq!(files.len(), 1);
+
+    let (path, contents) = &files[0];
+    assert_eq!(
+        path,
+        &std::path::PathBuf::from(".devcontainer/devcontainer.json")
+    );
+    assert_eq!(
+        String::from_utf8(contents.clone()).unwrap(),
+        r#"{ "image": "mcr.microsoft.com/devcontainers/rust:1-bullseye", "edition": "2021" }"#
+    );
+
+    options.insert("unknown".to_string(), "x".to_string());
+    assert!(extract_template(&data, &options).is_err());
+}
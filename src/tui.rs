@@ -0,0 +1,439 @@
+use bollard::{
+    container::{LogOutput, LogsOptions, Stats, StatsOptions, WaitContainerOptions},
+    Docker,
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use futures::{FutureExt, StreamExt};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, Paragraph},
+    Frame,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::errors::*;
+
+const MAX_LOG_LINES: usize = 1000;
+
+// Where the logs and the hook output go while the dashboard holds the terminal
+static OUTPUT: Mutex<Option<UnboundedSender<String>>> = Mutex::new(None);
+
+// Sends the lines to the dashboard log pane, false when it is not up
+pub fn send_output(text: &str) -> bool {
+    let output = OUTPUT.lock().unwrap();
+    match output.as_ref() {
+        Some(tx) => {
+            for line in text.lines() {
+                let _ = tx.send(line.to_string());
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+// env_logger, unless the dashboard is up
+pub struct DashboardLogger {
+    inner: env_logger::Logger,
+}
+
+impl DashboardLogger {
+    pub fn init(env: env_logger::Env) {
+        let inner = env_logger::Builder::from_env(env).build();
+        log::set_max_level(inner.filter());
+        let _ = log::set_boxed_logger(Box::new(DashboardLogger { inner }));
+    }
+}
+
+impl log::Log for DashboardLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+
+        let line = format!("[{} {}] {}", record.level(), record.target(), record.args());
+        if !send_output(&line) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+// Sends the output to the dashboard until dropped
+pub struct OutputCapture;
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        *OUTPUT.lock().unwrap() = None;
+    }
+}
+
+pub enum DashboardAction {
+    Stop,
+    Restart,
+    Shell,
+    ContainerExited,
+    ApplicationExited,
+}
+
+enum ContainerEvent {
+    Log(String),
+    Stats(Box<Stats>),
+    Exited,
+}
+
+pub struct Dashboard {
+    docker: Docker,
+    title: String,
+    container_id: String,
+    ports: Vec<String>,
+    phases: Vec<String>,
+    logs: Vec<String>,
+    cpu: Option<f64>,
+    memory: Option<(u64, u64)>,
+    exited: bool,
+    // restarts of the container, the events of the runs before are stale
+    run: u64,
+    tx: UnboundedSender<(u64, ContainerEvent)>,
+    events: UnboundedReceiver<(u64, ContainerEvent)>,
+    // logs, hook and application output, not tied to a run of the container
+    output_tx: UnboundedSender<String>,
+    output: UnboundedReceiver<String>,
+}
+
+impl Dashboard {
+    pub fn new(
+        docker: &Docker,
+        title: String,
+        container_id: String,
+        ports: Vec<String>,
+        phases: Vec<String>,
+    ) -> Self {
+        let (tx, events) = unbounded_channel();
+        let (output_tx, output) = unbounded_channel();
+
+        let dashboard = Dashboard {
+            docker: docker.clone(),
+            title,
+            container_id,
+            ports,
+            phases,
+            logs: vec![],
+            cpu: None,
+            memory: None,
+            exited: false,
+            run: 0,
+            tx,
+            events,
+            output_tx,
+            output,
+        };
+        dashboard.watch(None);
+
+        dashboard
+    }
+
+    // The restart stopped the container: its logs, stats and exit are watched again, the exit
+    // of the previous run is ignored
+    pub fn restarted(&mut self, since: i64) {
+        self.run += 1;
+        self.exited = false;
+        self.cpu = None;
+        self.memory = None;
+        self.watch(Some(since));
+    }
+
+    // Follows the logs (the last lines, or the ones since a restart), stats and exit of the
+    // current run of the container
+    fn watch(&self, since: Option<i64>) {
+        let run = self.run;
+
+        let logs_docker = self.docker.clone();
+        let logs_tx = self.tx.clone();
+        let logs_id = self.container_id.clone();
+        tokio::spawn(async move {
+            let mut stream = logs_docker.logs(
+                logs_id.as_str(),
+                Some(LogsOptions {
+                    follow: true,
+                    stdout: true,
+                    stderr: true,
+                    since: since.unwrap_or(0),
+                    tail: match since {
+                        Some(_) => "all".to_string(),
+                        None => "100".to_string(),
+                    },
+                    ..Default::default()
+                }),
+            );
+
+            while let Some(Ok(log)) = stream.next().await {
+                let bytes = match log {
+                    LogOutput::StdOut { message }
+                    | LogOutput::StdErr { message }
+                    | LogOutput::Console { message }
+                    | LogOutput::StdIn { message } => message,
+                };
+
+                for line in String::from_utf8_lossy(&bytes).lines() {
+                    if logs_tx
+                        .send((run, ContainerEvent::Log(line.to_string())))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+
+        let stats_docker = self.docker.clone();
+        let stats_tx = self.tx.clone();
+        let stats_id = self.container_id.clone();
+        tokio::spawn(async move {
+            let mut stream =
+                stats_docker.stats(stats_id.as_str(), Some(StatsOptions { stream: true }));
+
+            while let Some(Ok(stats)) = stream.next().await {
+                if stats_tx
+                    .send((run, ContainerEvent::Stats(Box::new(stats))))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        let wait_docker = self.docker.clone();
+        let wait_tx = self.tx.clone();
+        let wait_id = self.container_id.clone();
+        tokio::spawn(async move {
+            let mut stream =
+                wait_docker.wait_container(wait_id.as_str(), None::<WaitContainerOptions<String>>);
+            stream.next().await;
+            let _ = wait_tx.send((run, ContainerEvent::Exited));
+        });
+    }
+
+    // Logs and hook output go to the log pane, not the terminal, until the capture is dropped
+    pub fn capture_output(&self) -> OutputCapture {
+        *OUTPUT.lock().unwrap() = Some(self.output_tx.clone());
+        OutputCapture
+    }
+
+    // Lines of the application stdout or stderr, piped when it was spawned
+    pub fn follow_output<R: AsyncRead + Unpin + Send + 'static>(&self, reader: Option<R>) {
+        let reader = match reader {
+            Some(reader) => reader,
+            None => return,
+        };
+
+        let output_tx = self.output_tx.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if output_tx.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn push_log(&mut self, line: String) {
+        self.logs.push(line);
+        if self.logs.len() > MAX_LOG_LINES {
+            self.logs.remove(0);
+        }
+    }
+
+    pub fn add_phase(&mut self, phase: &str) {
+        self.phases.push(phase.to_string());
+    }
+
+    fn cpu_percent(stats: &Stats) -> Option<f64> {
+        let cpu_delta = stats.cpu_stats.cpu_usage.total_usage as f64
+            - stats.precpu_stats.cpu_usage.total_usage as f64;
+        let system_delta = stats.cpu_stats.system_cpu_usage? as f64
+            - stats.precpu_stats.system_cpu_usage.unwrap_or(0) as f64;
+        let cpus = stats.cpu_stats.online_cpus.unwrap_or(1) as f64;
+
+        if system_delta <= 0.0 {
+            return None;
+        }
+
+        Some(cpu_delta / system_delta * cpus * 100.0)
+    }
+
+    fn drain_events(&mut self) {
+        while let Ok(line) = self.output.try_recv() {
+            self.push_log(line);
+        }
+
+        while let Ok((run, event)) = self.events.try_recv() {
+            if run != self.run {
+                continue;
+            }
+            match event {
+                ContainerEvent::Log(line) => self.push_log(line),
+                ContainerEvent::Stats(stats) => {
+                    self.cpu = Self::cpu_percent(&stats);
+                    self.memory = stats
+                        .memory_stats
+                        .usage
+                        .map(|usage| (usage, stats.memory_stats.limit.unwrap_or(0)));
+                }
+                ContainerEvent::Exited => self.exited = true,
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, logs, footer] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let state = if self.exited { "exited" } else { "running" };
+        let ports = if self.ports.is_empty() {
+            "-".to_string()
+        } else {
+            self.ports.join(", ")
+        };
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!("Container: {} ({})", self.container_id, state)),
+                Line::from(format!("Forwarded ports: {}", ports)),
+            ])
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.title.as_str()),
+            ),
+            header,
+        );
+
+        let [phases_area, resources_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(body);
+
+        frame.render_widget(
+            List::new(self.phases.iter().map(|p| format!("✓ {}", p)))
+                .block(Block::default().borders(Borders::ALL).title("Phases")),
+            phases_area,
+        );
+
+        let cpu = self
+            .cpu
+            .map(|c| format!("{:.1}%", c))
+            .unwrap_or_else(|| "-".to_string());
+        let memory = self
+            .memory
+            .map(|(usage, limit)| {
+                format!(
+                    "{:.1} MiB / {:.1} MiB",
+                    usage as f64 / 1048576.0,
+                    limit as f64 / 1048576.0
+                )
+            })
+            .unwrap_or_else(|| "-".to_string());
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!("CPU: {}", cpu)),
+                Line::from(format!("Memory: {}", memory)),
+            ])
+            .block(Block::default().borders(Borders::ALL).title("Resources")),
+            resources_area,
+        );
+
+        let visible = logs.height.saturating_sub(2) as usize;
+        let skip = self.logs.len().saturating_sub(visible);
+        frame.render_widget(
+            Paragraph::new(
+                self.logs
+                    .iter()
+                    .skip(skip)
+                    .map(|l| Line::from(l.as_str()))
+                    .collect::<Vec<Line>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title("Logs")),
+            logs,
+        );
+
+        frame.render_widget(
+            Paragraph::new("q: stop  r: restart  s: shell")
+                .style(Style::default().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    async fn read_key() -> Result<Option<KeyCode>, Error> {
+        tokio::task::spawn_blocking(|| -> std::io::Result<Option<KeyCode>> {
+            if event::poll(Duration::from_millis(250))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        return Ok(Some(key.code));
+                    }
+                }
+            }
+            Ok(None)
+        })
+        .await
+        .map_err(|err| Error::Other(err.to_string()))?
+        .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    // Takes over the terminal until the user picks an action or the container/application exits
+    pub async fn run(&mut self, child: &mut Option<Child>) -> Result<DashboardAction, Error> {
+        let mut terminal = ratatui::init();
+        let _capture = self.capture_output();
+
+        let action = loop {
+            self.drain_events();
+
+            if self.exited {
+                break Ok(DashboardAction::ContainerExited);
+            }
+
+            if let Some(c) = child.as_mut() {
+                if c.now_or_never().is_some() {
+                    break Ok(DashboardAction::ApplicationExited);
+                }
+            }
+
+            if let Err(err) = terminal.draw(|frame| self.draw(frame)) {
+                break Err(Error::Other(err.to_string()));
+            }
+
+            match Self::read_key().await {
+                Ok(Some(KeyCode::Char('q'))) | Ok(Some(KeyCode::Esc)) => {
+                    break Ok(DashboardAction::Stop)
+                }
+                Ok(Some(KeyCode::Char('r'))) => break Ok(DashboardAction::Restart),
+                Ok(Some(KeyCode::Char('s'))) => break Ok(DashboardAction::Shell),
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        ratatui::restore();
+
+        action
+    }
+}
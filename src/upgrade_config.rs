@@ -0,0 +1,348 @@
+use serde_json::{json, Map, Value};
+
+use super::errors::*;
+
+// Span of a top level property in the json5 text, including its trailing comma
+#[derive(Debug)]
+struct Property {
+    key: String,
+    start: usize,
+    end: usize,
+}
+
+struct Scanner<'a> {
+    text: &'a [u8],
+}
+
+impl<'a> Scanner<'a> {
+    fn err(&self, i: usize) -> Error {
+        Error::InvalidConfig(format!("Unexpected end of config at position {}", i))
+    }
+
+    fn skip_ws_comments(&self, mut i: usize) -> usize {
+        while i < self.text.len() {
+            match self.text[i] {
+                b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+                b'/' if self.text.get(i + 1) == Some(&b'/') => {
+                    while i < self.text.len() && self.text[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                b'/' if self.text.get(i + 1) == Some(&b'*') => {
+                    i += 2;
+                    while i + 1 < self.text.len()
+                        && !(self.text[i] == b'*' && self.text[i + 1] == b'/')
+                    {
+                        i += 1;
+                    }
+                    i += 2;
+                }
+                _ => break,
+            }
+        }
+        i
+    }
+
+    fn skip_string(&self, mut i: usize) -> Result<usize, Error> {
+        let quote = self.text[i];
+        i += 1;
+        while i < self.text.len() {
+            match self.text[i] {
+                b'\\' => i += 2,
+                c if c == quote => return Ok(i + 1),
+                _ => i += 1,
+            }
+        }
+        Err(self.err(i))
+    }
+
+    fn skip_value(&self, mut i: usize) -> Result<usize, Error> {
+        let mut depth = 0;
+        while i < self.text.len() {
+            i = self.skip_ws_comments(i);
+            if i >= self.text.len() {
+                break;
+            }
+            match self.text[i] {
+                b'"' | b'\'' => i = self.skip_string(i)?,
+                b'{' | b'[' => {
+                    depth += 1;
+                    i += 1;
+                }
+                b'}' | b']' if depth == 0 => return Ok(i),
+                b'}' | b']' => {
+                    depth -= 1;
+                    i += 1;
+                }
+                b',' if depth == 0 => return Ok(i),
+                _ => i += 1,
+            }
+            if depth == 0 {
+                // scalars: keep going until a delimiter
+                let next = self.skip_ws_comments(i);
+                if next >= self.text.len() || matches!(self.text[next], b',' | b'}' | b']') {
+                    return Ok(i);
+                }
+            }
+        }
+        Err(self.err(i))
+    }
+
+    // Returns the top level properties and the position of the closing brace
+    fn top_level_properties(&self) -> Result<(Vec<Property>, usize), Error> {
+        let mut i = self.skip_ws_comments(0);
+        if self.text.get(i) != Some(&b'{') {
+            return Err(Error::InvalidConfig("Config must be an object".to_string()));
+        }
+        i += 1;
+
+        let mut properties = vec![];
+
+        loop {
+            i = self.skip_ws_comments(i);
+            match self.text.get(i) {
+                None => return Err(self.err(i)),
+                Some(b'}') => return Ok((properties, i)),
+                _ => {}
+            }
+
+            let start = i;
+            let key = if matches!(self.text[i], b'"' | b'\'') {
+                let end = self.skip_string(i)?;
+                let key = String::from_utf8_lossy(&self.text[i + 1..end - 1]).to_string();
+                i = end;
+                key
+            } else {
+                while i < self.text.len() && !matches!(self.text[i], b':' | b' ' | b'\t' | b'\n') {
+                    i += 1;
+                }
+                String::from_utf8_lossy(&self.text[start..i]).to_string()
+            };
+
+            i = self.skip_ws_comments(i);
+            if self.text.get(i) != Some(&b':') {
+                return Err(Error::InvalidConfig(format!(
+                    "Expected ':' after '{}' at position {}",
+                    key, i
+                )));
+            }
+            i = self.skip_value(i + 1)?;
+
+            let value_end = i;
+            let after = self.skip_ws_comments(i);
+            let end = if self.text.get(after) == Some(&b',') {
+                i = after + 1;
+                i
+            } else {
+                value_end
+            };
+
+            properties.push(Property { key, start, end });
+        }
+    }
+}
+
+fn merge_into(target: &mut Map<String, Value>, key: &str, value: Value) {
+    match (target.get_mut(key), value) {
+        (Some(Value::Object(existing)), Value::Object(new)) => {
+            for (k, v) in new {
+                existing.entry(k).or_insert(v);
+            }
+        }
+        (Some(Value::Array(existing)), Value::Array(new)) => {
+            for v in new {
+                if !existing.contains(&v) {
+                    existing.push(v);
+                }
+            }
+        }
+        (Some(_), _) => {}
+        (None, value) => {
+            target.insert(key.to_string(), value);
+        }
+    }
+}
+
+// Computes the new values of the migrated properties. `None` values are removed
+fn migrate(config: &Map<String, Value>) -> (Vec<(String, Option<Value>)>, Vec<String>) {
+    let mut edits = vec![];
+    let mut changes = vec![];
+
+    let extensions = config.get("extensions");
+    let settings = config.get("settings");
+    if extensions.is_some() || settings.is_some() {
+        let mut customizations = config
+            .get("customizations")
+            .filter(|c| c.is_object())
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let customizations_map = customizations.as_object_mut().unwrap();
+
+        let mut vscode = customizations_map
+            .remove("vscode")
+            .filter(|v| v.is_object())
+            .unwrap_or_else(|| json!({}));
+        let vscode_map = vscode.as_object_mut().unwrap();
+
+        if let Some(extensions) = extensions {
+            merge_into(vscode_map, "extensions", extensions.clone());
+            edits.push(("extensions".to_string(), None));
+            changes.push("Moved 'extensions' to 'customizations.vscode.extensions'".to_string());
+        }
+        if let Some(settings) = settings {
+            merge_into(vscode_map, "settings", settings.clone());
+            edits.push(("settings".to_string(), None));
+            changes.push("Moved 'settings' to 'customizations.vscode.settings'".to_string());
+        }
+
+        customizations_map.insert("vscode".to_string(), vscode);
+        edits.push(("customizations".to_string(), Some(customizations)));
+    }
+
+    if config.contains_key("devPort") {
+        edits.push(("devPort".to_string(), None));
+        changes.push("Removed deprecated 'devPort'".to_string());
+    }
+
+    // numeric appPort entries are better expressed as forwardPorts
+    let app_ports = match config.get("appPort") {
+        Some(Value::Number(n)) => Some(vec![Value::Number(n.clone())]),
+        Some(Value::Array(ports)) if ports.iter().all(|p| p.is_number()) => Some(ports.clone()),
+        _ => None,
+    };
+    if let Some(app_ports) = app_ports {
+        let mut forward_ports = config
+            .get("forwardPorts")
+            .cloned()
+            .unwrap_or_else(|| json!([]));
+        if let Some(ports) = forward_ports.as_array_mut() {
+            for p in app_ports {
+                if !ports.contains(&p) {
+                    ports.push(p);
+                }
+            }
+        }
+
+        edits.push(("appPort".to_string(), None));
+        edits.push(("forwardPorts".to_string(), Some(forward_ports)));
+        changes.push("Moved 'appPort' to 'forwardPorts'".to_string());
+    }
+
+    (edits, changes)
+}
+
+fn line_start(text: &str, pos: usize) -> usize {
+    let before = &text[..pos];
+    match before.rfind('\n') {
+        Some(idx) if before[idx + 1..].trim().is_empty() => idx + 1,
+        _ => pos,
+    }
+}
+
+fn line_end(text: &str, pos: usize) -> usize {
+    let after = &text[pos..];
+    match after.find('\n') {
+        Some(idx) if after[..idx].trim().is_empty() => pos + idx + 1,
+        _ => pos,
+    }
+}
+
+fn indent_value(value: &Value, indent: &str) -> String {
+    serde_json::to_string_pretty(value)
+        .unwrap()
+        .lines()
+        .collect::<Vec<&str>>()
+        .join(&format!("\n{}", indent))
+}
+
+// Returns the upgraded config and a description of the changes, or None when up to date.
+// Properties that are not migrated are left untouched, comments included
+pub fn upgrade(text: &str) -> Result<Option<(String, Vec<String>)>, Error> {
+    let config: Value =
+        json5::from_str(text).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+    let config = config
+        .as_object()
+        .ok_or_else(|| Error::InvalidConfig("Config must be an object".to_string()))?;
+
+    let (edits, changes) = migrate(config);
+    if edits.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((apply_edits(text, &edits)?, changes)))
+}
+
+// Replaces (or removes, when the value is None) top level properties of a json5 document.
+// Replaced properties are moved to the end of the object, everything else is kept as is
+pub fn apply_edits(text: &str, edits: &[(String, Option<Value>)]) -> Result<String, Error> {
+    let scanner = Scanner {
+        text: text.as_bytes(),
+    };
+    let (properties, closing) = scanner.top_level_properties()?;
+
+    let indent = properties
+        .first()
+        .map(|p| {
+            let start = line_start(text, p.start);
+            text[start..p.start].to_string()
+        })
+        .filter(|i| !i.is_empty())
+        .unwrap_or_else(|| "    ".to_string());
+
+    let mut removed: Vec<(usize, usize)> = properties
+        .iter()
+        .filter(|p| edits.iter().any(|(key, _)| key == &p.key))
+        .map(|p| (line_start(text, p.start), line_end(text, p.end)))
+        .collect();
+    removed.sort();
+
+    let mut body = String::new();
+    let mut pos = 0;
+    for (start, end) in removed {
+        body.push_str(&text[pos..start]);
+        pos = end;
+    }
+    body.push_str(&text[pos..closing]);
+
+    // make sure the last remaining property is followed by a comma
+    let trimmed_len = body.trim_end().len();
+    let last_char = text_last_code_char(&body[..trimmed_len]);
+    let mut result = body[..trimmed_len].to_string();
+    if !matches!(last_char, Some(',') | Some('{') | None) {
+        result.push(',');
+    }
+
+    for (key, value) in edits.iter() {
+        if let Some(value) = value {
+            result.push_str(&format!(
+                "\n{}\"{}\": {},",
+                indent,
+                key,
+                indent_value(value, indent.as_str())
+            ));
+        }
+    }
+
+    result.push('\n');
+    result.push_str(&text[closing..]);
+
+    Ok(result)
+}
+
+// Last char that is not part of a trailing line comment
+fn text_last_code_char(text: &str) -> Option<char> {
+    let last_line = text.lines().last()?;
+    match last_line.find("//") {
+        Some(idx) if !last_line[..idx].contains('"') => {
+            let before = last_line[..idx].trim_end();
+            if before.is_empty() {
+                // whole line is a comment, look at the previous lines
+                let prefix = &text[..text.len() - last_line.len()];
+                text_last_code_char(prefix.trim_end())
+            } else {
+                before.chars().last()
+            }
+        }
+        _ => text.chars().last(),
+    }
+}
@@ -0,0 +1,72 @@
+use super::upgrade_config::*;
+
+#[test]
+fn test_upgrade_up_to_date() {
+    let config = r#"{
+    "image": "ubuntu",
+    "forwardPorts": [8080],
+}"#;
+
+    assert!(upgrade(config).unwrap().is_none());
+}
+
+#[test]
+fn test_upgrade_legacy_fields() {
+    let config = r#"{
+    // the image
+    "image": "ubuntu",
+    "extensions": ["rust-lang.rust"],
+    "settings": {
+        "editor.tabSize": 4,
+    },
+    /* deprecated */
+    devPort: 8000,
+    "appPort": [3000, 3001],
+    "forwardPorts": [3000] // keep me
+}"#;
+
+    let (upgraded, changes) = upgrade(config).unwrap().unwrap();
+    assert_eq!(changes.len(), 4);
+
+    assert!(upgraded.contains("// the image"));
+    assert!(upgraded.contains("/* deprecated */"));
+    assert!(!upgraded.contains("devPort"));
+    assert!(!upgraded.contains("appPort"));
+
+    let value: serde_json::Value = json5::from_str(&upgraded).unwrap();
+    assert_eq!(value["image"], "ubuntu");
+    assert_eq!(
+        value["customizations"]["vscode"]["extensions"],
+        serde_json::json!(["rust-lang.rust"])
+    );
+    assert_eq!(
+        value["customizations"]["vscode"]["settings"]["editor.tabSize"],
+        4
+    );
+    assert_eq!(value["forwardPorts"], serde_json::json!([3000, 3001]));
+    assert!(value.get("extensions").is_none());
+    assert!(value.get("settings").is_none());
+}
+
+#[test]
+fn test_upgrade_merges_customizations() {
+    let config = r#"{
+    "image": "ubuntu",
+    "customizations": { "vscode": { "extensions": ["a"] } },
+    "extensions": ["a", "b"],
+}"#;
+
+    let (upgraded, _) = upgrade(config).unwrap().unwrap();
+    let value: serde_json::Value = json5::from_str(&upgraded).unwrap();
+    assert_eq!(
+        value["customizations"]["vscode"]["extensions"],
+        serde_json::json!(["a", "b"])
+    );
+}
+
+#[test]
+fn test_upgrade_keeps_string_app_port() {
+    let config = r#"{ "image": "ubuntu", "appPort": "8000:8080" }"#;
+
+    assert!(upgrade(config).unwrap().is_none());
+}
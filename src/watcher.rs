@@ -0,0 +1,60 @@
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Polls a set of files and reports the ones whose contents changed since the last check
+pub struct ConfigWatcher {
+    hashes: HashMap<PathBuf, Option<String>>,
+    interval: Duration,
+}
+
+impl ConfigWatcher {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let hashes = files
+            .into_iter()
+            .map(|f| {
+                let hash = Self::hash_file(&f);
+                (f, hash)
+            })
+            .collect();
+
+        ConfigWatcher {
+            hashes,
+            interval: Duration::from_secs(2),
+        }
+    }
+
+    fn hash_file(path: &Path) -> Option<String> {
+        let contents = std::fs::read(path).ok()?;
+        let mut hasher = Sha1::new();
+        hasher.input(&contents);
+        Some(hasher.result_str())
+    }
+
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+
+        for (path, hash) in self.hashes.iter_mut() {
+            let new_hash = Self::hash_file(path);
+            if new_hash != *hash {
+                *hash = new_hash;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+
+    pub async fn wait_for_change(&mut self) -> Vec<PathBuf> {
+        loop {
+            tokio::time::delay_for(self.interval).await;
+
+            let changed = self.changed();
+            if !changed.is_empty() {
+                return changed;
+            }
+        }
+    }
+}
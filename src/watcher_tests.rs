@@ -0,0 +1,18 @@
+use super::watcher::*;
+
+#[test]
+fn test_changed() {
+    let mut path = std::env::temp_dir();
+    path.push("devcontainers-rs-watcher-test.json");
+    std::fs::write(&path, "{}").unwrap();
+
+    let mut watcher = ConfigWatcher::new(vec![path.clone()]);
+    assert!(watcher.changed().is_empty());
+
+    std::fs::write(&path, "{ image: \"myimage\" }").unwrap();
+    assert_eq!(watcher.changed(), vec![path.clone()]);
+    assert!(watcher.changed().is_empty());
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(watcher.changed(), vec![path]);
+}
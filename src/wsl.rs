@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+// Socket exposed by Docker Desktop to the WSL distros when the integration is enabled
+const DOCKER_DESKTOP_SOCKETS: &[&str] = &[
+    "/mnt/wsl/docker-desktop/shared-sockets/guest-services/docker.sock",
+    "/mnt/wsl/shared-docker/docker.sock",
+];
+
+// The paths a daemon understands as bind mount sources: the ones of its linux machine (a WSL
+// distro included), or Windows ones for Docker Desktop on Windows
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PathStyle {
+    Linux,
+    Windows,
+}
+
+impl PathStyle {
+    pub fn detect() -> Self {
+        if cfg!(windows) {
+            PathStyle::Windows
+        } else {
+            PathStyle::Linux
+        }
+    }
+}
+
+pub fn is_wsl() -> bool {
+    if std::env::var("WSL_DISTRO_NAME").is_ok() {
+        return true;
+    }
+
+    std::fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|release| release.to_lowercase().contains("microsoft"))
+        .unwrap_or(false)
+}
+
+// C:\Users\me\project -> /mnt/c/Users/me/project
+pub fn windows_to_wsl_path(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    let drive = chars.next()?;
+
+    if !drive.is_ascii_alphabetic() || chars.next()? != ':' {
+        return None;
+    }
+
+    let rest = chars.as_str().replace('\\', "/");
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+
+    Some(format!("/mnt/{}{}", drive.to_ascii_lowercase(), rest))
+}
+
+// /mnt/c/Users/me/project -> C:\Users\me\project
+pub fn wsl_to_windows_path(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let (drive, rest) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, ""),
+    };
+
+    let mut chars = drive.chars();
+    let letter = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    if chars.next().is_some() {
+        return None;
+    }
+
+    Some(format!(
+        "{}:{}",
+        letter.to_ascii_uppercase(),
+        if rest.is_empty() {
+            "\\".to_string()
+        } else {
+            rest.replace('/', "\\")
+        }
+    ))
+}
+
+// \\wsl$\Ubuntu\home\me or \\wsl.localhost\Ubuntu\home\me -> ("Ubuntu", "/home/me")
+pub fn parse_wsl_share(path: &str) -> Option<(String, String)> {
+    let path = path.replace('\\', "/");
+    let rest = path
+        .strip_prefix("//wsl$/")
+        .or_else(|| path.strip_prefix("//wsl.localhost/"))?;
+
+    let (distro, rest) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+    if distro.is_empty() {
+        return None;
+    }
+
+    Some((distro.to_string(), rest.to_string()))
+}
+
+// /home/me/project of the `distro` -> \\wsl$\Ubuntu\home\me\project
+pub fn wsl_share_path(distro: &str, path: &str) -> String {
+    format!("\\\\wsl$\\{}{}", distro, path.replace('/', "\\"))
+}
+
+// A bind mount source in the `style` of the daemon. `distro` is the WSL distro we run in, for
+// its own paths, which Windows only sees through the \\wsl$ share
+pub fn to_style(path: &str, style: PathStyle, distro: Option<&str>) -> String {
+    let translated = match style {
+        PathStyle::Linux => {
+            windows_to_wsl_path(path).or_else(|| parse_wsl_share(path).map(|(_, path)| path))
+        }
+        PathStyle::Windows if path.starts_with('/') => {
+            wsl_to_windows_path(path).or_else(|| distro.map(|distro| wsl_share_path(distro, path)))
+        }
+        PathStyle::Windows => None,
+    };
+
+    translated.unwrap_or_else(|| path.to_string())
+}
+
+// Paths of this machine given in the Windows form. Only translated when running inside WSL,
+// everything else is returned untouched
+pub fn translate_path(path: &str) -> String {
+    if !is_wsl() {
+        return path.to_string();
+    }
+
+    to_style(path, PathStyle::Linux, None)
+}
+
+// The default socket is preferred, the Docker Desktop one is only used when the distro
+// does not run its own daemon
+pub fn docker_desktop_socket() -> Option<PathBuf> {
+    if !is_wsl() || PathBuf::from("/var/run/docker.sock").exists() {
+        return None;
+    }
+
+    DOCKER_DESKTOP_SOCKETS
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.exists())
+}
@@ -0,0 +1,86 @@
+use super::wsl::*;
+
+#[test]
+fn test_windows_to_wsl_path() {
+    assert_eq!(
+        windows_to_wsl_path("C:\\Users\\me\\project"),
+        Some("/mnt/c/Users/me/project".to_string())
+    );
+    assert_eq!(
+        windows_to_wsl_path("d:/src"),
+        Some("/mnt/d/src".to_string())
+    );
+    assert_eq!(windows_to_wsl_path("C:"), Some("/mnt/c".to_string()));
+
+    assert_eq!(windows_to_wsl_path("/home/me/project"), None);
+    assert_eq!(windows_to_wsl_path("C:project"), None);
+    assert_eq!(windows_to_wsl_path("relative/path"), None);
+}
+
+#[test]
+fn test_wsl_to_windows_path() {
+    assert_eq!(
+        wsl_to_windows_path("/mnt/c/Users/me/project"),
+        Some("C:\\Users\\me\\project".to_string())
+    );
+    assert_eq!(wsl_to_windows_path("/mnt/d"), Some("D:\\".to_string()));
+
+    assert_eq!(wsl_to_windows_path("/mnt/wsl/docker"), None);
+    assert_eq!(wsl_to_windows_path("/home/me/project"), None);
+}
+
+#[test]
+fn test_parse_wsl_share() {
+    assert_eq!(
+        parse_wsl_share("\\\\wsl$\\Ubuntu\\home\\me\\project"),
+        Some(("Ubuntu".to_string(), "/home/me/project".to_string()))
+    );
+    assert_eq!(
+        parse_wsl_share("//wsl.localhost/Debian"),
+        Some(("Debian".to_string(), "/".to_string()))
+    );
+
+    assert_eq!(parse_wsl_share("\\\\server\\share\\project"), None);
+    assert_eq!(parse_wsl_share("C:\\Users\\me"), None);
+}
+
+#[test]
+fn test_to_style() {
+    assert_eq!(
+        to_style("C:\\Users\\me\\project", PathStyle::Linux, None),
+        "/mnt/c/Users/me/project"
+    );
+    assert_eq!(
+        to_style(
+            "\\\\wsl$\\Ubuntu\\home\\me\\project",
+            PathStyle::Linux,
+            None
+        ),
+        "/home/me/project"
+    );
+    assert_eq!(
+        to_style("/home/me/project", PathStyle::Linux, Some("Ubuntu")),
+        "/home/me/project"
+    );
+
+    assert_eq!(
+        to_style(
+            "/mnt/c/Users/me/project",
+            PathStyle::Windows,
+            Some("Ubuntu")
+        ),
+        "C:\\Users\\me\\project"
+    );
+    assert_eq!(
+        to_style("/home/me/project", PathStyle::Windows, Some("Ubuntu")),
+        "\\\\wsl$\\Ubuntu\\home\\me\\project"
+    );
+    assert_eq!(
+        to_style("/home/me/project", PathStyle::Windows, None),
+        "/home/me/project"
+    );
+    assert_eq!(
+        to_style("C:\\Users\\me\\project", PathStyle::Windows, None),
+        "C:\\Users\\me\\project"
+    );
+}